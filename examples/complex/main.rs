@@ -44,7 +44,5 @@ fn main() {
         }
     };
 
-    app.load();
-
     app.application_loop();
 }
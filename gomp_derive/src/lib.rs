@@ -0,0 +1,209 @@
+//! Derive macros for gomp. Use them through `gomp` itself
+//! (`gomp::ecs::component::reflect::Reflect`), not this crate.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+/// Generates reflection data (`gomp::ecs::component::reflect::Reflect`) for a component with named
+/// fields.
+///
+/// The component is registered as its name in snake case (`PlayerController` is
+/// `player_controller`), unless it has `#[reflect(name = "...")]`. Every field has to implement
+/// `ReflectValue`, apart from those marked `#[reflect(skip)]`.
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand_reflect(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_reflect(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(ident, "Reflect can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(ident, "Reflect can only be derived for structs")),
+    };
+
+    let type_name = match find_name(&input.attrs)? {
+        Some(name) => name,
+        None => to_snake_case(&ident.to_string()),
+    };
+
+    let mut idents = vec![];
+    let mut names = vec![];
+    let mut types = vec![];
+
+    for field in fields {
+        if is_skipped(&field.attrs)? {
+            continue;
+        }
+
+        let field_ident = field.ident.clone().expect("named fields have names");
+
+        names.push(field_ident.to_string().trim_start_matches("r#").to_owned());
+        idents.push(field_ident);
+        types.push(field.ty.clone());
+    }
+
+    let reflect = quote!(::gomp::ecs::component::reflect);
+    let error = quote!(::gomp::error::Error);
+
+    Ok(quote! {
+        impl #impl_generics #reflect::Reflect for #ident #type_generics #where_clause {
+            fn type_name() -> &'static str where Self: Sized {
+                #type_name
+            }
+
+            fn fields() -> ::std::vec::Vec<#reflect::Field> where Self: Sized {
+                vec![
+                    #( #reflect::Field::new(#names, <#types as #reflect::ReflectValue>::field_type()), )*
+                ]
+            }
+
+            fn get_field(&self, name: &str) -> ::std::option::Option<#reflect::Value> {
+                match name {
+                    #( #names => ::std::option::Option::Some(#reflect::ReflectValue::to_value(&self.#idents)), )*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn set_field(&mut self, name: &str, value: &#reflect::Value) -> ::std::result::Result<(), #error> {
+                match name {
+                    #(
+                        #names => {
+                            self.#idents = <#types as #reflect::ReflectValue>::from_value(value).ok_or_else(|| {
+                                #error::InvalidField(format!(
+                                    "{}.{} can't be set to {:?}",
+                                    #type_name,
+                                    #names,
+                                    value,
+                                ))
+                            })?;
+
+                            ::std::result::Result::Ok(())
+                        },
+                    )*
+                    _ => ::std::result::Result::Err(#error::InvalidField(format!("{} has no field {}", #type_name, name))),
+                }
+            }
+        }
+    })
+}
+
+/// Finds `#[reflect(name = "...")]`.
+fn find_name(attrs: &[syn::Attribute]) -> syn::Result<Option<String>> {
+    let mut name = None;
+
+    for meta in reflect_metas(attrs)? {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("name") => match pair.lit {
+                Lit::Str(lit) => name = Some(lit.value()),
+                lit => return Err(syn::Error::new_spanned(lit, "expected a string")),
+            },
+            meta => return Err(syn::Error::new_spanned(meta, "expected `name = \"...\"`")),
+        }
+    }
+
+    Ok(name)
+}
+
+/// Looks for `#[reflect(skip)]`.
+fn is_skipped(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let mut skip = false;
+
+    for meta in reflect_metas(attrs)? {
+        match meta {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => skip = true,
+            meta => return Err(syn::Error::new_spanned(meta, "expected `skip`")),
+        }
+    }
+
+    Ok(skip)
+}
+
+/// Gets everything inside `#[reflect(...)]` attributes.
+fn reflect_metas(attrs: &[syn::Attribute]) -> syn::Result<Vec<NestedMeta>> {
+    let mut metas = vec![];
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("reflect")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            meta => return Err(syn::Error::new_spanned(meta, "expected `#[reflect(...)]`")),
+        }
+    }
+
+    Ok(metas)
+}
+
+/// `PlayerController` to `player_controller`.
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_snake_cased() {
+        assert_eq!(to_snake_case("Transform"), "transform");
+        assert_eq!(to_snake_case("PlayerController"), "player_controller");
+        assert_eq!(to_snake_case("already_snake"), "already_snake");
+    }
+
+    #[test]
+    fn skipped_fields_are_left_out() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[reflect(name = "hp")]
+            struct Health {
+                #[reflect(skip)]
+                parent: Weak<Entity>,
+
+                current: f32,
+            }
+        };
+
+        let output = expand_reflect(&input).unwrap().to_string();
+
+        assert!(output.contains("\"hp\""));
+        assert!(output.contains("\"current\""));
+        assert!(!output.contains("\"parent\""));
+    }
+
+    #[test]
+    fn only_structs_with_named_fields_can_be_reflected() {
+        let tuple: DeriveInput = syn::parse_quote!(struct Health(f32););
+        let unknown: DeriveInput = syn::parse_quote! {
+            struct Health {
+                #[reflect(hide)]
+                current: f32,
+            }
+        };
+
+        assert!(expand_reflect(&tuple).is_err());
+        assert!(expand_reflect(&unknown).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+//! Building applications out of plugins.
+//!
+//! An `AppBuilder` collects everything plugins register (systems, resources, event types, render
+//! passes, window event handlers and setup hooks), then `build` opens the window and puts it all
+//! together into an `Application`.
+
+use super::Application;
+use super::{AppExit, Seed};
+use super::config::AppConfig;
+use super::events::{Events, update_events};
+use super::plugin::Plugin;
+use super::resources::Resources;
+use super::timestep::FixedTimestep;
+use crate::asset::{AssetLoader, AssetServer};
+use crate::ecs::component::{Component, reflect::Reflect, registry};
+use crate::ecs::scene::Scene;
+use crate::ecs::scene::manager::{SceneChange, SceneManager};
+use crate::renderer::Renderer;
+use crate::renderer::pass::CustomPass;
+use crate::state::State;
+use crate::time::Time;
+
+use winit::{
+    event::WindowEvent,
+    event_loop::EventLoop,
+    window::{Window, WindowBuilder},
+    dpi::LogicalSize,
+};
+
+use log::{debug, warn};
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// When a system runs in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Once, before the first frame (after components in the initial scene have started).
+    Startup,
+
+    /// Once at the start of every frame, after events have moved along. Input is handled here.
+    First,
+
+    /// Once per simulation step, at the fixed tick rate. Game logic goes here.
+    FixedUpdate,
+
+    /// Once per frame, after the simulation steps.
+    Update,
+
+    /// Once per frame, after `Update`. The renderer draws the frame here.
+    Render,
+
+    /// Once at the end of every frame.
+    Last,
+
+    /// Whenever something asks to exit. Systems can cancel the `CloseRequest` resource to keep
+    /// running.
+    BeforeExit,
+
+    /// Once, when the application shuts down (before components are stopped).
+    Shutdown,
+}
+
+/// A function run every frame (or simulation step), with access to resources and the active
+/// scene.
+pub type System = Box<dyn FnMut(&mut Resources, &mut Scene)>;
+
+/// A `Stage::Render` system, which is also lent the paused scenes that show under the active one
+/// (see `ecs::scene::manager`), bottom first.
+pub type RenderSystem = Box<dyn FnMut(&mut Resources, &[Scene], &mut Scene)>;
+
+/// A function given every window event. Returns true if it handled the event, which stops it going
+/// any further.
+pub type WindowEventHandler = Box<dyn FnMut(&mut Resources, &WindowEvent) -> bool>;
+
+/// A function run once the window exists, before the application loop starts. Headless
+/// applications don't have a window.
+pub type Setup = Box<dyn FnOnce(&mut Resources, Option<&Window>) -> Result<(), Box<dyn std::error::Error>>>;
+
+/// Every system, by the stage it runs in.
+#[derive(Default)]
+pub(crate) struct Schedule {
+    systems: HashMap<Stage, Vec<System>>,
+
+    /// Every `Stage::Render` system, including those added with `add_system`.
+    render_systems: Vec<RenderSystem>,
+}
+
+impl Schedule {
+    /// Runs every system in a stage, in the order they were added.
+    pub(crate) fn run(&mut self, stage: Stage, resources: &mut Resources, scene: &mut Scene) {
+        if let Some(systems) = self.systems.get_mut(&stage) {
+            for system in systems {
+                system(resources, scene);
+            }
+        }
+    }
+
+    /// Runs every `Stage::Render` system, in the order they were added, lending them the scenes
+    /// under the active one.
+    pub(crate) fn run_render(&mut self, resources: &mut Resources, layers: &[Scene], scene: &mut Scene) {
+        for system in &mut self.render_systems {
+            system(resources, layers, scene);
+        }
+    }
+}
+
+/// Collects plugins, and builds an `Application` out of them.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct AppBuilder {
+    config: AppConfig,
+    resources: Resources,
+
+    /// The names of every plugin added so far.
+    plugins: HashSet<String>,
+
+    #[derivative(Debug = "ignore")]
+    schedule: Schedule,
+    #[derivative(Debug = "ignore")]
+    event_updaters: Vec<fn(&mut Resources)>,
+    #[derivative(Debug = "ignore")]
+    window_event_handlers: Vec<WindowEventHandler>,
+    #[derivative(Debug = "ignore")]
+    setups: Vec<Setup>,
+    #[derivative(Debug = "ignore")]
+    render_passes: Vec<Box<dyn CustomPass>>,
+}
+
+impl AppBuilder {
+    /// Creates a builder with no plugins. You probably want to add `DefaultPlugins`.
+    pub fn new(config: AppConfig) -> Self {
+        let mut builder = Self {
+            config,
+            resources: Resources::new(),
+            plugins: HashSet::new(),
+            schedule: Schedule::default(),
+            event_updaters: vec![],
+            window_event_handlers: vec![],
+            setups: vec![],
+            render_passes: vec![],
+        };
+
+        builder.add_event::<AppExit>();
+        builder.add_event::<SceneChange>();
+        builder
+    }
+
+    /// Gets the config the application is being built with.
+    pub fn get_config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    /// Gets the resources added so far.
+    pub fn get_resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    /// Gets a mutable reference to the resources added so far.
+    pub fn get_mut_resources(&mut self) -> &mut Resources {
+        &mut self.resources
+    }
+
+    /// Adds a plugin. Plugins that have already been added (by name) are skipped.
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        self.add_dyn_plugin(&plugin)
+    }
+
+    /// Adds a plugin that has already been boxed up (or borrowed).
+    pub fn add_dyn_plugin(&mut self, plugin: &dyn Plugin) -> &mut Self {
+        if !self.plugins.insert(plugin.name().to_string()) {
+            warn!("Plugin {} was added twice, ignoring it the second time", plugin.name());
+
+            return self;
+        }
+
+        debug!("Building plugin {}", plugin.name());
+        plugin.build(self);
+
+        self
+    }
+
+    /// Has a plugin with this name been added.
+    pub fn has_plugin(&self, name: &str) -> bool {
+        self.plugins.contains(name)
+    }
+
+    /// Adds a system to a stage. Systems in the same stage run in the order they were added.
+    pub fn add_system<F>(&mut self, stage: Stage, mut system: F) -> &mut Self
+    where
+        F: FnMut(&mut Resources, &mut Scene) + 'static,
+    {
+        match stage {
+            Stage::Render => self.add_render_system(move |resources, _, scene| system(resources, scene)),
+            _ => {
+                self.schedule.systems.entry(stage).or_default().push(Box::new(system));
+                self
+            },
+        }
+    }
+
+    /// Adds a `Stage::Render` system that is also given the paused scenes that show under the
+    /// active one.
+    pub fn add_render_system<F>(&mut self, system: F) -> &mut Self
+    where
+        F: FnMut(&mut Resources, &[Scene], &mut Scene) + 'static,
+    {
+        self.schedule.render_systems.push(Box::new(system));
+        self
+    }
+
+    /// Adds a resource, replacing any of the same type.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) -> &mut Self {
+        self.resources.insert(resource);
+        self
+    }
+
+    /// Registers a component type, so scenes can use it by name (see `ecs::component::registry`).
+    pub fn register_component<T: Component + Reflect + 'static>(&mut self) -> &mut Self {
+        registry::register_component::<T>();
+
+        self
+    }
+
+    /// Adds a loader for a type of asset to the `AssetServer` (see `asset::loader`).
+    pub fn add_asset_loader<L: AssetLoader>(&mut self, loader: L) -> &mut Self {
+        let root = self.config.asset_directory.clone();
+        self.resources.get_or_insert_with(|| AssetServer::new().with_root(root)).add_loader(loader);
+
+        self
+    }
+
+    /// Registers an event type, adding an `Events<T>` resource that moves along every frame.
+    /// Registering the same type twice does nothing.
+    pub fn add_event<T: 'static>(&mut self) -> &mut Self {
+        if !self.resources.contains::<Events<T>>() {
+            self.resources.insert(Events::<T>::new());
+            self.event_updaters.push(update_events::<T>);
+        }
+
+        self
+    }
+
+    /// Adds a render pass, drawn after the scene every frame. Needs a renderer (`RenderPlugin`).
+    pub fn add_render_pass<P: CustomPass + 'static>(&mut self, pass: P) -> &mut Self {
+        self.render_passes.push(Box::new(pass));
+        self
+    }
+
+    /// Adds a window event handler. Handlers are called in the order they were added, until one
+    /// handles the event.
+    pub fn add_window_event_handler<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: FnMut(&mut Resources, &WindowEvent) -> bool + 'static,
+    {
+        self.window_event_handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Adds a function that runs once the window has been opened. This is where things that need a
+    /// window (like the renderer) get made. There is no window when running headless.
+    pub fn add_setup<F>(&mut self, setup: F) -> &mut Self
+    where
+        F: FnOnce(&mut Resources, Option<&Window>) -> Result<(), Box<dyn std::error::Error>> + 'static,
+    {
+        self.setups.push(Box::new(setup));
+        self
+    }
+
+    /// Opens the window (unless running headless), runs every setup function and creates the
+    /// application.
+    pub fn build(self) -> Result<Application, Box<dyn std::error::Error>> {
+        let Self {
+            config,
+            mut resources,
+            schedule,
+            event_updaters,
+            window_event_handlers,
+            setups,
+            render_passes,
+            ..
+        } = self;
+
+        let (event_loop, window) = if config.headless {
+            debug!("Running headless, not opening a window");
+
+            (None, None)
+        } else {
+            let event_loop = EventLoop::new();
+            let window = WindowBuilder::new().build(&event_loop)?;
+
+            window.set_resizable(config.resizable);
+            window.set_title(&config.title);
+            window.set_min_inner_size(Some(LogicalSize::new(config.width, config.height)));
+            window.set_always_on_top(config.top);
+            window.set_decorations(config.décor);
+
+            (Some(event_loop), Some(window))
+        };
+
+        // The loop needs a clock, even if the time plugin was swapped for one that didn't make one
+        resources.get_or_insert_with(|| Time::new(Duration::from_secs_f64(1.0 / config.tick_rate.max(1) as f64)));
+        resources.get_or_insert_with(|| FixedTimestep::new(config.tick_rate, config.max_catch_up_steps));
+        resources.get_or_insert_with(|| Seed(config.seed.unwrap_or_else(seed_from_clock)));
+
+        for setup in setups {
+            setup(&mut resources, window.as_ref())?;
+        }
+
+        if !render_passes.is_empty() {
+            match resources.get_mut::<Renderer>() {
+                Some(renderer) => render_passes.into_iter().for_each(|pass| renderer.add_pass(pass)),
+                None => warn!("Render passes were added, but there is no renderer to draw them"),
+            }
+        }
+
+        Ok(Application {
+            event_loop,
+            window,
+            state: State::new(),
+            scenes: SceneManager::new(Scene::new("main")),
+            resources,
+            schedule,
+            event_updaters,
+            window_event_handlers,
+            last_frame: Instant::now(),
+            started: false,
+            stopped: false,
+            exit_code: None,
+            config,
+        })
+    }
+}
+
+/// Picks a seed from the clock, for when the config doesn't give one.
+fn seed_from_clock() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_nanos() as u64).unwrap_or_default()
+}
@@ -1,3 +1,7 @@
+use winit::event::VirtualKeyCode;
+
+use std::path::PathBuf;
+
 /// Configuration struct that is used to build
 /// the application and renderer.
 #[derive(Debug)]
@@ -24,6 +28,49 @@ pub struct AppConfig {
 
     /// Should the window be visibile
     pub visible: bool,
+
+    /// The key that saves a screenshot, if any.
+    pub screenshot_key: Option<VirtualKeyCode>,
+
+    /// The directory screenshots taken with the screenshot key are saved to.
+    pub screenshot_directory: PathBuf,
+
+    /// How many simulation steps to run per second. The simulation always steps by the same
+    /// amount of time, no matter the frame rate.
+    pub tick_rate: u32,
+
+    /// The most simulation steps to run in a single frame when catching up after a slow frame.
+    /// Any time left over after this is dropped, so one long hitch doesn't spiral into many.
+    pub max_catch_up_steps: u32,
+
+    /// Run without a window or renderer (for servers and tests). The loop then runs at the tick
+    /// rate, or can be stepped manually with `Application::step`.
+    pub headless: bool,
+
+    /// The input action that closes the application (bound to escape unless rebound), if any.
+    pub exit_action: Option<String>,
+
+    /// The file input bindings are loaded from at startup, if it exists. See `input::action`.
+    pub input_bindings: Option<PathBuf>,
+
+    /// The seed for the `Seed` resource. Picked from the clock if not set.
+    pub seed: Option<u64>,
+
+    /// The file input is recorded to, saved on shutdown. See `input::replay`.
+    pub record_input: Option<PathBuf>,
+
+    /// The file input is played back from, starting with the first frame. See `input::replay`.
+    pub replay_input: Option<PathBuf>,
+
+    /// The directory assets are loaded from. See `asset`.
+    pub asset_directory: PathBuf,
+
+    /// Packs assets are loaded from too, if they're not in the asset directory. Later packs win
+    /// over earlier ones, so patches go last. See `asset::pack`.
+    pub asset_packs: Vec<PathBuf>,
+
+    /// Are assets, scenes and shaders reloaded when their files change. On in debug builds.
+    pub hot_reload: bool,
 }
 
 impl AppConfig {
@@ -38,6 +85,19 @@ impl AppConfig {
             top: false,
             décor: true,
             visible: true,
+            screenshot_key: Some(VirtualKeyCode::F12),
+            screenshot_directory: PathBuf::from("screenshots"),
+            tick_rate: 60,
+            max_catch_up_steps: 5,
+            headless: false,
+            exit_action: Some("exit".to_owned()),
+            input_bindings: None,
+            seed: None,
+            record_input: None,
+            replay_input: None,
+            asset_directory: PathBuf::from("assets"),
+            asset_packs: Vec::new(),
+            hot_reload: cfg!(debug_assertions),
         }
     }
 
@@ -61,4 +121,49 @@ impl AppConfig {
 
     /// Sets if the window should be visible
     pub fn with_visibiliy(self, visible: bool) -> Self { Self { visible, .. self } }
+
+    /// Sets the key that saves a screenshot (`None` to disable it)
+    pub fn with_screenshot_key(self, screenshot_key: Option<VirtualKeyCode>) -> Self { Self { screenshot_key, .. self } }
+
+    /// Sets the directory screenshots are saved to
+    pub fn with_screenshot_directory(self, directory: &str) -> Self { Self { screenshot_directory: PathBuf::from(directory), .. self } }
+
+    /// Sets how many simulation steps run per second
+    pub fn with_tick_rate(self, tick_rate: u32) -> Self { Self { tick_rate, .. self } }
+
+    /// Sets the most simulation steps run in a single frame
+    pub fn with_max_catch_up_steps(self, max_catch_up_steps: u32) -> Self { Self { max_catch_up_steps, .. self } }
+
+    /// Sets if the application runs without a window or renderer
+    pub fn with_headless(self, headless: bool) -> Self { Self { headless, .. self } }
+
+    /// Sets the input action that closes the application (`None` to disable it)
+    pub fn with_exit_action(self, exit_action: Option<&str>) -> Self { Self { exit_action: exit_action.map(str::to_owned), .. self } }
+
+    /// Sets the file input bindings are loaded from
+    pub fn with_input_bindings(self, path: &str) -> Self { Self { input_bindings: Some(PathBuf::from(path)), .. self } }
+
+    /// Sets the seed for the `Seed` resource
+    pub fn with_seed(self, seed: u64) -> Self { Self { seed: Some(seed), .. self } }
+
+    /// Sets the file input is recorded to
+    pub fn with_input_recording(self, path: &str) -> Self { Self { record_input: Some(PathBuf::from(path)), .. self } }
+
+    /// Sets the file input is played back from
+    pub fn with_input_replay(self, path: &str) -> Self { Self { replay_input: Some(PathBuf::from(path)), .. self } }
+
+    /// Sets the directory assets are loaded from
+    pub fn with_asset_directory(self, directory: &str) -> Self { Self { asset_directory: PathBuf::from(directory), .. self } }
+
+    /// Adds a pack assets are loaded from
+    pub fn with_asset_pack(mut self, path: &str) -> Self { self.asset_packs.push(PathBuf::from(path)); self }
+
+    /// Sets if assets, scenes and shaders are reloaded when their files change
+    pub fn with_hot_reload(self, hot_reload: bool) -> Self { Self { hot_reload, .. self } }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,94 @@
+//! Events: messages sent between systems (and plugins).
+//!
+//! Every event type registered with `AppBuilder::add_event` gets an `Events<T>` resource. Events
+//! are double buffered: anything sent is readable for the rest of the frame it was sent in and all
+//! of the next one, so a system never misses an event just because it runs before the sender.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! struct GoalScored { by: u32 }
+//!
+//! app.add_event::<GoalScored>();
+//!
+//! // In one system
+//! resources.get_mut::<Events<GoalScored>>().unwrap().send(GoalScored { by: 1 });
+//!
+//! // In another
+//! for goal in resources.get::<Events<GoalScored>>().unwrap().iter() {
+//!     info!("Player {} scored", goal.by);
+//! }
+//! ```
+
+use super::resources::Resources;
+
+/// A queue of events of a single type.
+#[derive(Debug)]
+pub struct Events<T> {
+    /// Events sent last frame.
+    previous: Vec<T>,
+
+    /// Events sent this frame.
+    current: Vec<T>,
+}
+
+impl<T> Events<T> {
+    /// Creates an empty event queue.
+    pub fn new() -> Self {
+        Self {
+            previous: vec![],
+            current: vec![],
+        }
+    }
+
+    /// Sends an event.
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Iterates over every event sent this frame or last frame, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    /// Takes every event out of the queue, oldest first.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.previous.drain(..).chain(self.current.drain(..))
+    }
+
+    /// How many events are readable.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    /// Are there no readable events.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Throws away every event.
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    /// Moves onto the next frame. Events sent last frame are dropped, and this frame's become last
+    /// frame's.
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Updates the event queue of a type, if there is one. One of these is kept for each registered
+/// event type, and called at the start of every frame.
+pub(crate) fn update_events<T: 'static>(resources: &mut Resources) {
+    if let Some(events) = resources.get_mut::<Events<T>>() {
+        events.update();
+    }
+}
@@ -0,0 +1,138 @@
+//! Starting up and shutting down.
+//!
+//! An application goes through a few stages over its life:
+//!
+//! 1. **Startup**, just before the first frame. Every scene on the scene stack enters it (its
+//!    components are started, then its `on_enter` hook is called), then `Stage::Startup` systems
+//!    run.
+//! 2. **Before exit**, whenever something asks to exit (an `AppExit` event, `Application::exit`,
+//!    closing the window or pressing escape). `Stage::BeforeExit` systems run with a
+//!    `CloseRequest` resource, which they can cancel to keep the application running (to ask
+//!    "are you sure?", say).
+//! 3. **Shutdown**, once an exit wasn't cancelled. `Stage::Shutdown` systems run, then every scene
+//!    on the scene stack leaves it, top first (its `on_exit` hook is called, then its components
+//!    are stopped). Dropping an application that was started also shuts it down.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! app.add_system(Stage::BeforeExit, |resources, _| {
+//!     if resources.get::<UnsavedChanges>().is_some() {
+//!         resources.get_mut::<CloseRequest>().unwrap().cancel();
+//!     }
+//! });
+//! ```
+
+use super::{Application, AppExit};
+use super::builder::Stage;
+use super::events::Events;
+
+use log::{debug, info};
+
+/// A request to close the application, there only while `Stage::BeforeExit` systems run.
+#[derive(Debug)]
+pub struct CloseRequest {
+    code: i32,
+    cancelled: bool,
+}
+
+impl CloseRequest {
+    /// Gets the exit code the application will exit with.
+    pub fn get_code(&self) -> i32 {
+        self.code
+    }
+
+    /// Keeps the application running.
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+    }
+
+    /// Has the request been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+}
+
+impl Application {
+    /// Asks the application to exit with a code, at the end of the frame. This can be cancelled
+    /// by a `Stage::BeforeExit` system. Systems can send an `AppExit` event instead.
+    pub fn exit(&mut self, code: i32) {
+        if let Some(events) = self.resources.get_mut::<Events<AppExit>>() {
+            events.send(AppExit(code));
+        }
+    }
+
+    /// Has the application been started.
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// Has the application been shut down.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// The code the application exited with, once it has.
+    pub fn get_exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Enters every scene on the scene stack and runs the startup systems. Only happens once.
+    pub(super) fn startup(&mut self) {
+        if self.started {
+            return;
+        }
+
+        debug!("Starting up");
+        self.started = true;
+
+        self.scenes.start(&mut self.resources);
+        self.schedule.run(Stage::Startup, &mut self.resources, self.scenes.get_mut_active());
+    }
+
+    /// Handles any requests to exit. Returns the exit code if nothing cancelled the request.
+    pub(super) fn poll_exit(&mut self) -> Option<i32> {
+        // Take the events, so a cancelled request doesn't come round again next frame
+        let code = self.resources.get_mut::<Events<AppExit>>()?.drain().last()?.0;
+
+        debug!("Exit requested with code {}", code);
+        self.resources.insert(CloseRequest {
+            code,
+            cancelled: false,
+        });
+
+        self.schedule.run(Stage::BeforeExit, &mut self.resources, self.scenes.get_mut_active());
+
+        match self.resources.remove::<CloseRequest>() {
+            Some(request) if request.cancelled => {
+                info!("Exit was cancelled");
+
+                None
+            },
+            Some(request) => Some(request.code),
+            None => Some(code),
+        }
+    }
+
+    /// Runs the shutdown systems, and exits every scene on the scene stack. Only happens once.
+    pub(super) fn shutdown(&mut self, code: i32) {
+        if self.stopped {
+            return;
+        }
+
+        info!("Shutting down with code {}", code);
+        self.stopped = true;
+        self.exit_code = Some(code);
+
+        self.schedule.run(Stage::Shutdown, &mut self.resources, self.scenes.get_mut_active());
+        self.scenes.stop(&mut self.resources);
+    }
+}
+
+impl Drop for Application {
+    fn drop(&mut self) {
+        if self.started {
+            self.shutdown(self.exit_code.unwrap_or(0));
+        }
+    }
+}
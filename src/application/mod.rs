@@ -8,7 +8,7 @@
 //!
 //! # Examples!
 //!
-//! ```
+//! ```ignore
 //! // This is configuration struct that you pass into the application contructor. You can look in
 //! // the modules documentation to get all the values that you can set, and what they do. If needed,
 //! // you can also extract certain values from the application (in reference form), and apply
@@ -20,7 +20,8 @@
 //!     .with_height(300) // Physical size of 300px.
 //!     .with_width(400); // Physical size of 400px.
 //!
-//! // Actually construct the application.
+//! // Actually construct the application. `Application::new` uses the default plugins (time, input
+//! // and rendering). Use `Application::builder` to add your own plugins, or swap the defaults out.
 //! let app = match Application::new(config) {
 //!     Ok(a) => a,
 //!     Err(e) => {
@@ -31,16 +32,25 @@
 //! };
 //!
 //! // Start the application loop. This is not technically required, as you can write your own
-//! // application loop, but in the future "loop hooks" may be added to extend the application loop
-//! // without actually rewritting it yourself.
-//! match app.application_loop() {
-//!     Ok(()) => (),
-//!     Err(e) => {
-//!         error!("Something went mal in the application that cannot be recovered from.");
+//! // application loop, but plugins let you extend the loop without actually rewritting it
+//! // yourself (see the `plugin` module). It hands back the exit code once the application has
+//! // shut down (see the `lifecycle` module).
+//! std::process::exit(app.application_loop());
+//! ```
 //!
-//!         return;
-//!     }
-//! };
+//! # Headless
+//!
+//! Dedicated servers and tests don't want a window, or a GPU. `Application::new_headless` (or
+//! `AppConfig::with_headless`) builds an application without either. Everything else (scenes,
+//! systems, time) works exactly the same. The loop then runs at the tick rate, or you can step it
+//! yourself:
+//!
+//! ```ignore
+//! let mut app = Application::new_headless(AppConfig::new())?;
+//!
+//! // One simulation step per frame
+//! app.step(60);
+//! assert_eq!(app.get_time().unwrap().tick_count(), 60);
 //! ```
 //!
 //! # Panics!
@@ -49,71 +59,158 @@
 //! something panics, chances are it's a bug (e.g. a contributor/author forgetting to add a check,
 //! and then unwraping a value).
 //!
-//! The only thing that could panic is shader compilation. You can see the shader module inside the
-//! renderer module for more information on why this happens. Note that this panic *will not* cause
-//! your application to crash.
+//! That includes shaders: a shader that fails to compile is logged, and the last good version is
+//! kept (see `renderer::reload`).
 
 pub mod config;
+pub mod timestep;
+pub mod resources;
+pub mod events;
+pub mod plugin;
+pub mod builder;
+pub mod lifecycle;
 
-use futures::executor;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::{WindowBuilder, Window},
-    dpi::LogicalSize,
+    window::Window,
 };
 
-use log::{trace, debug, info, error};
+use log::{info, debug};
+
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 use super::renderer::Renderer;
 use super::state::State;
 use super::ecs::scene::Scene;
+use super::ecs::scene::manager::{SceneChange, SceneManager};
+use super::ecs::scene::prefab::PrefabLibrary;
+use super::ecs::scene::reload::SceneReloader;
+use super::ecs::scene::save::{SaveGame, SaveRegistry};
+use super::asset::AssetServer;
+use super::time::Time;
+use super::input::Input;
+use super::input::replay::{InputRecorder, InputRecording, InputReplay, RecordedEvent};
+use builder::{AppBuilder, Schedule, Stage, WindowEventHandler};
+use events::Events;
 use config::AppConfig;
+use plugin::DefaultPlugins;
+use crate::renderer::plugin::RenderPlugin;
+use resources::Resources;
+use timestep::FixedTimestep;
+
+/// Send this event to close the application at the end of the frame, with an exit code. See the
+/// `lifecycle` module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppExit(pub i32);
+
+/// The seed games should seed their random number generators with, so input replays (see
+/// `input::replay`) play out the same. Set by `AppConfig::seed`, or picked from the clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seed(pub u64);
 
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct Application {
-    event_loop: EventLoop<()>,
-    window: Window,
+    /// Taken when the loop starts. Headless applications don't have one.
+    event_loop: Option<EventLoop<()>>,
+    /// Headless applications don't have one of these either.
+    window: Option<Window>,
 
-    renderer: Renderer,
     state: State,
-    active_scene_index: usize,
-    // We will always have scenes in our application, so this is OK.
-    scenes: Vec<Scene>,
+    scenes: SceneManager,
+
+    resources: Resources,
+    #[derivative(Debug = "ignore")]
+    schedule: Schedule,
+    #[derivative(Debug = "ignore")]
+    event_updaters: Vec<fn(&mut Resources)>,
+    #[derivative(Debug = "ignore")]
+    window_event_handlers: Vec<WindowEventHandler>,
+
+    /// When the last frame started.
+    last_frame: Instant,
+
+    /// Have we started up, or shut down.
+    started: bool,
+    stopped: bool,
+    exit_code: Option<i32>,
+
+    config: AppConfig,
 }
 
 impl Application {
-    /// Creates a new application with a config.
+    /// Creates a new application with a config, and the default plugins.
     pub fn new(config: AppConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        let event_loop = EventLoop::new();
-        let window = WindowBuilder::new().build(&event_loop).unwrap();
-        let renderer = executor::block_on(Renderer::new(&window))?;
-        let scenes = vec![Scene::new("main")];
-
-        window.set_resizable(config.resizable);
-        window.set_title(&config.title);
-        window.set_min_inner_size(Some(LogicalSize::new(config.width, config.height)));
-        window.set_always_on_top(config.top);
-        window.set_decorations(config.décor);
-
-        Ok(Self {
-            event_loop,
-            window,
-            renderer,
-            state: State {},
-            active_scene_index: 0,
-            scenes,
-        })
+        let mut builder = Self::builder(config);
+        builder.add_plugin(DefaultPlugins::new());
+
+        builder.build()
+    }
+
+    /// Creates a new application with no window or renderer, and the rest of the default plugins.
+    pub fn new_headless(config: AppConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut builder = Self::builder(config.with_headless(true));
+        builder.add_plugin(DefaultPlugins::new().disable::<RenderPlugin>());
+
+        builder.build()
+    }
+
+    /// Creates a builder with no plugins, to build an application out of.
+    pub fn builder(config: AppConfig) -> AppBuilder {
+        AppBuilder::new(config)
     }
 
     /// Returns the underlying window type, in case we don't expose
-    /// something that you need.
-    pub fn expose(&mut self) -> &mut Window {
-        &mut self.window
+    /// something that you need. Headless applications don't have one.
+    pub fn expose(&mut self) -> Option<&mut Window> {
+        self.window.as_mut()
+    }
+
+    /// Is the application running without a window.
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
+    }
+
+    /// Runs `frames` frames straight away, each one a single simulation step long (so, time
+    /// scale aside, one simulation step per frame). Meant for tests and servers that drive the
+    /// application themselves, but works with a window too.
+    ///
+    /// The application is started first if it hasn't been. If it exits part way through, it is
+    /// shut down, no more frames are run, and the exit code is returned.
+    pub fn step(&mut self, frames: u32) -> Option<i32> {
+        if self.stopped {
+            return self.exit_code;
+        }
+
+        self.startup();
+
+        let frame_time = self.resources.get::<FixedTimestep>()
+            .map(|timestep| Duration::from_secs_f64(timestep.step()))
+            .unwrap_or_default();
+
+        for _ in 0..frames {
+            self.frame(Some(frame_time));
+
+            if let Some(code) = self.poll_exit() {
+                self.shutdown(code);
+
+                return Some(code);
+            }
+        }
+
+        None
     }
 
-    /// Handle our inputs.
-    pub fn input(&mut self, _event: &WindowEvent) -> bool {
+    /// Feeds a window event to the `Input` resource (if there is one), as if it came from the
+    /// window. Handy for driving headless applications. Always returns false, so the event can be
+    /// handled elsewhere too.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        if let Some(input) = self.resources.get_mut::<Input>() {
+            input.process_event(event);
+        }
+
         false
     }
 
@@ -124,84 +221,512 @@ impl Application {
 
     /// The application loops. Calls the renderer, runs the scripts,
     /// does the physics, all in one neat, nice, contained function.
-    pub fn application_loop(self) {
+    ///
+    /// The simulation (`State::update`, every component's `on_update` and `FixedUpdate` systems)
+    /// runs at a fixed rate (`AppConfig::tick_rate`), while rendering happens as often as the
+    /// display allows. The renderer is told how far between simulation steps it is, so movement
+    /// stays smooth.
+    ///
+    /// Headless applications run a frame per simulation step instead.
+    ///
+    /// Returns the exit code once the application has shut down (see the `lifecycle` module). On
+    /// platforms where winit can't give control back once the loop has started, the process exits
+    /// with the code instead.
+    pub fn application_loop(mut self) -> i32 {
         info!("Starting application loop");
 
-        let event_loop = self.event_loop;
-        let window = self.window;
-        let mut renderer = self.renderer;
-        let mut state = self.state;
-        let scenes = self.scenes;
-        let active_scene_index = self.active_scene_index;
+        self.startup();
+        self.last_frame = Instant::now();
+
+        let event_loop = match self.event_loop.take() {
+            Some(event_loop) => event_loop,
+            None => return self.headless_loop(),
+        };
 
         debug!("Moving into window event loop");
-        event_loop.run(move |event, _, control_flow| match event {
+        self.run_event_loop(event_loop)
+    }
+
+    #[cfg(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+    ))]
+    fn run_event_loop(mut self, mut event_loop: EventLoop<()>) -> i32 {
+        use winit::platform::run_return::EventLoopExtRunReturn;
+
+        event_loop.run_return(|event, _, control_flow| self.handle_event(event, control_flow));
+
+        self.exit_code.unwrap_or(0)
+    }
+
+    #[cfg(not(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+    )))]
+    fn run_event_loop(mut self, event_loop: EventLoop<()>) -> i32 {
+        event_loop.run(move |event, _, control_flow| {
+            let destroyed = matches!(event, Event::LoopDestroyed);
+            self.handle_event(event, control_flow);
+
+            // The loop never returns here, so this is the only way to hand back the exit code
+            if destroyed {
+                std::process::exit(self.exit_code.unwrap_or(0));
+            }
+        })
+    }
+
+    /// Handles a single event from the event loop.
+    fn handle_event(&mut self, event: Event<()>, control_flow: &mut ControlFlow) {
+        match event {
             Event::WindowEvent {
                 ref event,
                 window_id
-            } if window_id == window.id() && !state.input(event) => match event {
-                WindowEvent::CloseRequested | WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
-                            ..
-                        },
-                    ..
-                } => *control_flow = ControlFlow::Exit,
-
-                WindowEvent::Resized(physical_size) => {
-                    trace!("Window resized");
-
-                    renderer.resize(*physical_size);
-                },
-
-                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                    trace!("Window scale factor changed");
-
-                    renderer.resize(**new_inner_size);
-                },
-
-                _ => {},
-            },
-            
-            Event::RedrawRequested(_) => {
-                state.update();
-                match renderer.render(scenes.get(active_scene_index).expect("invalud active_scene_index during renderer invokation")) {
-                    Ok(_) => {}
-                    // Reconfigure the surface if lost
-                    Err(wgpu::SurfaceError::Lost) => renderer.resize(renderer.size),
-                    // The system is out of memory, we should probably quit
-                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                    // All other errors (Outdated, Timeout) should be resolved by the next frame
-                    Err(e) => error!("Failed to render frame: {:?}", e),
-                }
-            },
+            } if Some(window_id) == self.window.as_ref().map(Window::id) && self.handle_window_event(event) => self.exit(0),
+
+            Event::RedrawRequested(_) => self.frame(None),
 
             Event::MainEventsCleared => {
+                if let Some(code) = self.poll_exit() {
+                    self.shutdown(code);
+                    *control_flow = ControlFlow::Exit;
+
+                    return;
+                }
+
                 // RedrawRequested will only trigger once, unless we manually
                 // request it.
-                window.request_redraw();
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
             },
 
             _ => {},
-        });
+        }
+    }
+
+    /// Runs frames at the tick rate, until something asks to exit.
+    fn headless_loop(mut self) -> i32 {
+        debug!("Running headless loop");
+
+        let tick = Duration::from_secs_f64(1.0 / self.config.tick_rate.max(1) as f64);
+
+        loop {
+            let started = Instant::now();
+            self.frame(None);
+
+            if let Some(code) = self.poll_exit() {
+                self.shutdown(code);
+
+                return code;
+            }
+
+            if let Some(rest) = tick.checked_sub(started.elapsed()) {
+                std::thread::sleep(rest);
+            }
+        }
+    }
+
+    /// Records input events from the window, or drops them while a replay is playing, then passes
+    /// the rest on. Returns true if the window was closed, so the application should be asked to
+    /// close.
+    fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        if let Some(recorder) = self.resources.get_mut::<InputRecorder>() {
+            recorder.record_event(event);
+        }
+
+        let replaying = self.resources.get::<InputReplay>().map(InputReplay::is_playing).unwrap_or(false);
+
+        if replaying && RecordedEvent::from_window_event(event).is_some() {
+            return false;
+        }
+
+        self.dispatch_window_event(event)
+    }
+
+    /// Passes a window event to the state, then every handler, until one handles it. Returns true
+    /// if the window was closed.
+    fn dispatch_window_event(&mut self, event: &WindowEvent) -> bool {
+        if self.state.input(event) {
+            return false;
+        }
+
+        for handler in &mut self.window_event_handlers {
+            if handler(&mut self.resources, event) {
+                return false;
+            }
+        }
+
+        matches!(event, WindowEvent::CloseRequested)
+    }
+
+    /// Runs a single frame: moves events along, runs as many simulation steps as are owed, then
+    /// every other stage. The frame is `frame_time` long, or however long it's been since the
+    /// last frame.
+    fn frame(&mut self, frame_time: Option<Duration>) {
+        for update in &self.event_updaters {
+            update(&mut self.resources);
+        }
+
+        // Replayed input comes in before anything gets to look at this frame, like real input
+        let replayed = self.resources.get_mut::<InputReplay>().and_then(InputReplay::next_frame);
+
+        if let Some(replayed) = &replayed {
+            for event in &replayed.events {
+                self.dispatch_window_event(&event.to_window_event());
+            }
+        }
+
+        // Scene files that changed on disk are applied before anything gets to look at the scenes
+        self.reload_scenes();
+        self.update_assets();
+
+        let scene = self.scenes.get_mut_active();
+
+        self.schedule.run(Stage::First, &mut self.resources, scene);
+
+        // While recording, every frame is exactly one recording frame long.
+        let now = Instant::now();
+        let resources = &self.resources;
+        let real_frame_time = replayed.as_ref().map(|replayed| replayed.delta)
+            .or(frame_time)
+            .or_else(|| resources.get::<Renderer>().and_then(Renderer::recording_timestep))
+            .unwrap_or(now - self.last_frame);
+        self.last_frame = now;
+
+        let frame_time = match self.resources.get_mut::<Time>() {
+            Some(time) => time.begin_frame(real_frame_time),
+            None => real_frame_time,
+        };
+
+        let steps = match self.resources.get_mut::<FixedTimestep>() {
+            Some(timestep) => timestep.advance(frame_time.as_secs_f64()),
+            None => 1,
+        };
+
+        // Replays run exactly as many steps as were recorded, whatever the accumulator says
+        let steps = replayed.map(|replayed| replayed.ticks).unwrap_or(steps);
+
+        if let Some(recorder) = self.resources.get_mut::<InputRecorder>() {
+            recorder.end_frame(real_frame_time, steps);
+        }
+
+        for _ in 0..steps {
+            if let Some(time) = self.resources.get_mut::<Time>() {
+                time.begin_fixed_step();
+            }
+
+            if let Some(time) = self.resources.get::<Time>() {
+                self.state.update(time);
+                scene.update(time);
+            }
+
+            self.schedule.run(Stage::FixedUpdate, &mut self.resources, scene);
+
+            if let Some(time) = self.resources.get_mut::<Time>() {
+                time.end_fixed_step();
+            }
+        }
+
+        self.schedule.run(Stage::Update, &mut self.resources, scene);
+
+        // Paused scenes under overlays are drawn too, so lend them to the renderer
+        if let (Some(cover), Some(renderer)) = (self.scenes.take_cover_update(), self.resources.get_mut::<Renderer>()) {
+            renderer.set_cover(cover);
+        }
+
+        if let Some((layers, active)) = self.scenes.split_layers() {
+            self.schedule.run_render(&mut self.resources, layers, active);
+        }
+
+        self.schedule.run(Stage::Last, &mut self.resources, self.scenes.get_mut_active());
+
+        // Scene changes happen once everything is done with this frame's scene
+        let changes: Vec<_> = self.resources.get_mut::<Events<SceneChange>>()
+            .map(|events| events.drain().collect())
+            .unwrap_or_default();
+
+        for change in changes {
+            self.scenes.queue(change);
+        }
+
+        self.scenes.update(real_frame_time, &mut self.resources);
+    }
+
+    /// Applies any changed scene (and prefab) files to the scenes on the stack. See
+    /// `scene::reload`.
+    fn reload_scenes(&mut self) {
+        let mut reloader = match self.resources.remove::<SceneReloader>() {
+            Some(reloader) => reloader,
+            None => return,
+        };
+
+        reloader.update(&mut self.scenes, self.resources.get_or_insert_with(PrefabLibrary::new));
+        self.resources.insert(reloader);
+    }
+
+    /// Finishes off loaded assets, and frees unused ones. See `asset`.
+    fn update_assets(&mut self) {
+        if let Some(mut assets) = self.resources.remove::<AssetServer>() {
+            assets.update(&mut self.resources);
+            self.resources.insert(assets);
+        }
+    }
+
+    /// Loads a scene file with the application's `PrefabLibrary`, so it's reloaded when the file
+    /// changes (see `scene::reload`).
+    pub fn load_scene<P: AsRef<Path>>(&mut self, path: P) -> Result<Scene, crate::error::Error> {
+        self.resources.get_or_insert_with(PrefabLibrary::new).load_scene(path)
+    }
+
+    /// Saves the persisted entities of the active scene, and the resources in the `SaveRegistry`
+    /// resource, to a file (see `scene::save`).
+    pub fn save_game<P: AsRef<Path>>(&mut self, path: P) -> Result<(), crate::error::Error> {
+        let registry = self.resources.remove::<SaveRegistry>().unwrap_or_default();
+        let save = registry.capture(self.scenes.get_active(), &self.resources);
+        self.resources.insert(registry);
+
+        save.save(path)
+    }
+
+    /// Loads a save file into the active scene and the resources, upgrading it with the
+    /// `SaveRegistry` resource if it's old (see `scene::save`).
+    pub fn load_game<P: AsRef<Path>>(&mut self, path: P) -> Result<(), crate::error::Error> {
+        let save = SaveGame::load(path)?;
+        let registry = self.resources.remove::<SaveRegistry>().unwrap_or_default();
+        let result = registry.restore(save, self.scenes.get_mut_active(), &mut self.resources);
+        self.resources.insert(registry);
+
+        result
+    }
+
+    /// Puts a scene on top of the scene stack at the end of the frame, pausing the active one.
+    /// See `SceneManager`.
+    pub fn push_scene(&mut self, scene: Scene) {
+        self.change_scene(SceneChange::push(scene));
+    }
+
+    /// Takes the active scene off the scene stack at the end of the frame.
+    pub fn pop_scene(&mut self) {
+        self.change_scene(SceneChange::pop());
+    }
+
+    /// Swaps the active scene for another at the end of the frame.
+    pub fn replace_scene(&mut self, scene: Scene) {
+        self.change_scene(SceneChange::replace(scene));
+    }
+
+    /// Changes the scene stack at the end of the frame (with a transition, perhaps).
+    pub fn change_scene(&mut self, change: SceneChange) {
+        if let Some(events) = self.resources.get_mut::<Events<SceneChange>>() {
+            events.send(change);
+        }
+    }
+
+    /// Gets the scene stack.
+    pub fn get_scene_manager(&self) -> &SceneManager {
+        &self.scenes
+    }
+
+    /// Saves the next presented frame to a PNG file. Does nothing without a renderer.
+    pub fn capture_frame<P: AsRef<Path>>(&mut self, path: P) {
+        if let Some(renderer) = self.get_mut_renderer() {
+            renderer.capture_frame(path);
+        }
+    }
+
+    /// Starts saving every presented frame to numbered PNGs in a directory. See
+    /// `Renderer::start_recording`. Does nothing without a renderer.
+    pub fn start_recording<P: AsRef<Path>>(&mut self, directory: P, fps: u32) -> Result<(), crate::error::Error> {
+        match self.get_mut_renderer() {
+            Some(renderer) => renderer.start_recording(directory, fps),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops recording frames.
+    pub fn stop_recording(&mut self) {
+        if let Some(renderer) = self.get_mut_renderer() {
+            renderer.stop_recording();
+        }
+    }
+
+    /// Starts recording input (see `input::replay`), replacing any recording in progress.
+    pub fn start_input_recording(&mut self) {
+        let seed = self.resources.get::<Seed>().map(|seed| seed.0).unwrap_or_default();
+
+        self.resources.insert(InputRecorder::new(seed, self.config.tick_rate));
+    }
+
+    /// Stops recording input, and gets the recording (if there was one).
+    pub fn stop_input_recording(&mut self) -> Option<InputRecording> {
+        self.resources.remove::<InputRecorder>().map(InputRecorder::finish)
+    }
+
+    /// Plays back a recording, from the next frame. Sets the `Seed` to the recording's, so this
+    /// should happen before the application starts (and games seed anything from it).
+    pub fn replay_input(&mut self, recording: InputRecording) {
+        self.resources.insert(Seed(recording.seed));
+        self.resources.insert(InputReplay::new(recording));
+    }
+
+    /// Get a reference to the resources.
+    pub fn get_resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    /// Get a mutable reference to the resources.
+    pub fn get_mut_resources(&mut self) -> &mut Resources {
+        &mut self.resources
+    }
+
+    /// Get a reference to the clock.
+    pub fn get_time(&self) -> Option<&Time> {
+        self.resources.get()
+    }
+
+    /// Get a mutable reference to the clock (to pause it, or change the time scale).
+    pub fn get_mut_time(&mut self) -> Option<&mut Time> {
+        self.resources.get_mut()
+    }
+
+    /// Get a reference to the renderer, if there is one (see `RenderPlugin`).
+    pub fn get_renderer(&self) -> Option<&Renderer> {
+        self.resources.get()
     }
 
-    /// Get a reference to the renderer.
-    pub fn get_renderer(&self) -> &Renderer {
-        &self.renderer
+    /// Get a mutable reference to the renderer (to draw debug shapes, for example).
+    pub fn get_mut_renderer(&mut self) -> Option<&mut Renderer> {
+        self.resources.get_mut()
     }
 
     /// Gets a mutable reference to the active scene.
     pub fn get_mut_active_scene(&mut self) -> &mut Scene {
-        self.scenes.get_mut(self.active_scene_index).expect("invalid active_scene_index during renderer invokation")
+        self.scenes.get_mut_active()
     }
 
     /// Get a reference to the active scene. Gomp does not provide a method to get all the scenes,
     /// as it is required Gomp manages them for runtime safety.
     pub fn get_active_scene(&self) -> &Scene {
-        &self.scenes.get(self.active_scene_index).expect("invalid active_scene_index during renderer invokation")
+        self.scenes.get_active()
+    }
+
+    /// Gets the config the application was built with.
+    pub fn get_config(&self) -> &AppConfig {
+        &self.config
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lifecycle::CloseRequest;
+
+    /// What the test systems saw.
+    #[derive(Debug, Default)]
+    struct Counts {
+        startup: u32,
+        fixed_updates: u32,
+        updates: u32,
+        shut_down: bool,
+    }
+
+    /// A headless application that counts its systems, and sends `AppExit(3)` on `exit_on`
+    /// update (if there is one).
+    fn counting_app(exit_on: Option<u32>) -> Application {
+        let mut builder = Application::builder(AppConfig::new().with_headless(true).with_hot_reload(false));
+
+        builder.add_plugin(DefaultPlugins::new().disable::<RenderPlugin>())
+            .insert_resource(Counts::default())
+            .add_system(Stage::Startup, |resources, _| resources.get_mut::<Counts>().unwrap().startup += 1)
+            .add_system(Stage::FixedUpdate, |resources, _| resources.get_mut::<Counts>().unwrap().fixed_updates += 1)
+            .add_system(Stage::Update, move |resources, _| {
+                let counts = resources.get_mut::<Counts>().unwrap();
+                counts.updates += 1;
+
+                if Some(counts.updates) == exit_on {
+                    resources.get_mut::<Events<AppExit>>().unwrap().send(AppExit(3));
+                }
+            })
+            .add_system(Stage::Shutdown, |resources, _| resources.get_mut::<Counts>().unwrap().shut_down = true);
+
+        builder.build().unwrap()
+    }
+
+    fn counts(app: &Application) -> &Counts {
+        app.get_resources().get::<Counts>().unwrap()
+    }
+
+    #[test]
+    fn headless_applications_have_no_window_or_renderer() {
+        let mut app = Application::new_headless(AppConfig::new().with_hot_reload(false)).unwrap();
+
+        assert!(app.is_headless());
+        assert!(app.expose().is_none());
+        assert!(app.get_renderer().is_none());
+        assert!(app.get_time().is_some());
+    }
+
+    #[test]
+    fn stepping_runs_a_simulation_step_per_frame() {
+        let mut app = counting_app(None);
+        assert!(!app.is_started());
+
+        assert_eq!(app.step(60), None);
+        assert!(app.is_started());
+
+        let seen = counts(&app);
+        assert_eq!(seen.startup, 1);
+        assert_eq!(seen.fixed_updates, 60);
+        assert_eq!(seen.updates, 60);
+        assert!(!seen.shut_down);
+
+        let time = app.get_time().unwrap();
+        assert_eq!(time.tick_count(), 60);
+        assert_eq!(time.frame_count(), 60);
+
+        app.step(10);
+        assert_eq!(counts(&app).startup, 1);
+        assert_eq!(counts(&app).fixed_updates, 70);
+    }
+
+    #[test]
+    fn app_exit_ends_the_run_with_its_code() {
+        let mut app = counting_app(Some(5));
+
+        assert_eq!(app.step(60), Some(3));
+        assert!(app.is_stopped());
+        assert_eq!(app.get_exit_code(), Some(3));
+
+        // The frame that asked to exit finished, and nothing ran after it
+        let seen = counts(&app);
+        assert_eq!(seen.updates, 5);
+        assert_eq!(seen.fixed_updates, 5);
+        assert!(seen.shut_down);
+
+        assert_eq!(app.step(10), Some(3));
+        assert_eq!(counts(&app).updates, 5);
+    }
+
+    #[test]
+    fn cancelled_exits_keep_the_application_running() {
+        let mut builder = Application::builder(AppConfig::new().with_headless(true).with_hot_reload(false));
+        builder.add_plugin(DefaultPlugins::new().disable::<RenderPlugin>())
+            .add_system(Stage::Update, |resources, _| resources.get_mut::<Events<AppExit>>().unwrap().send(AppExit(1)))
+            .add_system(Stage::BeforeExit, |resources, _| resources.get_mut::<CloseRequest>().unwrap().cancel());
+
+        let mut app = builder.build().unwrap();
+
+        assert_eq!(app.step(10), None);
+        assert!(!app.is_stopped());
+    }
+}
@@ -0,0 +1,104 @@
+//! Plugins: bundles of systems, resources, events, render passes and window event handlers.
+//!
+//! Plugins are how the application loop is extended without rewriting it. Even the built-in
+//! subsystems (time, input and rendering) are plugins, collected in `DefaultPlugins`, so any of
+//! them can be turned off or swapped for your own.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! struct ScorePlugin;
+//!
+//! impl Plugin for ScorePlugin {
+//!     fn build(&self, app: &mut AppBuilder) {
+//!         app.insert_resource(Score::default())
+//!             .add_event::<GoalScored>()
+//!             .add_system(Stage::FixedUpdate, count_goals);
+//!     }
+//! }
+//!
+//! let mut app = Application::builder(config);
+//! app.add_plugin(DefaultPlugins::new().replace::<InputPlugin, _>(MyInputPlugin))
+//!     .add_plugin(ScorePlugin);
+//!
+//! app.build()?.application_loop();
+//! ```
+
+use super::builder::AppBuilder;
+use crate::input::plugin::{InputPlugin, GamepadPlugin, ActionsPlugin, ReplayPlugin};
+use crate::renderer::plugin::RenderPlugin;
+use crate::ecs::scene::plugin::SceneReloadPlugin;
+use crate::asset::plugin::AssetPlugin;
+use crate::time::plugin::TimePlugin;
+
+use std::any::{Any, TypeId};
+
+/// Something that adds functionality to an application.
+pub trait Plugin: Any {
+    /// Registers everything the plugin needs with the application.
+    fn build(&self, app: &mut AppBuilder);
+
+    /// The name of the plugin. Adding two plugins with the same name only builds the first.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The plugins every application gets by default: `TimePlugin`, `InputPlugin`, `GamepadPlugin`,
+/// `ActionsPlugin`, `ReplayPlugin`, `SceneReloadPlugin`, `AssetPlugin` and `RenderPlugin` (in that
+/// order).
+pub struct DefaultPlugins {
+    plugins: Vec<(TypeId, Box<dyn Plugin>)>,
+}
+
+impl DefaultPlugins {
+    /// Creates the default set of plugins.
+    pub fn new() -> Self {
+        Self {
+            plugins: vec![
+                (TypeId::of::<TimePlugin>(), Box::new(TimePlugin)),
+                (TypeId::of::<InputPlugin>(), Box::new(InputPlugin)),
+                (TypeId::of::<GamepadPlugin>(), Box::new(GamepadPlugin)),
+                (TypeId::of::<ActionsPlugin>(), Box::new(ActionsPlugin)),
+                (TypeId::of::<ReplayPlugin>(), Box::new(ReplayPlugin)),
+                (TypeId::of::<SceneReloadPlugin>(), Box::new(SceneReloadPlugin)),
+                (TypeId::of::<AssetPlugin>(), Box::new(AssetPlugin)),
+                (TypeId::of::<RenderPlugin>(), Box::new(RenderPlugin)),
+            ],
+        }
+    }
+
+    /// Leaves a plugin out.
+    pub fn disable<P: Plugin>(mut self) -> Self {
+        self.plugins.retain(|(id, _)| *id != TypeId::of::<P>());
+        self
+    }
+
+    /// Swaps a plugin for another, which is built in its place.
+    pub fn replace<P: Plugin, R: Plugin>(mut self, with: R) -> Self {
+        if let Some(entry) = self.plugins.iter_mut().find(|(id, _)| *id == TypeId::of::<P>()) {
+            *entry = (TypeId::of::<R>(), Box::new(with));
+        }
+
+        self
+    }
+
+    /// Is a plugin in the set.
+    pub fn contains<P: Plugin>(&self) -> bool {
+        self.plugins.iter().any(|(id, _)| *id == TypeId::of::<P>())
+    }
+}
+
+impl Default for DefaultPlugins {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Plugin for DefaultPlugins {
+    fn build(&self, app: &mut AppBuilder) {
+        for (_, plugin) in &self.plugins {
+            app.add_dyn_plugin(plugin.as_ref());
+        }
+    }
+}
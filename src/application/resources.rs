@@ -0,0 +1,67 @@
+//! Resources: global, one-of-a-kind values shared between systems.
+//!
+//! Things like the clock, the renderer and input state don't belong to any entity, so they live
+//! here instead, keyed by their type. There can only be one resource of each type, so wrap values
+//! in a newtype if you need two of the same kind.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A map of resources, keyed by type.
+#[derive(Default)]
+pub struct Resources {
+    resources: HashMap<TypeId, (&'static str, Box<dyn Any>)>,
+}
+
+impl Resources {
+    /// Creates an empty resource map.
+    pub fn new() -> Self {
+        Self {
+            resources: HashMap::new(),
+        }
+    }
+
+    /// Adds a resource, returning the old one of the same type (if there was one).
+    pub fn insert<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources.insert(TypeId::of::<R>(), (std::any::type_name::<R>(), Box::new(resource)))
+            .and_then(|(_, old)| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// Removes a resource, and gives it back.
+    pub fn remove<R: 'static>(&mut self) -> Option<R> {
+        self.resources.remove(&TypeId::of::<R>())
+            .and_then(|(_, resource)| resource.downcast().ok())
+            .map(|resource| *resource)
+    }
+
+    /// Is there a resource of this type.
+    pub fn contains<R: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    /// Gets a reference to a resource.
+    pub fn get<R: 'static>(&self) -> Option<&R> {
+        self.resources.get(&TypeId::of::<R>()).and_then(|(_, resource)| resource.downcast_ref())
+    }
+
+    /// Gets a mutable reference to a resource.
+    pub fn get_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut(&TypeId::of::<R>()).and_then(|(_, resource)| resource.downcast_mut())
+    }
+
+    /// Gets a mutable reference to a resource, adding one made by `f` if there isn't one yet.
+    pub fn get_or_insert_with<R: 'static, F: FnOnce() -> R>(&mut self, f: F) -> &mut R {
+        self.resources.entry(TypeId::of::<R>())
+            .or_insert_with(|| (std::any::type_name::<R>(), Box::new(f())))
+            .1
+            .downcast_mut()
+            .expect("resource stored under the wrong type")
+    }
+}
+
+impl std::fmt::Debug for Resources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.resources.values().map(|(name, _)| name)).finish()
+    }
+}
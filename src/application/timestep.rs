@@ -0,0 +1,116 @@
+//! Fixed timestep bookkeeping.
+//!
+//! The simulation always steps forward by the same amount of time, so physics behaves the same on
+//! a 60 Hz monitor as it does on a 144 Hz one. Every frame, the real time that passed is added to
+//! an accumulator, and whole steps are taken out of it. Whatever is left over (less than one step)
+//! becomes the interpolation alpha, so rendering can blend between the last two steps.
+
+/// Keeps track of how many simulation steps are owed.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    /// The length of a step, in seconds.
+    step: f64,
+
+    /// The most steps to take in a single frame.
+    max_steps: u32,
+
+    /// Time that has passed, but hasn't been simulated yet, in seconds.
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    /// Creates a new fixed timestep that runs `tick_rate` steps per second.
+    pub fn new(tick_rate: u32, max_steps: u32) -> Self {
+        Self {
+            step: 1.0 / tick_rate.max(1) as f64,
+            max_steps: max_steps.max(1),
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds the time a frame took, and returns how many steps should be run this frame.
+    pub fn advance(&mut self, frame_time: f64) -> u32 {
+        self.accumulator += frame_time;
+
+        // A little slack, so a frame exactly one step long always gives exactly one step (despite
+        // rounding along the way).
+        let owed = (self.accumulator / self.step + 1e-6).floor();
+        let steps = (owed as u32).min(self.max_steps);
+
+        self.accumulator = (self.accumulator - steps as f64 * self.step).max(0.0);
+
+        // Drop whatever we couldn't catch up on, rather than trying to catch up forever.
+        if self.accumulator >= self.step {
+            log::debug!("Simulation fell {} steps behind, dropping them", (self.accumulator / self.step).floor());
+
+            self.accumulator %= self.step;
+        }
+
+        steps
+    }
+
+    /// How far we are between the last step and the next one (0 to 1).
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.step
+    }
+
+    /// The length of a step, in seconds.
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_frame_one_step_long_takes_one_step() {
+        let mut timestep = FixedTimestep::new(60, 5);
+
+        for _ in 0..120 {
+            assert_eq!(timestep.advance(1.0 / 60.0), 1);
+        }
+
+        assert!(timestep.alpha() < 1e-3);
+    }
+
+    #[test]
+    fn leftover_time_carries_over() {
+        let mut timestep = FixedTimestep::new(10, 5);
+
+        assert_eq!(timestep.advance(0.25), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-9);
+
+        assert_eq!(timestep.advance(0.05), 1);
+        assert!(timestep.alpha() < 1e-9);
+    }
+
+    #[test]
+    fn short_frames_take_no_steps() {
+        let mut timestep = FixedTimestep::new(10, 5);
+
+        assert_eq!(timestep.advance(0.04), 0);
+        assert_eq!(timestep.advance(0.04), 0);
+        assert!((timestep.alpha() - 0.8).abs() < 1e-9);
+        assert_eq!(timestep.advance(0.04), 1);
+    }
+
+    #[test]
+    fn long_frames_are_capped_and_the_rest_dropped() {
+        let mut timestep = FixedTimestep::new(10, 3);
+
+        assert_eq!(timestep.advance(1.05), 3);
+        assert!(timestep.alpha() < 1.0);
+
+        // Nothing is left to catch up on
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+
+    #[test]
+    fn a_zero_tick_rate_is_treated_as_one() {
+        let timestep = FixedTimestep::new(0, 0);
+
+        assert_eq!(timestep.step(), 1.0);
+    }
+}
@@ -0,0 +1,443 @@
+//! Texture atlases: lots of little images packed into one big texture.
+//!
+//! Things drawn from the same texture can be drawn together, so packing every sprite of a game (or
+//! of a level) into an atlas means far fewer draw calls. Each image ends up as a named region of
+//! the atlas, named after its path in the folder it came from, without the extension
+//! (`player/idle_0`, say), and looked up by that name with `TextureAtlas::get_region`, or by a
+//! `Sprite` component (see `ecs::component::sprite`).
+//!
+//! Images are spaced out with transparent padding, and their edge pixels are repeated outwards
+//! (extruded) into it, so filtering never picks up a neighbour's pixels at the edges of a region.
+//!
+//! Atlases can be packed when the game runs:
+//!
+//! ```ignore
+//! let assets = resources.get::<AssetServer>().unwrap();
+//! let (image, layout) = AtlasBuilder::new()
+//!     .with_directory(assets.get_files(), "sprites")?
+//!     .build()?;
+//!
+//! let atlas = TextureAtlas::new(renderer, "Sprites", &image, layout);
+//! ```
+//!
+//! or ahead of time, with the `gomp-atlas` tool, which writes the image and an `.atlas` file:
+//!
+//! ```text
+//! gomp-atlas art/sprites assets/sprites.png --padding 2 --extrude 1
+//! ```
+//!
+//! The `.atlas` file is loaded like any other asset
+//! (`assets.load::<TextureAtlas>("sprites.atlas")`), with the image it names, next to it. It's
+//! RON, and looks like this:
+//!
+//! ```text
+//! (
+//!     image: "sprites.png",
+//!     width: 256,
+//!     height: 128,
+//!     regions: {
+//!         "paddle": (x: 3, y: 3, width: 16, height: 64),
+//!     },
+//! )
+//! ```
+
+use super::loaders::IMAGE_EXTENSIONS;
+use super::vfs::{VirtualFs, to_pack_path};
+use crate::error::Error as GompError;
+use crate::renderer::Renderer;
+use crate::renderer::texture::Texture;
+
+use image::RgbaImage;
+use serde::{Serialize, Deserialize};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Where an image is in an atlas, in pixels (not counting the extruded edges).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRegion {
+    /// Gets the top left and bottom right of the region, in UV coordinates, in an atlas of the
+    /// given size.
+    pub fn get_uvs(&self, (width, height): (u32, u32)) -> ([f32; 2], [f32; 2]) {
+        let (width, height) = (width as f32, height as f32);
+
+        (
+            [self.x as f32 / width, self.y as f32 / height],
+            [(self.x + self.width) as f32 / width, (self.y + self.height) as f32 / height],
+        )
+    }
+}
+
+/// Where every image is in an atlas. What's in an `.atlas` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasLayout {
+    /// The atlas image, relative to the `.atlas` file. Empty for atlases packed at runtime.
+    #[serde(default, skip_serializing_if="String::is_empty")]
+    image: String,
+    width: u32,
+    height: u32,
+
+    /// By name.
+    regions: BTreeMap<String, AtlasRegion>,
+}
+
+impl AtlasLayout {
+    /// Sets the atlas image, relative to the `.atlas` file.
+    pub fn with_image(self, image: &str) -> Self { Self { image: image.to_owned(), .. self } }
+
+    /// Gets the atlas image, relative to the `.atlas` file.
+    pub fn get_image(&self) -> &str {
+        &self.image
+    }
+
+    /// Gets the size of the atlas, in pixels.
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Gets a region, by name.
+    pub fn get_region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    /// Gets every region, by name, in order.
+    pub fn get_regions(&self) -> &BTreeMap<String, AtlasRegion> {
+        &self.regions
+    }
+
+    /// Reads a layout from RON.
+    pub fn from_ron(source: &str) -> Result<Self, GompError> {
+        ron::from_str(source).map_err(|e| GompError::InvalidAsset(format!("{}:{}: {}", e.position.line, e.position.col, e.code)))
+    }
+
+    /// Writes the layout as (pretty) RON.
+    pub fn to_ron(&self) -> Result<String, GompError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new()).map_err(|e| GompError::InvalidAsset(e.to_string()))
+    }
+}
+
+/// Packs images into an atlas.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct AtlasBuilder {
+    /// Transparent pixels between images, and around the edge of the atlas.
+    padding: u32,
+
+    /// How many times the edge pixels of each image are repeated outwards.
+    extrude: u32,
+
+    /// The widest and tallest the atlas can be.
+    max_size: u32,
+
+    /// By name.
+    #[derivative(Debug="ignore")]
+    images: BTreeMap<String, RgbaImage>,
+}
+
+impl AtlasBuilder {
+    /// Creates an empty atlas, with 2 pixels of padding, edges extruded by 1, up to 4096 pixels
+    /// across.
+    pub fn new() -> Self {
+        Self {
+            padding: 2,
+            extrude: 1,
+            max_size: 4096,
+            images: BTreeMap::new(),
+        }
+    }
+
+    /// Sets how many transparent pixels are left between images.
+    pub fn with_padding(self, padding: u32) -> Self { Self { padding, .. self } }
+
+    /// Sets how many times the edge pixels of each image are repeated outwards.
+    pub fn with_extrude(self, extrude: u32) -> Self { Self { extrude, .. self } }
+
+    /// Sets the widest and tallest the atlas can be.
+    pub fn with_max_size(self, max_size: u32) -> Self { Self { max_size, .. self } }
+
+    /// Adds every image in a directory (and the directories in it), named after their paths
+    /// relative to it, without the extension.
+    pub fn with_directory<P: AsRef<Path>>(mut self, files: &VirtualFs, directory: P) -> Result<Self, GompError> {
+        self.add_directory(files, directory)?;
+        Ok(self)
+    }
+
+    /// Adds every image in a directory (and the directories in it), named after their paths
+    /// relative to it, without the extension.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, files: &VirtualFs, directory: P) -> Result<(), GompError> {
+        let directory = directory.as_ref();
+
+        for path in files.get_files(directory) {
+            let is_image = path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+
+            if !is_image {
+                continue;
+            }
+
+            let name = to_pack_path(&path.strip_prefix(directory).unwrap_or(&path).with_extension(""));
+
+            if self.images.contains_key(&name) {
+                return Err(GompError::InvalidAsset(format!("two images in {} are named {}", directory.display(), name)));
+            }
+
+            let image = image::load_from_memory(&files.read(&path)?)
+                .map_err(|e| GompError::InvalidAsset(format!("{}: {}", path.display(), e)))?;
+
+            self.images.insert(name, image.to_rgba8());
+        }
+
+        Ok(())
+    }
+
+    /// Adds an image, replacing any with the same name.
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        self.images.insert(name.to_owned(), image);
+    }
+
+    /// How many images have been added.
+    pub fn get_image_count(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Packs the images into the smallest (power of two sized) atlas they fit in.
+    pub fn build(&self) -> Result<(RgbaImage, AtlasLayout), GompError> {
+        // Tallest first packs rows tighter
+        let mut order: Vec<(&String, &RgbaImage)> = self.images.iter().collect();
+        order.sort_by(|(_, a), (_, b)| b.height().cmp(&a.height()).then(b.width().cmp(&a.width())));
+
+        let (widest, area) = order.iter().fold((1, 0), |(widest, area), (_, image)| {
+            let (width, height) = self.get_slot_size(image);
+
+            (widest.max(width + self.padding * 2), area + u64::from(width + self.padding) * u64::from(height + self.padding))
+        });
+
+        let mut width = widest.next_power_of_two();
+        let mut height = ((area as f64).sqrt() as u32).max(1).next_power_of_two();
+
+        let placed = loop {
+            if width > self.max_size || height > self.max_size {
+                return Err(GompError::InvalidAsset(format!(
+                    "{} images don't fit in a {}x{} atlas", order.len(), self.max_size, self.max_size
+                )));
+            }
+
+            if let Some(placed) = self.place(&order, width, height) {
+                break placed;
+            }
+
+            if width <= height {
+                width *= 2;
+            } else {
+                height *= 2;
+            }
+        };
+
+        let mut atlas = RgbaImage::new(width, height);
+        let mut regions = BTreeMap::new();
+
+        for ((name, image), (x, y)) in order.into_iter().zip(placed) {
+            self.blit(&mut atlas, image, x, y);
+
+            regions.insert(name.clone(), AtlasRegion {
+                x: x + self.extrude,
+                y: y + self.extrude,
+                width: image.width(),
+                height: image.height(),
+            });
+        }
+
+        Ok((atlas, AtlasLayout {
+            image: String::new(),
+            width,
+            height,
+            regions,
+        }))
+    }
+
+    /// Gets the space an image takes up, with its extruded edges.
+    fn get_slot_size(&self, image: &RgbaImage) -> (u32, u32) {
+        (image.width() + self.extrude * 2, image.height() + self.extrude * 2)
+    }
+
+    /// Places images in rows (shelves), left to right, top to bottom. Hands back where the top left
+    /// of each one's slot goes, or `None` if they don't all fit.
+    fn place(&self, images: &[(&String, &RgbaImage)], width: u32, height: u32) -> Option<Vec<(u32, u32)>> {
+        let mut placed = Vec::with_capacity(images.len());
+        let (mut x, mut y) = (self.padding, self.padding);
+        let mut row_height = 0;
+
+        for (_, image) in images {
+            let (slot_width, slot_height) = self.get_slot_size(image);
+
+            if x + slot_width + self.padding > width {
+                x = self.padding;
+                y += row_height + self.padding;
+                row_height = 0;
+            }
+
+            if x + slot_width + self.padding > width || y + slot_height + self.padding > height {
+                return None;
+            }
+
+            placed.push((x, y));
+            x += slot_width + self.padding;
+            row_height = row_height.max(slot_height);
+        }
+
+        Some(placed)
+    }
+
+    /// Copies an image into its slot, repeating its edge pixels out to the edges of the slot.
+    fn blit(&self, atlas: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32) {
+        let (slot_width, slot_height) = self.get_slot_size(image);
+
+        if image.width() == 0 || image.height() == 0 {
+            return;
+        }
+
+        for slot_y in 0..slot_height {
+            for slot_x in 0..slot_width {
+                let source_x = slot_x.saturating_sub(self.extrude).min(image.width() - 1);
+                let source_y = slot_y.saturating_sub(self.extrude).min(image.height() - 1);
+
+                atlas.put_pixel(x + slot_x, y + slot_y, *image.get_pixel(source_x, source_y));
+            }
+        }
+    }
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A packed atlas, uploaded to the GPU.
+#[derive(Debug)]
+pub struct TextureAtlas {
+    texture: Texture,
+    layout: AtlasLayout,
+}
+
+impl TextureAtlas {
+    /// Uploads a packed atlas.
+    pub fn new(renderer: &Renderer, label: &str, image: &RgbaImage, layout: AtlasLayout) -> Self {
+        Self {
+            texture: renderer.create_texture(label, image),
+            layout,
+        }
+    }
+
+    /// Gets the texture every region is in.
+    pub fn get_texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Gets where every image is.
+    pub fn get_layout(&self) -> &AtlasLayout {
+        &self.layout
+    }
+
+    /// Gets a region, by name.
+    pub fn get_region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.layout.get_region(name)
+    }
+
+    /// Gets the top left and bottom right of a region, by name, in UV coordinates.
+    pub fn get_uvs(&self, name: &str) -> Option<([f32; 2], [f32; 2])> {
+        Some(self.get_region(name)?.get_uvs(self.layout.get_size()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn images(sizes: &[(u32, u32)]) -> Vec<(String, RgbaImage)> {
+        sizes.iter()
+            .enumerate()
+            .map(|(i, (width, height))| (i.to_string(), RgbaImage::new(*width, *height)))
+            .collect()
+    }
+
+    fn place(builder: &AtlasBuilder, images: &[(String, RgbaImage)], width: u32, height: u32) -> Option<Vec<(u32, u32)>> {
+        let images: Vec<(&String, &RgbaImage)> = images.iter().map(|(name, image)| (name, image)).collect();
+
+        builder.place(&images, width, height)
+    }
+
+    #[test]
+    fn images_are_placed_in_rows_with_padding() {
+        let builder = AtlasBuilder::new().with_padding(2).with_extrude(1);
+        let images = images(&[(10, 10), (10, 6), (20, 4)]);
+
+        // Slots are 12x12, 12x8 and 22x6. The third doesn't fit after the first two in 40 pixels,
+        // so it starts a row under the tallest
+        assert_eq!(place(&builder, &images, 40, 40), Some(vec![(2, 2), (16, 2), (2, 16)]));
+    }
+
+    #[test]
+    fn images_that_do_not_fit_are_refused() {
+        let builder = AtlasBuilder::new().with_padding(2).with_extrude(0);
+
+        // Exactly fits, with padding on both sides
+        assert!(place(&builder, &images(&[(12, 12)]), 16, 16).is_some());
+        assert!(place(&builder, &images(&[(13, 12)]), 16, 16).is_none());
+        assert!(place(&builder, &images(&[(12, 13)]), 16, 16).is_none());
+
+        // Each fits, but not both
+        assert!(place(&builder, &images(&[(12, 6), (12, 6)]), 16, 16).is_none());
+    }
+
+    #[test]
+    fn built_atlases_fit_everything_and_extrude_edges() {
+        let mut builder = AtlasBuilder::new().with_padding(1).with_extrude(1);
+        let mut red = RgbaImage::new(4, 4);
+
+        for pixel in red.pixels_mut() {
+            *pixel = image::Rgba([255, 0, 0, 255]);
+        }
+
+        builder.add_image("red", red);
+        builder.add_image("wide", RgbaImage::new(30, 2));
+
+        let (image, layout) = builder.build().unwrap();
+        let (width, height) = layout.get_size();
+        let region = *layout.get_region("red").unwrap();
+
+        assert_eq!((image.width(), image.height()), (width, height));
+        assert!(width.is_power_of_two() && height.is_power_of_two());
+
+        // The pixel just outside the region is a copy of its edge
+        assert_eq!(image.get_pixel(region.x - 1, region.y)[0], 255);
+        assert_eq!(region.get_uvs((width, height)).0, [region.x as f32 / width as f32, region.y as f32 / height as f32]);
+    }
+
+    #[test]
+    fn atlases_too_big_are_refused() {
+        let mut builder = AtlasBuilder::new().with_max_size(16);
+        builder.add_image("big", RgbaImage::new(20, 20));
+
+        assert!(matches!(builder.build(), Err(GompError::InvalidAsset(_))));
+    }
+
+    #[test]
+    fn layouts_round_trip_through_ron() {
+        let mut builder = AtlasBuilder::new();
+        builder.add_image("a", RgbaImage::new(3, 5));
+
+        let (_, layout) = builder.build().unwrap();
+        let layout = layout.with_image("sprites.png");
+
+        assert_eq!(AtlasLayout::from_ron(&layout.to_ron().unwrap()).unwrap(), layout);
+    }
+}
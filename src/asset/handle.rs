@@ -0,0 +1,60 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
+
+/// Identifies an asset in the `AssetServer`.
+pub type AssetId = u64;
+
+/// A reference counted handle to an asset of type `T`. Cheap to clone. Once the last handle to an
+/// asset is dropped, the asset server frees it (at the start of the next frame).
+pub struct Handle<T> {
+    /// Shared by every handle to the asset. The server only keeps a weak reference.
+    token: Rc<AssetId>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(crate) fn new(token: Rc<AssetId>) -> Self {
+        Self {
+            token,
+            marker: PhantomData,
+        }
+    }
+
+    /// Gets the ID of the asset.
+    pub fn get_id(&self) -> AssetId {
+        *self.token
+    }
+
+    /// Gets a weak reference to the handle's token, for the server to keep.
+    pub(crate) fn downgrade(&self) -> Weak<AssetId> {
+        Rc::downgrade(&self.token)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self::new(Rc::clone(&self.token))
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.get_id())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_id() == other.get_id()
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get_id().hash(state);
+    }
+}
@@ -0,0 +1,101 @@
+use super::vfs::VirtualFs;
+use crate::application::resources::Resources;
+use crate::error::Error as GompError;
+
+use std::any::Any;
+use std::path::Path;
+
+/// Loads a type of asset, from files with certain extensions.
+///
+/// Loading happens in two halves. `load` turns the bytes of the file into something useful on one
+/// of the asset server's threads (decoding an image, say), then `finish` turns that into the asset
+/// on the main thread, where it can get at the resources (to upload the image to the GPU, say).
+/// Loaders for assets that don't need the second half can load straight into the asset, and have
+/// `finish` hand it back. Assets made from more than one file can read the others through the
+/// `LoadContext`.
+///
+/// ```ignore
+/// struct LevelLoader;
+///
+/// impl AssetLoader for LevelLoader {
+///     type Asset = Level;
+///     type Loaded = Level;
+///
+///     fn get_extensions(&self) -> &[&'static str] {
+///         &["level"]
+///     }
+///
+///     fn load(&self, bytes: &[u8], _context: &LoadContext) -> Result<Level, GompError> {
+///         Level::parse(bytes)
+///     }
+///
+///     fn finish(&self, level: Level, _resources: &mut Resources) -> Result<Level, GompError> {
+///         Ok(level)
+///     }
+/// }
+///
+/// app.add_asset_loader(LevelLoader);
+/// ```
+pub trait AssetLoader: Send + Sync + 'static {
+    /// The asset that's loaded.
+    type Asset: 'static;
+
+    /// What the background half of loading hands to the main thread.
+    type Loaded: Send + 'static;
+
+    /// The file extensions (without the dot) this loader loads.
+    fn get_extensions(&self) -> &[&'static str];
+
+    /// Reads the bytes of a file. Runs on a background thread.
+    fn load(&self, bytes: &[u8], context: &LoadContext) -> Result<Self::Loaded, GompError>;
+
+    /// Turns what was read into the asset. Runs on the main thread.
+    fn finish(&self, loaded: Self::Loaded, resources: &mut Resources) -> Result<Self::Asset, GompError>;
+}
+
+/// The file an `AssetLoader` is loading, and the files around it.
+#[derive(Debug)]
+pub struct LoadContext<'a> {
+    path: &'a Path,
+    files: &'a VirtualFs,
+}
+
+impl<'a> LoadContext<'a> {
+    pub(crate) fn new(path: &'a Path, files: &'a VirtualFs) -> Self {
+        Self {
+            path,
+            files,
+        }
+    }
+
+    /// Gets the path of the file being loaded.
+    pub fn get_path(&self) -> &Path {
+        self.path
+    }
+
+    /// Reads another file, relative to the one being loaded. Changes to it aren't hot reloaded.
+    pub fn read_relative<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, GompError> {
+        let directory = self.path.parent().unwrap_or_else(|| Path::new(""));
+
+        self.files.read(directory.join(path))
+    }
+}
+
+/// An `AssetLoader`, with the types taken out, so loaders of different assets can be kept together.
+pub(crate) trait ErasedLoader: Send + Sync {
+    fn load(&self, bytes: &[u8], context: &LoadContext) -> Result<Box<dyn Any + Send>, GompError>;
+
+    fn finish(&self, loaded: Box<dyn Any + Send>, resources: &mut Resources) -> Result<Box<dyn Any>, GompError>;
+}
+
+impl<L: AssetLoader> ErasedLoader for L {
+    fn load(&self, bytes: &[u8], context: &LoadContext) -> Result<Box<dyn Any + Send>, GompError> {
+        AssetLoader::load(self, bytes, context).map(|loaded| Box::new(loaded) as Box<dyn Any + Send>)
+    }
+
+    fn finish(&self, loaded: Box<dyn Any + Send>, resources: &mut Resources) -> Result<Box<dyn Any>, GompError> {
+        let loaded = loaded.downcast::<L::Loaded>().expect("loaded asset of the wrong type");
+
+        AssetLoader::finish(self, *loaded, resources).map(|asset| Box::new(asset) as Box<dyn Any>)
+    }
+}
@@ -0,0 +1,125 @@
+//! The built-in asset loaders.
+
+use super::atlas::{AtlasLayout, TextureAtlas};
+use super::loader::{AssetLoader, LoadContext};
+use crate::application::resources::Resources;
+use crate::error::Error as GompError;
+use crate::renderer::Renderer;
+use crate::renderer::texture::Texture;
+use crate::renderer::text::font::Font;
+
+use image::RgbaImage;
+
+/// The image formats that can be loaded.
+pub(crate) const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "gif", "tga"];
+
+/// Loads images, as RGBA.
+#[derive(Debug, Default)]
+pub struct ImageLoader;
+
+impl AssetLoader for ImageLoader {
+    type Asset = RgbaImage;
+    type Loaded = RgbaImage;
+
+    fn get_extensions(&self) -> &[&'static str] {
+        IMAGE_EXTENSIONS
+    }
+
+    fn load(&self, bytes: &[u8], _context: &LoadContext) -> Result<RgbaImage, GompError> {
+        decode_image(bytes)
+    }
+
+    fn finish(&self, image: RgbaImage, _resources: &mut Resources) -> Result<RgbaImage, GompError> {
+        Ok(image)
+    }
+}
+
+/// Loads images as textures. The image is decoded in the background, and uploaded to the GPU on
+/// the main thread, so there has to be a `Renderer`.
+#[derive(Debug, Default)]
+pub struct TextureLoader;
+
+impl AssetLoader for TextureLoader {
+    type Asset = Texture;
+    type Loaded = (String, RgbaImage);
+
+    fn get_extensions(&self) -> &[&'static str] {
+        IMAGE_EXTENSIONS
+    }
+
+    fn load(&self, bytes: &[u8], context: &LoadContext) -> Result<(String, RgbaImage), GompError> {
+        Ok((context.get_path().display().to_string(), decode_image(bytes)?))
+    }
+
+    fn finish(&self, (label, image): (String, RgbaImage), resources: &mut Resources) -> Result<Texture, GompError> {
+        let renderer = resources.get::<Renderer>()
+            .ok_or_else(|| GompError::InvalidAsset("textures can't be made without a renderer".to_owned()))?;
+
+        Ok(renderer.create_texture(&label, &image))
+    }
+}
+
+/// Loads TrueType (TTF) and OpenType (OTF) fonts.
+#[derive(Debug, Default)]
+pub struct FontLoader;
+
+impl AssetLoader for FontLoader {
+    type Asset = Font;
+    type Loaded = Font;
+
+    fn get_extensions(&self) -> &[&'static str] {
+        &["ttf", "otf"]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &LoadContext) -> Result<Font, GompError> {
+        Font::from_bytes(bytes.to_vec())
+    }
+
+    fn finish(&self, font: Font, _resources: &mut Resources) -> Result<Font, GompError> {
+        Ok(font)
+    }
+}
+
+/// Loads atlases packed ahead of time (see `atlas`), from `.atlas` files and the images they name.
+/// Like textures, they're uploaded on the main thread, so there has to be a `Renderer`.
+#[derive(Debug, Default)]
+pub struct AtlasLoader;
+
+impl AssetLoader for AtlasLoader {
+    type Asset = TextureAtlas;
+    type Loaded = (String, RgbaImage, AtlasLayout);
+
+    fn get_extensions(&self) -> &[&'static str] {
+        &["atlas"]
+    }
+
+    fn load(&self, bytes: &[u8], context: &LoadContext) -> Result<(String, RgbaImage, AtlasLayout), GompError> {
+        let source = std::str::from_utf8(bytes).map_err(|e| GompError::InvalidAsset(e.to_string()))?;
+        let layout = AtlasLayout::from_ron(source)?;
+
+        if layout.get_image().is_empty() {
+            return Err(GompError::InvalidAsset("the atlas doesn't name its image".to_owned()));
+        }
+
+        let image = decode_image(&context.read_relative(layout.get_image())?)?;
+
+        if image.dimensions() != layout.get_size() {
+            return Err(GompError::InvalidAsset(format!("{} isn't the size of the atlas", layout.get_image())));
+        }
+
+        Ok((context.get_path().display().to_string(), image, layout))
+    }
+
+    fn finish(&self, (label, image, layout): (String, RgbaImage, AtlasLayout), resources: &mut Resources) -> Result<TextureAtlas, GompError> {
+        let renderer = resources.get::<Renderer>()
+            .ok_or_else(|| GompError::InvalidAsset("atlases can't be made without a renderer".to_owned()))?;
+
+        Ok(TextureAtlas::new(renderer, &label, &image, layout))
+    }
+}
+
+fn decode_image(bytes: &[u8]) -> Result<RgbaImage, GompError> {
+    image::load_from_memory(bytes)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| GompError::InvalidAsset(e.to_string()))
+}
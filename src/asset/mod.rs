@@ -0,0 +1,63 @@
+//! Assets: textures, fonts, and anything else that's loaded from a file.
+//!
+//! The `AssetServer` resource loads assets on a handful of background threads, so loading a big
+//! texture doesn't hold up the frame. Loading hands back a typed `Handle` straight away; the asset
+//! itself turns up a frame or so later. Handles are reference counted, and an asset is freed once
+//! the last handle to it is dropped.
+//!
+//! ```ignore
+//! let assets = resources.get_mut::<AssetServer>().unwrap();
+//! let paddle: Handle<Texture> = assets.load("paddle.png");
+//!
+//! // ... later on
+//! match assets.get_load_state(&paddle) {
+//!     LoadState::Loading => draw_loading_screen(),
+//!     LoadState::Loaded => draw(assets.get(&paddle).unwrap()),
+//!     LoadState::Failed(why) => panic!("no paddle: {}", why),
+//! }
+//! ```
+//!
+//! Paths are relative to `AppConfig::asset_directory` (`assets` unless it's set). Shipped games
+//! can bundle their assets into packs instead (see `pack`), listed in `AppConfig::asset_packs`;
+//! anything that isn't in the asset directory is read from those. Mods and patches can be mounted
+//! over the top of both (see `vfs`).
+//!
+//! Which loader is used depends on the type of asset asked for and the file's extension. Images
+//! can be loaded as `Texture`s or as `RgbaImage`s, fonts as `Font`s, and atlases (see `atlas`) as
+//! `TextureAtlas`es. Other types of asset can be loaded by adding an `AssetLoader` for them (see
+//! `AppBuilder::add_asset_loader`).
+//!
+//! With `AppConfig::hot_reload` on (it is in debug builds), assets are loaded again when their
+//! files change, and an `AssetEvent::Modified` is sent, so anything made from them can be remade.
+//! Textures are uploaded to the GPU again as part of loading, so there's nothing to do for those.
+//! Files are watched by polling them (see `watch`), which works anywhere, even in CI containers.
+//! Scenes (see `ecs::scene::reload`) and shaders (see `renderer::reload`) are reloaded too.
+//!
+//! ```ignore
+//! app.add_system(Stage::Update, |resources, _| {
+//!     let events = resources.get::<Events<AssetEvent>>().unwrap();
+//!
+//!     for event in events.iter() {
+//!         if let AssetEvent::Modified(id) = event {
+//!             info!("Asset {} changed", id);
+//!         }
+//!     }
+//! });
+//! ```
+
+pub mod handle;
+pub mod loader;
+pub mod loaders;
+pub mod atlas;
+pub mod server;
+pub mod vfs;
+pub mod pack;
+pub mod plugin;
+mod pool;
+
+pub use handle::Handle;
+pub use loader::{AssetLoader, LoadContext};
+pub use server::{AssetEvent, AssetServer, LoadState};
+pub use vfs::VirtualFs;
+pub use pack::{Pack, PackBuilder};
+pub use atlas::{AtlasBuilder, TextureAtlas};
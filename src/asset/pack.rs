@@ -0,0 +1,565 @@
+//! Asset packs: a whole assets directory in one file, for shipping.
+//!
+//! A pack is every file from a directory, one after the other, followed by an index of where each
+//! one is. Files are deflated if that makes them smaller (so already compressed images and the
+//! like are stored as they are), and each has a checksum that's checked whenever it's read. The
+//! pack as a whole has a content hash, which is the same for the same files whatever order they're
+//! added in, so it can tell which version of a game's assets (or of a patch) is installed.
+//!
+//! Packs are made with the `gomp-pack` tool, or a `PackBuilder`:
+//!
+//! ```ignore
+//! PackBuilder::new()
+//!     .with_directory("assets")?
+//!     .write("game.pack")?;
+//! ```
+//!
+//! and mounted in the `AssetServer`'s `VirtualFs` (see `vfs`), which the asset plugin does for
+//! every pack in `AppConfig::asset_packs`.
+
+use super::vfs::{AssetSource, walk_directory, to_pack_path};
+use crate::error::Error as GompError;
+use crate::versioned;
+
+use bincode::Options;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::inflate::core::{DecompressorOxide, decompress, inflate_flags};
+use serde::{Serialize, Deserialize};
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// The first bytes of every pack.
+const MAGIC: &[u8; 8] = b"GOMPPACK";
+
+/// Bumped whenever the file format changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// The magic, format version and where the index starts.
+const HEADER_SIZE: u64 = versioned::HEADER_SIZE as u64 + 8;
+
+/// How a file is stored in a pack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    /// As it is.
+    None,
+
+    /// Deflated (with no zlib header).
+    Deflate,
+}
+
+/// Where a file is in a pack, and how it's stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackEntry {
+    /// From the start of the pack.
+    offset: u64,
+
+    /// How many bytes it takes up in the pack.
+    stored_size: u64,
+
+    /// How many bytes it is once it has been read.
+    size: u64,
+    compression: Compression,
+
+    /// FNV-1a, of what was added.
+    checksum: u64,
+}
+
+impl PackEntry {
+    /// Gets how many bytes the file takes up in the pack.
+    pub fn get_stored_size(&self) -> u64 {
+        self.stored_size
+    }
+
+    /// Gets how many bytes the file is.
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    /// Gets how the file is stored.
+    pub fn get_compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Gets the file's checksum.
+    pub fn get_checksum(&self) -> u64 {
+        self.checksum
+    }
+}
+
+/// Written at the end of the pack.
+#[derive(Serialize, Deserialize)]
+struct PackIndex {
+    content_hash: u64,
+
+    /// By path, with `/` between directories.
+    entries: BTreeMap<String, PackEntry>,
+}
+
+/// Where a file that's going in a pack comes from.
+enum PackInput {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+/// Makes a pack.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PackBuilder {
+    /// From 0 (don't compress anything) to 10.
+    level: u8,
+
+    /// By path in the pack.
+    #[derivative(Debug="ignore")]
+    files: BTreeMap<String, PackInput>,
+}
+
+impl PackBuilder {
+    /// Creates an empty pack, which compresses files at level 6.
+    pub fn new() -> Self {
+        Self {
+            level: 6,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Sets how hard files are compressed, from 0 (not at all) to 10.
+    pub fn with_compression_level(self, level: u8) -> Self { Self { level: level.min(10), .. self } }
+
+    /// Adds every file in a directory (and the directories in it), under their paths relative to
+    /// it. Hidden files (starting with a `.`) are left out.
+    pub fn with_directory<P: AsRef<Path>>(mut self, directory: P) -> Result<Self, GompError> {
+        self.add_directory(directory)?;
+        Ok(self)
+    }
+
+    /// Adds every file in a directory (and the directories in it), under their paths relative to
+    /// it. Hidden files (starting with a `.`) are left out.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, directory: P) -> Result<(), GompError> {
+        let directory = directory.as_ref();
+
+        for path in walk_directory(directory, Path::new(""))? {
+            self.files.insert(to_pack_path(&path), PackInput::File(directory.join(&path)));
+        }
+
+        Ok(())
+    }
+
+    /// Adds a file from disk, as `path` in the pack.
+    pub fn add_file<P: AsRef<Path>, F: AsRef<Path>>(&mut self, path: P, file: F) {
+        self.files.insert(to_pack_path(path.as_ref()), PackInput::File(file.as_ref().to_path_buf()));
+    }
+
+    /// Adds a file made in code, as `path` in the pack.
+    pub fn add_bytes<P: AsRef<Path>>(&mut self, path: P, bytes: Vec<u8>) {
+        self.files.insert(to_pack_path(path.as_ref()), PackInput::Bytes(bytes));
+    }
+
+    /// How many files have been added.
+    pub fn get_file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Writes the pack to a file, and hands back its content hash.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<u64, GompError> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Writes the pack, and hands back its content hash.
+    pub fn write_to<W: Write + Seek>(&self, mut writer: W) -> Result<u64, GompError> {
+        writer.write_all(&versioned::write_header(MAGIC, FORMAT_VERSION))?;
+
+        // Where the index starts, filled in once it's known
+        writer.write_all(&0u64.to_le_bytes())?;
+
+        let mut entries = BTreeMap::new();
+        let mut offset = HEADER_SIZE;
+
+        for (path, input) in &self.files {
+            let bytes = match input {
+                PackInput::File(file) => std::fs::read(file)?,
+                PackInput::Bytes(bytes) => bytes.clone(),
+            };
+
+            let deflated = match self.level {
+                0 => None,
+                level => Some(compress_to_vec(&bytes, level)).filter(|deflated| deflated.len() < bytes.len()),
+            };
+
+            let (compression, stored) = match &deflated {
+                Some(deflated) => (Compression::Deflate, deflated.as_slice()),
+                None => (Compression::None, bytes.as_slice()),
+            };
+
+            writer.write_all(stored)?;
+            entries.insert(path.clone(), PackEntry {
+                offset,
+                stored_size: stored.len() as u64,
+                size: bytes.len() as u64,
+                compression,
+                checksum: fnv1a(FNV_OFFSET, &bytes),
+            });
+
+            offset += stored.len() as u64;
+        }
+
+        let index = PackIndex {
+            content_hash: content_hash(&entries),
+            entries,
+        };
+
+        bincode::DefaultOptions::new()
+            .serialize_into(&mut writer, &index)
+            .map_err(|e| GompError::InvalidPack(e.to_string()))?;
+
+        writer.seek(SeekFrom::Start(HEADER_SIZE - 8))?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(index.content_hash)
+    }
+}
+
+impl Default for PackBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A pack, opened for reading. Only the index is kept in memory; files are read from disk when
+/// they're asked for.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Pack {
+    path: PathBuf,
+    content_hash: u64,
+
+    /// Where the files end, and the index starts.
+    index_offset: u64,
+
+    #[derivative(Debug="ignore")]
+    entries: BTreeMap<String, PackEntry>,
+}
+
+impl Pack {
+    /// Opens a pack, reading its index.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GompError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+
+        let mut header = [0u8; HEADER_SIZE as usize];
+        file.read_exact(&mut header).map_err(|_| GompError::InvalidPack("not a pack file".to_owned()))?;
+
+        let (_, index_offset) = versioned::read_header(&header, MAGIC, "pack", FORMAT_VERSION)
+            .map_err(GompError::InvalidPack)?;
+        let index_offset = u64::from_le_bytes(index_offset.try_into().expect("8 bytes"));
+        let length = file.metadata()?.len();
+
+        if index_offset < HEADER_SIZE || index_offset > length {
+            return Err(GompError::InvalidPack("the index is corrupt".to_owned()));
+        }
+
+        file.seek(SeekFrom::Start(index_offset))?;
+
+        // The index is the rest of the file, so anything claiming to be bigger is corrupt
+        let index: PackIndex = bincode::DefaultOptions::new()
+            .with_limit(length - index_offset)
+            .deserialize_from(file)
+            .map_err(|e| GompError::InvalidPack(e.to_string()))?;
+
+        Ok(Self {
+            path,
+            content_hash: index.content_hash,
+            index_offset,
+            entries: index.entries,
+        })
+    }
+
+    /// Gets the file the pack was opened from.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the pack's content hash.
+    pub fn get_content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    /// Gets where a file is in the pack, and how it's stored.
+    pub fn get_entry<P: AsRef<Path>>(&self, path: P) -> Option<&PackEntry> {
+        self.entries.get(&to_pack_path(path.as_ref()))
+    }
+
+    /// Gets the path of every file in the pack, in order.
+    pub fn get_paths(&self) -> impl Iterator<Item=&str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Reads a file from the pack, checking it's intact.
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, GompError> {
+        let name = to_pack_path(path.as_ref());
+        let entry = self.entries.get(&name)
+            .ok_or_else(|| GompError::InvalidPack(format!("{} isn't in {}", name, self.path.display())))?;
+
+        // Files are all between the header and the index
+        let in_bounds = entry.offset >= HEADER_SIZE && entry.offset.checked_add(entry.stored_size)
+            .is_some_and(|end| end <= self.index_offset);
+
+        if !in_bounds {
+            return Err(GompError::InvalidPack(format!("{} is corrupt", name)));
+        }
+
+        let mut file = File::open(&self.path)?;
+        let mut stored = vec![0u8; entry.stored_size as usize];
+
+        file.seek(SeekFrom::Start(entry.offset))?;
+        file.read_exact(&mut stored)?;
+
+        let bytes = match entry.compression {
+            Compression::None => stored,
+            Compression::Deflate => inflate_with_limit(&stored, entry.size as usize)
+                .map_err(|e| GompError::InvalidPack(format!("{} doesn't inflate: {:?}", name, e)))?,
+        };
+
+        if bytes.len() as u64 != entry.size || fnv1a(FNV_OFFSET, &bytes) != entry.checksum {
+            return Err(GompError::InvalidPack(format!("{} is corrupt", name)));
+        }
+
+        Ok(bytes)
+    }
+
+    /// Reads every file in the pack, checking they're all intact.
+    pub fn verify(&self) -> Result<(), GompError> {
+        for path in self.get_paths() {
+            self.read_file(path)?;
+        }
+
+        if content_hash(&self.entries) != self.content_hash {
+            return Err(GompError::InvalidPack("the index is corrupt".to_owned()));
+        }
+
+        Ok(())
+    }
+}
+
+impl AssetSource for Pack {
+    fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(&to_pack_path(path))
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>, GompError> {
+        self.read_file(path)
+    }
+
+    fn get_files(&self, directory: &Path) -> Vec<PathBuf> {
+        let prefix = match to_pack_path(directory) {
+            directory if directory.is_empty() => directory,
+            directory => directory + "/",
+        };
+
+        self.entries.keys()
+            .filter(|path| path.starts_with(&prefix))
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+/// Inflates a file, giving up once it's bigger than `limit` (what the index says it should be).
+/// Like `decompress_to_vec_with_limit`, but that doubles its buffer as it goes, so it refuses
+/// files that would fit if the last doubling overshoots the limit.
+fn inflate_with_limit(stored: &[u8], limit: usize) -> Result<Vec<u8>, TINFLStatus> {
+    let flags = inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+    let mut decompressor = Box::<DecompressorOxide>::default();
+    let mut bytes = vec![0; stored.len().saturating_mul(2).clamp(1, limit.max(1))];
+    let (mut in_pos, mut out_pos) = (0, 0);
+
+    loop {
+        let (status, in_read, out_written) = decompress(&mut decompressor, &stored[in_pos..], &mut bytes, out_pos, flags);
+        in_pos += in_read;
+        out_pos += out_written;
+
+        match status {
+            TINFLStatus::Done => {
+                bytes.truncate(out_pos);
+
+                return Ok(bytes);
+            },
+            TINFLStatus::HasMoreOutput if bytes.len() < limit => {
+                let grown = bytes.len().saturating_mul(2).min(limit);
+                bytes.resize(grown, 0);
+            },
+            status => return Err(status),
+        }
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a. Not cryptographic, but plenty to spot a corrupt file or a changed pack.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    hash
+}
+
+/// Hashes the path and checksum of every file, in path order.
+fn content_hash(entries: &BTreeMap<String, PackEntry>) -> u64 {
+    entries.iter().fold(FNV_OFFSET, |hash, (path, entry)| {
+        let hash = fnv1a(hash, path.as_bytes());
+        let hash = fnv1a(hash, &[0]);
+
+        fnv1a(hash, &entry.checksum.to_le_bytes())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pack file in the temp directory, deleted when it's dropped.
+    struct TempPack(PathBuf);
+
+    impl TempPack {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("gomp-{}-{}.pack", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempPack {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Bytes that don't deflate (xorshift).
+    fn noise(length: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+
+        (0..length).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            state as u8
+        }).collect()
+    }
+
+    fn files() -> Vec<(&'static str, Vec<u8>)> {
+        vec![
+            ("text/repeats.txt", b"gomp ".repeat(1000)),
+            ("noise.bin", noise(2000)),
+            ("empty", vec![]),
+        ]
+    }
+
+    fn write(name: &str) -> (TempPack, u64) {
+        let pack = TempPack::new(name);
+        let mut builder = PackBuilder::new();
+
+        for (path, bytes) in files() {
+            builder.add_bytes(path, bytes);
+        }
+
+        let content_hash = builder.write(&pack.0).unwrap();
+
+        (pack, content_hash)
+    }
+
+    #[test]
+    fn packs_round_trip() {
+        let (file, content_hash) = write("round-trip");
+        let pack = Pack::open(&file.0).unwrap();
+
+        assert_eq!(pack.get_content_hash(), content_hash);
+        assert_eq!(pack.get_paths().collect::<Vec<_>>(), ["empty", "noise.bin", "text/repeats.txt"]);
+        assert_eq!(pack.get_files(Path::new("text")), [PathBuf::from("text/repeats.txt")]);
+
+        for (path, bytes) in files() {
+            assert_eq!(pack.read_file(path).unwrap(), bytes, "{}", path);
+        }
+
+        // Only worth deflating if it's smaller
+        assert_eq!(pack.get_entry("text/repeats.txt").unwrap().get_compression(), Compression::Deflate);
+        assert_eq!(pack.get_entry("noise.bin").unwrap().get_compression(), Compression::None);
+
+        pack.verify().unwrap();
+    }
+
+    #[test]
+    fn the_content_hash_only_depends_on_the_files() {
+        let (_, content_hash) = write("hash");
+
+        let mut builder = PackBuilder::new().with_compression_level(0);
+
+        for (path, bytes) in files().into_iter().rev() {
+            builder.add_bytes(path, bytes);
+        }
+
+        let other = TempPack::new("hash-other");
+
+        assert_eq!(builder.write(&other.0).unwrap(), content_hash);
+    }
+
+    #[test]
+    fn corrupt_files_are_caught() {
+        let (file, _) = write("corrupt");
+        let offset = Pack::open(&file.0).unwrap().get_entry("noise.bin").unwrap().offset;
+
+        let mut bytes = std::fs::read(&file.0).unwrap();
+        bytes[offset as usize] ^= 0xff;
+        std::fs::write(&file.0, bytes).unwrap();
+
+        let pack = Pack::open(&file.0).unwrap();
+
+        assert!(matches!(pack.read_file("noise.bin"), Err(GompError::InvalidPack(_))));
+        assert!(pack.read_file("empty").is_ok());
+        assert!(pack.verify().is_err());
+    }
+
+    #[test]
+    fn entries_outside_the_files_are_refused() {
+        let (file, _) = write("bounds");
+        let mut pack = Pack::open(&file.0).unwrap();
+
+        pack.entries.get_mut("noise.bin").unwrap().stored_size = u64::MAX;
+
+        assert!(matches!(pack.read_file("noise.bin"), Err(GompError::InvalidPack(_))));
+    }
+
+    #[test]
+    fn broken_packs_are_refused() {
+        let (file, _) = write("broken");
+        let bytes = std::fs::read(&file.0).unwrap();
+
+        // Cut short in the index
+        std::fs::write(&file.0, &bytes[..bytes.len() - 4]).unwrap();
+        assert!(matches!(Pack::open(&file.0), Err(GompError::InvalidPack(_))));
+
+        // With the index past the end
+        let mut moved = bytes.clone();
+        moved[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&file.0, moved).unwrap();
+        assert!(matches!(Pack::open(&file.0), Err(GompError::InvalidPack(_))));
+
+        // Not a pack at all
+        std::fs::write(&file.0, b"GOMPSAVE").unwrap();
+        assert!(matches!(Pack::open(&file.0), Err(GompError::InvalidPack(_))));
+    }
+
+    #[test]
+    fn inflating_stops_at_the_limit() {
+        let bytes = b"gomp ".repeat(1000);
+        let deflated = compress_to_vec(&bytes, 6);
+
+        assert_eq!(inflate_with_limit(&deflated, bytes.len()).unwrap(), bytes);
+        assert_eq!(inflate_with_limit(&deflated, bytes.len() - 1), Err(TINFLStatus::HasMoreOutput));
+    }
+}
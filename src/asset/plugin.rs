@@ -0,0 +1,43 @@
+//! The asset plugin.
+
+use super::loaders::{AtlasLoader, FontLoader, ImageLoader, TextureLoader};
+use super::pack::Pack;
+use super::server::{AssetEvent, AssetServer};
+use crate::application::builder::AppBuilder;
+use crate::application::plugin::Plugin;
+
+use log::error;
+
+/// What the packs in `AppConfig::asset_packs` are mounted at, under the asset directory.
+const PACK_PRIORITY: i32 = -1;
+
+/// Adds the `AssetServer` resource (unless there already is one), loading from
+/// `AppConfig::asset_directory` and the packs in `AppConfig::asset_packs`, with the built-in
+/// loaders for images, textures, fonts and atlases. Assets are hot reloaded if
+/// `AppConfig::hot_reload` is on. `AssetEvent`s are sent as events.
+#[derive(Debug, Default)]
+pub struct AssetPlugin;
+
+impl Plugin for AssetPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let root = app.get_config().asset_directory.clone();
+        let packs = app.get_config().asset_packs.clone();
+        let hot_reload = app.get_config().hot_reload;
+        let assets = app.get_mut_resources().get_or_insert_with(|| AssetServer::new().with_root(root));
+
+        for path in packs {
+            match Pack::open(&path) {
+                Ok(pack) => assets.mount(pack, PACK_PRIORITY),
+                Err(e) => error!("Failed to open asset pack {}: {}", path.display(), e),
+            }
+        }
+
+        assets.set_hot_reload(hot_reload);
+        assets.add_loader(ImageLoader);
+        assets.add_loader(TextureLoader);
+        assets.add_loader(FontLoader);
+        assets.add_loader(AtlasLoader);
+
+        app.add_event::<AssetEvent>();
+    }
+}
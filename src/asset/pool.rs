@@ -0,0 +1,51 @@
+//! A tiny pool of threads to load assets on.
+
+use log::debug;
+
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs jobs on a fixed number of threads. The threads finish up once the pool is dropped.
+#[derive(Debug)]
+pub(crate) struct ThreadPool {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl ThreadPool {
+    pub(crate) fn new(threads: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        debug!("Starting {} asset loading threads", threads);
+
+        for i in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+
+            thread::Builder::new()
+                .name(format!("gomp-assets-{}", i))
+                .spawn(move || loop {
+                    // The lock is only held while waiting for a job, not while running it
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => return,
+                    }
+                })
+                .expect("failed to start an asset loading thread");
+        }
+
+        Self { jobs }
+    }
+
+    /// Runs a job on whichever thread is free first.
+    pub(crate) fn spawn<F: FnOnce() + Send + 'static>(&self, job: F) {
+        // Sending only fails once every thread has gone, which only happens if they panicked
+        let _ = self.jobs.send(Box::new(job));
+    }
+}
@@ -0,0 +1,491 @@
+use super::handle::{AssetId, Handle};
+use super::loader::{AssetLoader, ErasedLoader, LoadContext};
+use super::pool::ThreadPool;
+use super::vfs::{AssetSource, VirtualFs};
+use crate::application::events::Events;
+use crate::application::resources::Resources;
+use crate::error::Error as GompError;
+use crate::watch::FileWatcher;
+
+use log::{debug, info, error};
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, mpsc};
+
+/// How loading an asset is going.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+
+    /// Loading went wrong, and this is why.
+    Failed(String),
+}
+
+/// Sent when something happens to an asset. Tell which asset it was with `Handle::get_id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetEvent {
+    /// The asset loaded.
+    Loaded(AssetId),
+
+    /// The asset was reloaded, as its file changed. Anything made from it should be made again.
+    Modified(AssetId),
+
+    /// The asset couldn't be loaded.
+    Failed(AssetId),
+
+    /// The last handle to the asset was dropped, so it was freed.
+    Freed(AssetId),
+}
+
+/// What's been loaded on a background thread, for an asset.
+type LoadResult = (AssetId, Result<Box<dyn Any + Send>, GompError>);
+
+enum EntryState {
+    Loading,
+    Loaded(Box<dyn Any>),
+    Failed(String),
+}
+
+struct Entry {
+    /// In the server's files. Assets added from code don't have one.
+    path: Option<PathBuf>,
+
+    /// The file on disk being watched for changes, if it is.
+    watched: Option<PathBuf>,
+    type_id: TypeId,
+
+    /// Dead once every handle to the asset has been dropped.
+    token: Weak<AssetId>,
+
+    /// Assets added from code don't have one.
+    loader: Option<Arc<dyn ErasedLoader>>,
+    state: EntryState,
+}
+
+/// Loads assets in the background, and keeps them while there are handles to them.
+///
+/// Loading an asset hands back a `Handle` straight away, and the asset turns up once it has been
+/// loaded (see `get_load_state`). Loading the same file as the same type of asset twice hands back
+/// a handle to the same asset. The application calls `update` at the start of every frame, which
+/// finishes off loaded assets and frees the ones that aren't used any more, sending an
+/// `AssetEvent` for each.
+///
+/// With hot reloading on, the files assets were loaded from are watched (unless they came from a
+/// pack), and loaded again when they change. Handles carry on pointing at the same asset, which is
+/// swapped for the new version once it has loaded (and `AssetEvent::Modified` is sent). If the new
+/// version doesn't load, the error is logged and the last good version is kept.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct AssetServer {
+    /// Where assets are read from. Shared with the loading threads.
+    files: Arc<VirtualFs>,
+
+    /// How many threads to load on.
+    threads: usize,
+
+    /// By the type of asset they load, and file extension.
+    #[derivative(Debug="ignore")]
+    loaders: HashMap<(TypeId, String), Arc<dyn ErasedLoader>>,
+
+    #[derivative(Debug="ignore")]
+    entries: HashMap<AssetId, Entry>,
+    by_path: HashMap<(TypeId, PathBuf), AssetId>,
+    next_id: AssetId,
+
+    /// Watches the files of loaded assets, with hot reloading on.
+    watcher: Option<FileWatcher>,
+
+    /// Started with the first load.
+    pool: Option<ThreadPool>,
+    sender: mpsc::Sender<LoadResult>,
+    #[derivative(Debug="ignore")]
+    results: mpsc::Receiver<LoadResult>,
+}
+
+impl AssetServer {
+    /// Creates an asset server with no loaders, reading from the working directory.
+    pub fn new() -> Self {
+        let (sender, results) = mpsc::channel();
+
+        Self {
+            files: Arc::new(VirtualFs::new().with_directory("", 0)),
+            threads: std::thread::available_parallelism().map(|threads| threads.get().min(4)).unwrap_or(2),
+            loaders: HashMap::new(),
+            entries: HashMap::new(),
+            by_path: HashMap::new(),
+            next_id: 0,
+            watcher: None,
+            pool: None,
+            sender,
+            results,
+        }
+    }
+
+    /// Sets the directory assets are read from, in place of anything mounted before.
+    pub fn with_root<P: AsRef<Path>>(self, root: P) -> Self { self.with_files(VirtualFs::new().with_directory(root, 0)) }
+
+    /// Sets where assets are read from (see `vfs`).
+    pub fn with_files(self, files: VirtualFs) -> Self { Self { files: Arc::new(files), .. self } }
+
+    /// Mounts a directory or pack (see `vfs`), with a priority. The directory from `with_root` is
+    /// mounted at 0. Only assets loaded from then on are read from it.
+    pub fn with_mount<S: AssetSource>(mut self, source: S, priority: i32) -> Self {
+        self.mount(source, priority);
+        self
+    }
+
+    /// Mounts a directory or pack (see `vfs`), with a priority. The directory from `with_root` is
+    /// mounted at 0. Only assets loaded from then on are read from it.
+    pub fn mount<S: AssetSource>(&mut self, source: S, priority: i32) {
+        Arc::make_mut(&mut self.files).mount(source, priority);
+    }
+
+    /// Sets how many threads assets are loaded on. Only works before the first load.
+    pub fn with_threads(self, threads: usize) -> Self { Self { threads, .. self } }
+
+    /// Sets if assets are reloaded when their files change.
+    pub fn with_hot_reload(mut self, hot_reload: bool) -> Self {
+        self.set_hot_reload(hot_reload);
+        self
+    }
+
+    /// Sets if assets are reloaded when their files change. Turning it on watches the files of
+    /// every asset that's already been loaded, too.
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        if !hot_reload {
+            self.watcher = None;
+
+            for entry in self.entries.values_mut() {
+                entry.watched = None;
+            }
+
+            return;
+        }
+
+        let watcher = self.watcher.get_or_insert_with(FileWatcher::new);
+        let files = &self.files;
+
+        for entry in self.entries.values_mut().filter(|entry| entry.watched.is_none()) {
+            entry.watched = entry.path.as_ref().and_then(|path| watch(watcher, files, path));
+        }
+    }
+
+    /// Are assets reloaded when their files change.
+    pub fn is_hot_reloading(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    /// Adds a loader.
+    pub fn with_loader<L: AssetLoader>(mut self, loader: L) -> Self {
+        self.add_loader(loader);
+        self
+    }
+
+    /// Adds a loader, replacing any that loads the same type of asset from the same extensions.
+    pub fn add_loader<L: AssetLoader>(&mut self, loader: L) {
+        let loader = Arc::new(loader);
+
+        for extension in loader.get_extensions() {
+            self.loaders.insert((TypeId::of::<L::Asset>(), extension.to_lowercase()), loader.clone());
+        }
+    }
+
+    /// Gets where assets are read from.
+    pub fn get_files(&self) -> &VirtualFs {
+        &self.files
+    }
+
+    /// Starts loading an asset, with the loader for its type and extension, and hands back a
+    /// handle to it. If it's already loaded (or loading), that's what the handle is to.
+    pub fn load<T: 'static, P: AsRef<Path>>(&mut self, path: P) -> Handle<T> {
+        let path = path.as_ref().to_path_buf();
+        let key = (TypeId::of::<T>(), path.clone());
+
+        let existing = self.by_path.get(&key)
+            .and_then(|id| self.entries.get(id))
+            .and_then(|entry| entry.token.upgrade());
+
+        if let Some(token) = existing {
+            return Handle::new(token);
+        }
+
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let loader = self.loaders.get(&(TypeId::of::<T>(), extension.clone())).cloned();
+
+        let state = match &loader {
+            Some(loader) => {
+                debug!("Loading asset {}", path.display());
+                self.spawn_load(self.next_id, loader.clone(), &path);
+
+                EntryState::Loading
+            },
+            None => {
+                let message = format!("no loader for .{} files as {}", extension, std::any::type_name::<T>());
+                error!("Failed to load asset {}: {}", path.display(), message);
+
+                EntryState::Failed(message)
+            },
+        };
+
+        let files = &self.files;
+        let watched = self.watcher.as_mut().and_then(|watcher| watch(watcher, files, &path));
+        let handle = self.insert(Some(path), TypeId::of::<T>(), loader, state);
+
+        self.by_path.insert(key, handle.get_id());
+        self.entries.get_mut(&handle.get_id()).expect("just inserted").watched = watched;
+
+        handle
+    }
+
+    /// Adds an asset made in code, so it can be handed around like a loaded one.
+    pub fn add<T: 'static>(&mut self, asset: T) -> Handle<T> {
+        self.insert(None, TypeId::of::<T>(), None, EntryState::Loaded(Box::new(asset)))
+    }
+
+    /// Gets an asset, if it has loaded.
+    pub fn get<T: 'static>(&self, handle: &Handle<T>) -> Option<&T> {
+        match &self.entries.get(&handle.get_id())?.state {
+            EntryState::Loaded(asset) => asset.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// Gets an asset to change, if it has loaded.
+    pub fn get_mut<T: 'static>(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        match &mut self.entries.get_mut(&handle.get_id())?.state {
+            EntryState::Loaded(asset) => asset.downcast_mut(),
+            _ => None,
+        }
+    }
+
+    /// Gets how loading an asset is going.
+    pub fn get_load_state<T>(&self, handle: &Handle<T>) -> LoadState {
+        match self.entries.get(&handle.get_id()).map(|entry| &entry.state) {
+            Some(EntryState::Loading) => LoadState::Loading,
+            Some(EntryState::Loaded(_)) => LoadState::Loaded,
+            Some(EntryState::Failed(message)) => LoadState::Failed(message.clone()),
+            None => LoadState::Failed("the asset isn't from this server".to_owned()),
+        }
+    }
+
+    /// Has an asset loaded.
+    pub fn is_loaded<T>(&self, handle: &Handle<T>) -> bool {
+        self.get_load_state(handle) == LoadState::Loaded
+    }
+
+    /// Gets the path an asset was loaded from, if it was.
+    pub fn get_path<T>(&self, handle: &Handle<T>) -> Option<&Path> {
+        self.entries.get(&handle.get_id())?.path.as_deref()
+    }
+
+    /// How many assets are still loading. Handy for loading screens.
+    pub fn get_loading_count(&self) -> usize {
+        self.entries.values().filter(|entry| matches!(entry.state, EntryState::Loading)).count()
+    }
+
+    /// Frees assets that don't have any handles left, starts loading any whose files changed
+    /// (with hot reloading on), then finishes off any that have been loaded. Called at the start of
+    /// every frame. Sends an `AssetEvent` for everything that happened, if there's an
+    /// `Events<AssetEvent>` resource.
+    pub fn update(&mut self, resources: &mut Resources) {
+        let mut events = self.free_unused();
+
+        self.reload_changed();
+
+        while let Ok((id, loaded)) = self.results.try_recv() {
+            // Assets that were freed while they loaded are thrown away
+            let entry = match self.entries.get_mut(&id) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let loader = entry.loader.as_ref().expect("loaded asset has a loader");
+            let path = entry.path.as_deref().unwrap_or_else(|| Path::new("")).display();
+
+            match (loaded.and_then(|loaded| loader.finish(loaded, resources)), &entry.state) {
+                (Ok(asset), EntryState::Loaded(_)) => {
+                    info!("Reloaded asset {}", path);
+
+                    entry.state = EntryState::Loaded(asset);
+                    events.push(AssetEvent::Modified(id));
+                },
+                (Ok(asset), _) => {
+                    debug!("Loaded asset {}", path);
+
+                    entry.state = EntryState::Loaded(asset);
+                    events.push(AssetEvent::Loaded(id));
+                },
+                (Err(e), EntryState::Loaded(_)) => {
+                    error!("Failed to reload asset {}, keeping the last good version: {}", path, e);
+                },
+                (Err(e), _) => {
+                    error!("Failed to load asset {}: {}", path, e);
+
+                    entry.state = EntryState::Failed(e.to_string());
+                    events.push(AssetEvent::Failed(id));
+                },
+            }
+        }
+
+        if let Some(sent) = resources.get_mut::<Events<AssetEvent>>() {
+            for event in events {
+                sent.send(event);
+            }
+        }
+    }
+
+    /// Frees every asset without any handles left.
+    fn free_unused(&mut self) -> Vec<AssetEvent> {
+        let unused: Vec<AssetId> = self.entries.iter()
+            .filter(|(_, entry)| entry.token.strong_count() == 0)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &unused {
+            let entry = self.entries.remove(id).expect("unused asset has an entry");
+            let path = match entry.path {
+                Some(path) => path,
+                None => continue,
+            };
+
+            debug!("Freeing asset {}", path.display());
+
+            // Unless the file was loaded again after the last handle went, as a new asset
+            let key = (entry.type_id, path.clone());
+
+            if self.by_path.get(&key) == Some(id) {
+                self.by_path.remove(&key);
+            }
+
+            // The same file can be loaded as more than one type of asset
+            let watched = match entry.watched {
+                Some(watched) => watched,
+                None => continue,
+            };
+
+            let still_used = self.entries.values().any(|entry| entry.watched.as_ref() == Some(&watched));
+
+            if let (Some(watcher), false) = (&mut self.watcher, still_used) {
+                watcher.unwatch(watched);
+            }
+        }
+
+        unused.into_iter().map(AssetEvent::Freed).collect()
+    }
+
+    /// Starts loading every asset whose file has changed again.
+    fn reload_changed(&mut self) {
+        let changed = match &mut self.watcher {
+            Some(watcher) => watcher.poll(),
+            None => return,
+        };
+
+        let reloads: Vec<(AssetId, Arc<dyn ErasedLoader>, PathBuf)> = self.entries.iter()
+            .filter(|(_, entry)| entry.watched.as_ref().is_some_and(|watched| changed.contains(watched)))
+            .filter_map(|(id, entry)| Some((*id, entry.loader.clone()?, entry.path.clone()?)))
+            .collect();
+
+        for (id, loader, path) in reloads {
+            debug!("Reloading asset {}", path.display());
+            self.spawn_load(id, loader, &path);
+        }
+    }
+
+    fn insert<T>(&mut self, path: Option<PathBuf>, type_id: TypeId, loader: Option<Arc<dyn ErasedLoader>>, state: EntryState) -> Handle<T> {
+        let token = Rc::new(self.next_id);
+        let handle = Handle::new(token);
+
+        self.entries.insert(self.next_id, Entry {
+            path,
+            watched: None,
+            type_id,
+            token: handle.downgrade(),
+            loader,
+            state,
+        });
+
+        self.next_id += 1;
+
+        handle
+    }
+
+    /// Reads and loads a file on a background thread.
+    fn spawn_load(&mut self, id: AssetId, loader: Arc<dyn ErasedLoader>, path: &Path) {
+        let threads = self.threads;
+        let pool = self.pool.get_or_insert_with(|| ThreadPool::new(threads));
+        let sender = self.sender.clone();
+        let files = self.files.clone();
+        let path = path.to_path_buf();
+
+        pool.spawn(move || {
+            let loaded = files.read(&path).and_then(|bytes| loader.load(&bytes, &LoadContext::new(&path, &files)));
+
+            // The server has gone if this fails, so there's nobody to tell
+            let _ = sender.send((id, loaded));
+        });
+    }
+}
+
+/// Watches the file on disk an asset is read from, if it's read from a directory.
+fn watch(watcher: &mut FileWatcher, files: &VirtualFs, path: &Path) -> Option<PathBuf> {
+    let watched = files.get_file_path(path)?;
+    watcher.watch(&watched);
+
+    Some(watched)
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TextLoader;
+
+    impl AssetLoader for TextLoader {
+        type Asset = String;
+        type Loaded = String;
+
+        fn get_extensions(&self) -> &[&'static str] {
+            &["txt"]
+        }
+
+        fn load(&self, bytes: &[u8], _context: &LoadContext) -> Result<String, GompError> {
+            Ok(String::from_utf8_lossy(bytes).into_owned())
+        }
+
+        fn finish(&self, loaded: String, _resources: &mut Resources) -> Result<String, GompError> {
+            Ok(loaded)
+        }
+    }
+
+    #[test]
+    fn loading_again_before_freeing_keeps_the_new_asset() {
+        let mut assets = AssetServer::new().with_files(VirtualFs::new());
+        assets.add_loader(TextLoader);
+
+        let first = assets.load::<String, _>("a.txt");
+        let first_id = first.get_id();
+        drop(first);
+
+        // The last handle has gone, but the old asset hasn't been freed yet
+        let second = assets.load::<String, _>("a.txt");
+        assert_ne!(second.get_id(), first_id);
+
+        assets.free_unused();
+
+        assert_eq!(assets.load::<String, _>("a.txt").get_id(), second.get_id());
+    }
+}
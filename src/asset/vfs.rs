@@ -0,0 +1,224 @@
+//! Where assets are read from.
+//!
+//! The `AssetServer` reads files through a `VirtualFs`: a stack of directories and packs (see
+//! `pack`), each mounted with a priority. Reading a file takes it from the highest priority
+//! source that has it, and sources mounted at the same priority are overlaid in the order they
+//! were mounted, with later ones on top. So mods and patches only need the files they change:
+//!
+//! ```ignore
+//! let files = VirtualFs::new()
+//!     .with_pack(Pack::open("game.pack")?, 0)
+//!     // Patches are mounted after the game, so they win
+//!     .with_pack(Pack::open("patch_1.pack")?, 0)
+//!     // And mods win over everything
+//!     .with_directory("mods/hats", 10);
+//!
+//! app.insert_resource(AssetServer::new().with_files(files));
+//! ```
+//!
+//! The files are only ever read, never written. Other places assets live (over the network, say)
+//! can be mounted by implementing `AssetSource` for them.
+
+use super::pack::Pack;
+use crate::error::Error as GompError;
+
+use std::fmt::Debug;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Somewhere assets can be read from. Sources are shared with the threads assets load on.
+pub trait AssetSource: Debug + Send + Sync + 'static {
+    /// Is there a file at this path.
+    fn contains(&self, path: &Path) -> bool;
+
+    /// Reads a whole file.
+    fn read(&self, path: &Path) -> Result<Vec<u8>, GompError>;
+
+    /// Gets every file in a directory, and the directories in it.
+    fn get_files(&self, directory: &Path) -> Vec<PathBuf>;
+
+    /// Gets the file on disk a path is read from, if it is (so it can be watched for changes).
+    fn get_file_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// A directory on disk.
+#[derive(Debug, Clone)]
+pub struct Directory {
+    root: PathBuf,
+}
+
+impl Directory {
+    /// Creates a source for the files in a directory. It doesn't need to exist (yet).
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Gets the directory.
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AssetSource for Directory {
+    fn contains(&self, path: &Path) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>, GompError> {
+        Ok(std::fs::read(self.root.join(path))?)
+    }
+
+    fn get_files(&self, directory: &Path) -> Vec<PathBuf> {
+        walk_directory(&self.root.join(directory), directory).unwrap_or_default()
+    }
+
+    fn get_file_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.root.join(path))
+    }
+}
+
+/// A mounted source.
+#[derive(Debug, Clone)]
+struct Mount {
+    source: Arc<dyn AssetSource>,
+    priority: i32,
+}
+
+/// Directories and packs, overlaid by priority.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualFs {
+    /// Highest priority first, and the last mounted first within a priority.
+    mounts: Vec<Mount>,
+}
+
+impl VirtualFs {
+    /// Creates a file system with nothing mounted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mounts a directory.
+    pub fn with_directory<P: AsRef<Path>>(self, directory: P, priority: i32) -> Self {
+        self.with_source(Directory::new(directory), priority)
+    }
+
+    /// Mounts a pack.
+    pub fn with_pack(self, pack: Pack, priority: i32) -> Self {
+        self.with_source(pack, priority)
+    }
+
+    /// Mounts a source.
+    pub fn with_source<S: AssetSource>(mut self, source: S, priority: i32) -> Self {
+        self.mount(source, priority);
+        self
+    }
+
+    /// Mounts a source, on top of everything else mounted at the same priority.
+    pub fn mount<S: AssetSource>(&mut self, source: S, priority: i32) {
+        let index = self.mounts.iter()
+            .position(|mount| mount.priority <= priority)
+            .unwrap_or(self.mounts.len());
+
+        self.mounts.insert(index, Mount {
+            source: Arc::new(source),
+            priority,
+        });
+    }
+
+    /// Unmounts everything.
+    pub fn clear(&mut self) {
+        self.mounts.clear();
+    }
+
+    /// Is anything mounted.
+    pub fn is_empty(&self) -> bool {
+        self.mounts.is_empty()
+    }
+
+    /// Is there a file at this path in any source.
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.find(path.as_ref()).is_some()
+    }
+
+    /// Reads a whole file, from the highest priority source that has it.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, GompError> {
+        let path = path.as_ref();
+
+        match self.find(path) {
+            Some(source) => source.read(path),
+            None => Err(IoError::new(
+                ErrorKind::NotFound,
+                format!("{} isn't in any mounted directory or pack", path.display()),
+            ).into()),
+        }
+    }
+
+    /// Gets the file on disk a path is read from, if it's read from a directory.
+    pub fn get_file_path<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        let path = path.as_ref();
+
+        self.find(path)?.get_file_path(path)
+    }
+
+    /// Gets every file in a directory, and the directories in it, from every source, in order.
+    pub fn get_files<P: AsRef<Path>>(&self, directory: P) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.mounts.iter()
+            .flat_map(|mount| mount.source.get_files(directory.as_ref()))
+            .collect();
+
+        files.sort();
+        files.dedup();
+
+        files
+    }
+
+    fn find(&self, path: &Path) -> Option<&dyn AssetSource> {
+        self.mounts.iter()
+            .map(|mount| mount.source.as_ref())
+            .find(|source| source.contains(path))
+    }
+}
+
+/// Gets every file in a directory, and the directories in it, leaving out hidden ones, with
+/// `prefix` in front of their paths relative to it.
+pub(crate) fn walk_directory(directory: &Path, prefix: &Path) -> Result<Vec<PathBuf>, GompError> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            files.extend(walk_directory(&entry.path(), &prefix.join(&name))?);
+        } else {
+            files.push(prefix.join(&name));
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Turns a path into how it's written in a pack: relative, with `/` between directories.
+pub(crate) fn to_pack_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+
+            // Kept, so paths outside the root don't match anything
+            Component::ParentDir => Some("..".into()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,79 @@
+//! Packs a directory of images into a texture atlas (see `gomp::asset::atlas`), writing the atlas
+//! image and an `.atlas` file next to it, which is what gets loaded.
+//!
+//! ```text
+//! gomp-atlas art/sprites assets/sprites.png                # writes sprites.png and sprites.atlas
+//! gomp-atlas art/sprites assets/sprites.png --padding 4 --extrude 2 --max-size 2048
+//! ```
+
+use gomp::asset::atlas::AtlasBuilder;
+use gomp::asset::vfs::VirtualFs;
+use gomp::error::Error as GompError;
+
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "\
+usage: gomp-atlas <directory> <image> [--padding <pixels>] [--extrude <pixels>] [--max-size <pixels>]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let (directory, image) = match (args.first(), args.get(1)) {
+        (Some(directory), Some(image)) if !directory.starts_with("--") && !image.starts_with("--") => (directory, image),
+        _ => usage(),
+    };
+
+    let mut builder = AtlasBuilder::new();
+
+    for option in args[2..].chunks(2) {
+        let value = match option.get(1).map(|value| value.parse()) {
+            Some(Ok(value)) => value,
+            _ => usage(),
+        };
+
+        builder = match option[0].as_str() {
+            "--padding" => builder.with_padding(value),
+            "--extrude" => builder.with_extrude(value),
+            "--max-size" => builder.with_max_size(value),
+            _ => usage(),
+        };
+    }
+
+    if let Err(e) = pack(builder, directory, Path::new(image)) {
+        eprintln!("gomp-atlas: {}", e);
+        exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    exit(2);
+}
+
+fn pack(builder: AtlasBuilder, directory: &str, image_path: &Path) -> Result<(), GompError> {
+    let files = VirtualFs::new().with_directory(directory, 0);
+    let builder = builder.with_directory(&files, "")?;
+
+    if builder.get_image_count() == 0 {
+        return Err(GompError::InvalidAsset(format!("there are no images in {}", directory)));
+    }
+
+    let (image, layout) = builder.build()?;
+    let image_name = image_path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let layout = layout.with_image(image_name);
+    let layout_path = image_path.with_extension("atlas");
+
+    image.save(image_path).map_err(|e| GompError::InvalidAsset(e.to_string()))?;
+
+    // The layout goes last, as it's what's watched for hot reloading
+    std::fs::write(&layout_path, layout.to_ron()?)?;
+
+    let (width, height) = layout.get_size();
+    println!(
+        "Packed {} images from {} into {} ({}x{}), and {}",
+        layout.get_regions().len(), directory, image_path.display(), width, height, layout_path.display()
+    );
+
+    Ok(())
+}
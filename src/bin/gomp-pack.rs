@@ -0,0 +1,85 @@
+//! Bundles an assets directory into a pack (see `gomp::asset::pack`), or looks inside one.
+//!
+//! ```text
+//! gomp-pack assets game.pack            # pack a directory
+//! gomp-pack assets game.pack --level 9  # ... compressing harder (0 to not compress at all)
+//! gomp-pack --list game.pack            # list what's in a pack
+//! gomp-pack --verify game.pack          # check every file in a pack is intact
+//! ```
+
+use gomp::asset::pack::{Compression, Pack, PackBuilder};
+use gomp::error::Error as GompError;
+
+use std::process::exit;
+
+const USAGE: &str = "\
+usage: gomp-pack <directory> <pack> [--level <0-10>]
+       gomp-pack --list <pack>
+       gomp-pack --verify <pack>";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["--list", pack] => list(pack),
+        ["--verify", pack] => verify(pack),
+        [directory, pack] => build(directory, pack, 6),
+        [directory, pack, "--level", level] => match level.parse() {
+            Ok(level) if level <= 10 => build(directory, pack, level),
+            _ => usage(),
+        },
+        _ => usage(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("gomp-pack: {}", e);
+        exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    exit(2);
+}
+
+fn build(directory: &str, pack: &str, level: u8) -> Result<(), GompError> {
+    let builder = PackBuilder::new()
+        .with_compression_level(level)
+        .with_directory(directory)?;
+
+    let content_hash = builder.write(pack)?;
+    let size = std::fs::metadata(pack)?.len();
+
+    println!("Packed {} files from {} into {} ({} bytes)", builder.get_file_count(), directory, pack, size);
+    println!("Content hash {:016x}", content_hash);
+
+    Ok(())
+}
+
+fn list(pack: &str) -> Result<(), GompError> {
+    let pack = Pack::open(pack)?;
+
+    for path in pack.get_paths() {
+        let entry = pack.get_entry(path).expect("listed file is in the pack");
+        let compression = match entry.get_compression() {
+            Compression::None => "stored",
+            Compression::Deflate => "deflated",
+        };
+
+        println!("{:>10} {:>10} {:<8} {}", entry.get_size(), entry.get_stored_size(), compression, path);
+    }
+
+    println!("Content hash {:016x}", pack.get_content_hash());
+
+    Ok(())
+}
+
+fn verify(pack: &str) -> Result<(), GompError> {
+    let pack = Pack::open(pack)?;
+    pack.verify()?;
+
+    println!("{} is intact (content hash {:016x})", pack.get_path().display(), pack.get_content_hash());
+
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! Friendly (data-only) descriptions of components.
+//!
+//! A `FriendlyComponent` is the name a component is registered as (see `registry`), and the values
+//! of its fields. Nothing else: no parent entity, no interpolation state. They're what
+//! `FriendlyEntity` and `FriendlyScene` are made of, and what a live component turns back into.
+//!
+//! Fields that aren't given keep the component's defaults when it's built. Fields that aren't
+//! reflected (a text's font, say) can't be described, so set them once the scene is built.
+
+use super::Component;
+use super::reflect::{FieldName, ReflectValue, Value};
+use super::registry;
+use crate::ecs::entity::Entity;
+use crate::error::Error as GompError;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+
+use std::fmt;
+use std::rc::Weak;
+
+/// A data-only description of a component. In scene files, it's written as the name of the
+/// component (`type`), and its fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FriendlyComponent {
+    type_name: String,
+
+    /// In the order they were set.
+    fields: Vec<(String, Value)>,
+}
+
+impl FriendlyComponent {
+    /// Describes a component by the name it's registered as, with no fields set.
+    pub fn new(type_name: &str) -> Self {
+        Self {
+            type_name: type_name.to_owned(),
+            fields: vec![],
+        }
+    }
+
+    /// A transform at a position.
+    pub fn transform(position: [f64; 3]) -> Self {
+        Self::new("transform").with_field("position", position)
+    }
+
+    /// A unit quad mesh, in a colour.
+    pub fn mesh(colour: [f32; 4]) -> Self {
+        Self::new("mesh").with_field("colour", colour)
+    }
+
+    /// Some text.
+    pub fn text(text: &str, size: f32) -> Self {
+        Self::new("text")
+            .with_field("text", text.to_owned())
+            .with_field("size", size)
+    }
+
+    /// A region of a texture atlas, by the path of the `.atlas` file and the region's name.
+    pub fn sprite(atlas: &str, region: &str) -> Self {
+        Self::new("sprite")
+            .with_field("atlas", atlas.to_owned())
+            .with_field("region", region.to_owned())
+    }
+
+    /// Sets a field.
+    pub fn with_field<T: ReflectValue>(mut self, name: &str, value: T) -> Self {
+        self.set_field(name, value.to_value());
+        self
+    }
+
+    /// Sets a field, replacing it if it's already set.
+    pub fn set_field(&mut self, name: &str, value: Value) {
+        match self.fields.iter_mut().find(|(field, _)| field == name) {
+            Some((_, field_value)) => *field_value = value,
+            None => self.fields.push((name.to_owned(), value)),
+        }
+    }
+
+    /// Gets a field, if it's set.
+    pub fn get_field(&self, name: &str) -> Option<&Value> {
+        self.fields.iter().find(|(field, _)| field == name).map(|(_, value)| value)
+    }
+
+    /// Gets the name of the kind of component described.
+    pub fn get_type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Gets every field that's set, in the order they were set.
+    pub fn get_fields(&self) -> &Vec<(String, Value)> {
+        &self.fields
+    }
+
+    /// Builds the live component for an entity, with this thread's registry.
+    pub fn build(&self, parent: Weak<Entity>) -> Result<Box<dyn Component>, GompError> {
+        registry::with_registry(|registry| registry.build(self, parent))
+    }
+
+    /// Describes a live component, if its type is registered.
+    pub fn describe(component: &dyn Component) -> Option<Self> {
+        registry::with_registry(|registry| registry.describe(component))
+    }
+}
+
+impl Serialize for FriendlyComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len() + 1))?;
+        map.serialize_entry("type", &self.type_name)?;
+
+        for (name, value) in &self.fields {
+            map.serialize_entry(name, value)?;
+        }
+
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for FriendlyComponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Any, rather than a map, so RON's `(type: "mesh")` works as well as `{"type": "mesh"}`
+        deserializer.deserialize_any(FriendlyComponentVisitor)
+    }
+}
+
+struct FriendlyComponentVisitor;
+
+impl<'de> Visitor<'de> for FriendlyComponentVisitor {
+    type Value = FriendlyComponent;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a component, with its type and fields")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FriendlyComponent, A::Error> {
+        let mut type_name = None;
+        let mut fields = vec![];
+
+        while let Some(FieldName(name)) = map.next_key()? {
+            if name == "type" {
+                type_name = Some(map.next_value::<String>()?);
+            } else if fields.iter().any(|(field, _)| *field == name) {
+                return Err(de::Error::custom(format!("duplicate field `{}`", name)));
+            } else {
+                fields.push((name, map.next_value()?));
+            }
+        }
+
+        Ok(FriendlyComponent {
+            type_name: type_name.ok_or_else(|| de::Error::missing_field("type"))?,
+            fields,
+        })
+    }
+}
@@ -1,17 +1,68 @@
 use super::{Component, ComponentType};
+use super::reflect::Reflect;
 use super::Entity;
+use crate::time::Time;
+use crate::renderer::geometry::Geometry;
+use crate::renderer::material::Material;
 
+use std::any::Any;
 use std::rc::{Rc, Weak};
 
+#[derive(Reflect)]
 pub struct Mesh {
+    #[reflect(skip)]
     parent: Weak<Entity>,
+
+    /// The geometry of the mesh. Shared, so meshes with the same geometry can be instanced.
+    geometry: Rc<Geometry>,
+
+    /// How the mesh is shaded.
+    material: Material,
+
+    /// The colour the mesh is tinted with (RGBA).
+    colour: [f32; 4],
+}
+
+impl Mesh {
+    /// Gets the geometry of the mesh.
+    pub fn get_geometry(&self) -> &Rc<Geometry> {
+        &self.geometry
+    }
+
+    /// Sets the geometry of the mesh.
+    pub fn set_geometry(&mut self, geometry: Rc<Geometry>) {
+        self.geometry = geometry;
+    }
+
+    /// Gets the material of the mesh.
+    pub fn get_material(&self) -> Material {
+        self.material
+    }
+
+    /// Sets the material of the mesh.
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    /// Gets the colour of the mesh.
+    pub fn get_colour(&self) -> [f32; 4] {
+        self.colour
+    }
+
+    /// Sets the colour of the mesh.
+    pub fn set_colour(&mut self, colour: [f32; 4]) {
+        self.colour = colour;
+    }
 }
 
 impl Component for Mesh {
-    /// Creates a new mesh.
+    /// Creates a new mesh. Starts out as a white unit quad.
     fn from(parent: Weak<Entity>) -> Self {
         Mesh {
             parent,
+            geometry: Geometry::unit_quad(),
+            material: Material::default(),
+            colour: [1.0, 1.0, 1.0, 1.0],
         }
     }
 
@@ -19,6 +70,14 @@ impl Component for Mesh {
         ComponentType::Mesh
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn get_weak_parent(&self) -> &Weak<Entity> {
         &self.parent
     }
@@ -27,7 +86,7 @@ impl Component for Mesh {
         self.parent.upgrade().expect("dangling weak pointer to parent")
     }
 
-    fn on_update(&mut self) {
+    fn on_update(&mut self, _time: &Time) {
 
     }
 
@@ -39,4 +98,3 @@ impl Component for Mesh {
 
     }
 }
-
@@ -1,12 +1,30 @@
 pub mod mesh;
 pub mod transform;
 pub mod script;
+pub mod text;
+pub mod sprite;
+pub mod persist;
+pub mod friendly;
+pub mod reflect;
+pub mod registry;
+
+pub use friendly::FriendlyComponent;
 
 use super::entity::Entity;
+use crate::time::Time;
 
+use std::any::Any;
+use std::cell::RefCell;
 use std::rc::{Rc, Weak};
 
+/// A shared, mutable component. The scene owns these, and entities hold weak references to them.
+pub type ComponentRef = Rc<RefCell<Box<dyn Component>>>;
+
+/// A weak reference to a component. See `ComponentRef`.
+pub type WeakComponentRef = Weak<RefCell<Box<dyn Component>>>;
+
 /// All the different types of components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ComponentType {
     /// A mesh component.
     Mesh,
@@ -16,11 +34,38 @@ pub enum ComponentType {
 
     /// A script component.
     Script,
+
+    /// A text component.
+    Text,
+
+    /// A region of a texture atlas.
+    Sprite,
+
+    /// Marks an entity to be saved (see `scene::save`).
+    Persist,
+
+    /// A component from outside the engine, by the name it's registered as (see `registry`).
+    Custom(&'static str),
 }
 
 /// A component that belongs to an entity.
 /// The generic is the typre of entity that
 /// this component (in paticular) belongs to.
+///
+/// # Upgrading
+///
+/// `as_any` and `as_mut_any` are new, and have no default (`Self` isn't known to be `'static` in
+/// the trait), so components written before them need both added. They're always the same:
+///
+/// ```ignore
+/// fn as_any(&self) -> &dyn Any {
+///     self
+/// }
+///
+/// fn as_mut_any(&mut self) -> &mut dyn Any {
+///     self
+/// }
+/// ```
 pub trait Component {
     /// Creates a component from a entity.
     fn from(entity: Weak<Entity>) -> Self where Self: Sized;
@@ -28,6 +73,13 @@ pub trait Component {
     /// Helps enforce a OOP-like visitor pattern so we can dissern between different types of
     /// components.
     fn type_of(&self) -> ComponentType;
+
+    /// Gets the component as `Any`, so it can be downcast to its concrete type once `type_of` has
+    /// told us what that is.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Gets the component as mutable `Any`. See `as_any`.
+    fn as_mut_any(&mut self) -> &mut dyn Any;
     
     /// Get the parent entity as a weak reference. Will never panic, completely safe.
     fn get_weak_parent(&self) -> &Weak<Entity>; 
@@ -36,8 +88,14 @@ pub trait Component {
     /// this is considered a bug.
     fn get_parent(&self) -> Rc<Entity>;
 
-    /// Called once per frame.
-    fn on_update(&mut self);
+    /// Called once per simulation step. Simulation steps happen at a fixed rate (see
+    /// `AppConfig::tick_rate`), independent of the frame rate, so `time.delta()` is always the
+    /// fixed step length here.
+    fn on_update(&mut self, time: &Time);
+
+    /// Called before every simulation step, so the component can remember its state before the
+    /// step. Used to interpolate between the last two steps when rendering.
+    fn store_previous(&mut self) {}
 
     /// Called on initialisation.
     fn on_start(&mut self);
@@ -45,8 +103,3 @@ pub trait Component {
     /// Called on destruction.
     fn on_stop(&mut self);
 }
-
-/// A "friendly" (more human interactable/readable/writtable) component trait.
-pub trait FriendlyComponent {
-
-}
@@ -0,0 +1,79 @@
+use super::{Component, ComponentType};
+use super::reflect::Reflect;
+use super::Entity;
+use crate::time::Time;
+
+use std::any::Any;
+use std::rc::{Rc, Weak};
+
+/// Marks an entity to be kept in save games (see `scene::save`), along with its components. Only
+/// components that are registered can be saved.
+#[derive(Reflect)]
+pub struct Persist {
+    #[reflect(skip)]
+    parent: Weak<Entity>,
+
+    /// The names of the components to save (as they're registered). Empty saves all of them.
+    components: Vec<String>,
+}
+
+impl Persist {
+    /// Gets the names of the components to save. Empty means all of them.
+    pub fn get_components(&self) -> &Vec<String> {
+        &self.components
+    }
+
+    /// Sets the names of the components to save. Empty saves all of them.
+    pub fn set_components(&mut self, components: Vec<String>) {
+        self.components = components;
+    }
+
+    /// Is a component (by the name it's registered as) saved.
+    pub fn persists(&self, type_name: &str) -> bool {
+        type_name == Self::type_name()
+            || self.components.is_empty()
+            || self.components.iter().any(|component| component == type_name)
+    }
+}
+
+impl Component for Persist {
+    /// Saves every component.
+    fn from(parent: Weak<Entity>) -> Self {
+        Persist {
+            parent,
+            components: vec![],
+        }
+    }
+
+    fn type_of(&self) -> ComponentType {
+        ComponentType::Persist
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_weak_parent(&self) -> &Weak<Entity> {
+        &self.parent
+    }
+
+    fn get_parent(&self) -> Rc<Entity> {
+        self.parent.upgrade().expect("dangling weak pointer to parent")
+    }
+
+    fn on_update(&mut self, _time: &Time) {
+
+    }
+
+    fn on_start(&mut self) {
+
+    }
+
+    fn on_stop(&mut self) {
+
+    }
+}
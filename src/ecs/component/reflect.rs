@@ -0,0 +1,671 @@
+//! Component reflection.
+//!
+//! Reflection lets the engine work with components it doesn't know the type of: build them by
+//! name, list their fields, and read and write those fields as `Value`s. Scene files, inspectors
+//! and scripts all go through it (usually by way of the `registry`).
+//!
+//! Components get it by deriving `Reflect`. Every field has to implement `ReflectValue`, apart from
+//! the ones marked `#[reflect(skip)]`:
+//!
+//! ```ignore
+//! #[derive(Reflect)]
+//! #[reflect(name = "health")]
+//! pub struct Health {
+//!     #[reflect(skip)]
+//!     parent: Weak<Entity>,
+//!
+//!     current: f32,
+//!     max: f32,
+//! }
+//!
+//! registry::register_component::<Health>();
+//! ```
+//!
+//! Fields inside fields are reached with paths, split by dots: `position.0` is the x of a
+//! transform's position.
+
+use crate::error::Error as GompError;
+use crate::renderer::geometry::Geometry;
+use crate::renderer::material::Material;
+use crate::renderer::text::Alignment;
+use crate::renderer::vertex::Vertex;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+pub use gomp_derive::Reflect;
+
+/// The value of a reflected field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Nothing, for optional fields.
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+
+    /// Arrays, lists and tuples.
+    List(Vec<Value>),
+
+    /// Structures, by field name.
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Gets a value inside this one by path (see the module docs). An empty path is this value.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        split_path(path).try_fold(self, |value, segment| match value {
+            Value::List(items) => items.get(segment.parse::<usize>().ok()?),
+            Value::Map(fields) => fields.get(segment),
+            _ => None,
+        })
+    }
+
+    /// Gets a mutable value inside this one by path (see the module docs).
+    pub fn get_mut_path(&mut self, path: &str) -> Option<&mut Value> {
+        split_path(path).try_fold(self, |value, segment| match value {
+            Value::List(items) => items.get_mut(segment.parse::<usize>().ok()?),
+            Value::Map(fields) => fields.get_mut(segment),
+            _ => None,
+        })
+    }
+
+    /// Gets the value as a float, if it's a number.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Value::Int(int) => Some(int as f64),
+            Value::Float(float) => Some(float),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a path into its segments. Empty paths have none.
+pub(crate) fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('.').filter(|segment| !segment.is_empty())
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::None => serializer.serialize_none(),
+            Value::Bool(bool) => serializer.serialize_bool(*bool),
+            Value::Int(int) => serializer.serialize_i64(*int),
+            Value::Float(float) => serializer.serialize_f64(*float),
+            Value::String(string) => serializer.serialize_str(string),
+            Value::List(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+
+                seq.end()
+            },
+            Value::Map(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+
+                for (name, value) in fields {
+                    map.serialize_entry(name, value)?;
+                }
+
+                map.end()
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a field value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Int(value))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Value, E> {
+        i64::try_from(value).map(Value::Int).map_err(|_| E::custom("integer too large"))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = vec![];
+
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+
+        Ok(Value::List(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut fields = BTreeMap::new();
+
+        while let Some((FieldName(name), value)) = map.next_entry()? {
+            fields.insert(name, value);
+        }
+
+        Ok(Value::Map(fields))
+    }
+}
+
+/// The name of a field, in a map. RON gives out the field names of its structures (`(name: value)`)
+/// as identifiers, and the keys of its maps (`{"name": value}`) as strings, so take either.
+pub(crate) struct FieldName(pub String);
+
+impl<'de> Deserialize<'de> for FieldName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FieldNameVisitor)
+    }
+}
+
+struct FieldNameVisitor;
+
+impl<'de> Visitor<'de> for FieldNameVisitor {
+    type Value = FieldName;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a field name")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<FieldName, E> {
+        Ok(FieldName(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<FieldName, E> {
+        Ok(FieldName(value))
+    }
+}
+
+/// The type of a reflected field, for editors (and error messages).
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Bool,
+    Int,
+    Float,
+    String,
+
+    /// One of a set of names.
+    Enum(&'static [&'static str]),
+
+    /// Either nothing, or a value.
+    Optional(Box<FieldType>),
+
+    /// Any number of values.
+    List(Box<FieldType>),
+
+    /// A set number of values.
+    Array(Box<FieldType>, usize),
+
+    /// A set number of values, of different types.
+    Tuple(Vec<FieldType>),
+
+    /// Named fields.
+    Struct(Vec<Field>),
+
+    /// Another entity (see `entity::reference`).
+    Entity,
+}
+
+/// A reflected field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    name: &'static str,
+    field_type: FieldType,
+}
+
+impl Field {
+    pub fn new(name: &'static str, field_type: FieldType) -> Self {
+        Self {
+            name,
+            field_type,
+        }
+    }
+
+    /// Gets the name of the field.
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Gets the type of the field.
+    pub fn get_type(&self) -> &FieldType {
+        &self.field_type
+    }
+}
+
+/// A type that can be the field of a reflected component.
+pub trait ReflectValue: Sized {
+    /// Describes the type.
+    fn field_type() -> FieldType;
+
+    /// Gets the value.
+    fn to_value(&self) -> Value;
+
+    /// Reads a value back. `None` if it's the wrong shape.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+/// A component with reflection data. Derive it, rather than implementing it by hand.
+pub trait Reflect {
+    /// The name the component is registered as.
+    fn type_name() -> &'static str where Self: Sized;
+
+    /// The reflected fields, in order.
+    fn fields() -> Vec<Field> where Self: Sized;
+
+    /// Gets a field, by name.
+    fn get_field(&self, name: &str) -> Option<Value>;
+
+    /// Sets a field, by name.
+    fn set_field(&mut self, name: &str, value: &Value) -> Result<(), GompError>;
+}
+
+impl ReflectValue for bool {
+    fn field_type() -> FieldType {
+        FieldType::Bool
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match *value {
+            Value::Bool(bool) => Some(bool),
+            _ => None,
+        }
+    }
+}
+
+/// Integers go through `i64`, and refuse values that don't fit.
+macro_rules! reflect_int {
+    ($($int:ty),*) => {
+        $(
+            impl ReflectValue for $int {
+                fn field_type() -> FieldType {
+                    FieldType::Int
+                }
+
+                #[allow(clippy::unnecessary_cast)]
+                fn to_value(&self) -> Value {
+                    Value::Int(*self as i64)
+                }
+
+                fn from_value(value: &Value) -> Option<Self> {
+                    match *value {
+                        Value::Int(int) => <$int>::try_from(int).ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+reflect_int!(i8, i16, i32, i64, u8, u16, u32, usize);
+
+impl ReflectValue for f32 {
+    fn field_type() -> FieldType {
+        FieldType::Float
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Float(*self as f64)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_float().map(|float| float as f32)
+    }
+}
+
+impl ReflectValue for f64 {
+    fn field_type() -> FieldType {
+        FieldType::Float
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Float(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_float()
+    }
+}
+
+impl ReflectValue for String {
+    fn field_type() -> FieldType {
+        FieldType::String
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(string) => Some(string.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ReflectValue> ReflectValue for Option<T> {
+    fn field_type() -> FieldType {
+        FieldType::Optional(Box::new(T::field_type()))
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => value.to_value(),
+            None => Value::None,
+        }
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::None => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: ReflectValue> ReflectValue for Vec<T> {
+    fn field_type() -> FieldType {
+        FieldType::List(Box::new(T::field_type()))
+    }
+
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(T::to_value).collect())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::List(items) => items.iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ReflectValue, const N: usize> ReflectValue for [T; N] {
+    fn field_type() -> FieldType {
+        FieldType::Array(Box::new(T::field_type()), N)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(T::to_value).collect())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::List(items) => {
+                let items = items.iter().map(T::from_value).collect::<Option<Vec<T>>>()?;
+
+                <[T; N]>::try_from(items).ok()
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<A: ReflectValue, B: ReflectValue> ReflectValue for (A, B) {
+    fn field_type() -> FieldType {
+        FieldType::Tuple(vec![A::field_type(), B::field_type()])
+    }
+
+    fn to_value(&self) -> Value {
+        Value::List(vec![self.0.to_value(), self.1.to_value()])
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::List(items) if items.len() == 2 => Some((A::from_value(&items[0])?, B::from_value(&items[1])?)),
+            _ => None,
+        }
+    }
+}
+
+/// Unit enums are their variant names, in snake case.
+macro_rules! reflect_enum {
+    ($enum:ty { $($variant:ident => $name:literal),* $(,)? }) => {
+        impl ReflectValue for $enum {
+            fn field_type() -> FieldType {
+                FieldType::Enum(&[$($name),*])
+            }
+
+            fn to_value(&self) -> Value {
+                Value::String(match self {
+                    $(<$enum>::$variant => $name,)*
+                }.to_owned())
+            }
+
+            fn from_value(value: &Value) -> Option<Self> {
+                match value {
+                    $(Value::String(name) if name == $name => Some(<$enum>::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+reflect_enum!(Material {
+    Flat => "flat",
+});
+
+reflect_enum!(Alignment {
+    Left => "left",
+    Centre => "centre",
+    Right => "right",
+});
+
+/// Geometry is its vertices (positions and colours) and indices. The unit quad is nothing at all,
+/// and geometry read back is shared with any identical geometry still alive, so it can still be
+/// instanced.
+impl ReflectValue for Rc<Geometry> {
+    fn field_type() -> FieldType {
+        FieldType::Optional(Box::new(FieldType::Struct(vec![
+            Field::new("vertices", <Vec<([f32; 3], [f32; 3])>>::field_type()),
+            Field::new("indices", <Vec<u16>>::field_type()),
+        ])))
+    }
+
+    fn to_value(&self) -> Value {
+        if Rc::ptr_eq(self, &Geometry::unit_quad()) {
+            return Value::None;
+        }
+
+        let vertices: Vec<([f32; 3], [f32; 3])> = self.get_vertices().iter()
+            .map(|vertex| (vertex.get_position(), vertex.get_colour()))
+            .collect();
+
+        let mut fields = BTreeMap::new();
+        fields.insert("vertices".to_owned(), vertices.to_value());
+        fields.insert("indices".to_owned(), self.get_indices().to_value());
+
+        Value::Map(fields)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::None => Some(Geometry::unit_quad()),
+            Value::Map(fields) => {
+                let vertices = <Vec<([f32; 3], [f32; 3])>>::from_value(fields.get("vertices")?)?;
+                let indices = <Vec<u16>>::from_value(fields.get("indices")?)?;
+
+                Some(Geometry::shared(
+                    vertices.into_iter().map(|(position, colour)| Vertex::new(position, colour)).collect(),
+                    indices,
+                ))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    #[derive(Reflect)]
+    struct PlayerStats {
+        name: String,
+        nickname: Option<String>,
+        offset: (f32, i32),
+        targets: Vec<[f64; 2]>,
+
+        #[reflect(skip)]
+        cache: Cell<u32>,
+    }
+
+    #[derive(Reflect)]
+    #[reflect(name = "hp")]
+    struct Health {
+        current: f32,
+    }
+
+    fn stats() -> PlayerStats {
+        PlayerStats {
+            name: "left".to_owned(),
+            nickname: None,
+            offset: (0.5, -2),
+            targets: vec![[1.0, 2.0]],
+            cache: Cell::new(7),
+        }
+    }
+
+    fn map(fields: &[(&str, Value)]) -> Value {
+        Value::Map(fields.iter().map(|(name, value)| (name.to_string(), value.clone())).collect())
+    }
+
+    #[test]
+    fn paths_reach_into_lists_and_maps() {
+        let mut value = map(&[
+            ("position", [1.0, 2.0, 3.0].to_value()),
+            ("path", map(&[("points", vec![(1u8, 2u8), (3, 4)].to_value())])),
+        ]);
+
+        assert_eq!(value.get_path(""), Some(&value.clone()));
+        assert_eq!(value.get_path("position.1"), Some(&Value::Float(2.0)));
+        assert_eq!(value.get_path("path.points.1.0"), Some(&Value::Int(3)));
+
+        assert_eq!(value.get_path("position.3"), None);
+        assert_eq!(value.get_path("position.x"), None);
+        assert_eq!(value.get_path("path.missing"), None);
+        assert_eq!(value.get_path("position.1.0"), None);
+
+        *value.get_mut_path("path.points.0.1").unwrap() = Value::Int(9);
+        assert_eq!(value.get_path("path.points.0"), Some(&Value::List(vec![Value::Int(1), Value::Int(9)])));
+    }
+
+    #[test]
+    fn values_round_trip() {
+        assert_eq!(Option::<u8>::from_value(&Some(3u8).to_value()), Some(Some(3)));
+        assert_eq!(Option::<u8>::from_value(&None::<u8>.to_value()), Some(None));
+        assert_eq!(None::<u8>.to_value(), Value::None);
+
+        let targets = vec![[1.0, 2.0], [3.0, 4.0]];
+        assert_eq!(<Vec<[f64; 2]>>::from_value(&targets.to_value()), Some(targets));
+        assert_eq!(<Vec<u8>>::from_value(&Value::List(vec![])), Some(vec![]));
+
+        assert_eq!(<(f32, i32)>::from_value(&(0.5f32, -2).to_value()), Some((0.5, -2)));
+        assert_eq!(Alignment::from_value(&Alignment::Centre.to_value()), Some(Alignment::Centre));
+
+        // Ints are floats too
+        assert_eq!(f32::from_value(&Value::Int(2)), Some(2.0));
+    }
+
+    #[test]
+    fn values_of_the_wrong_shape_are_refused() {
+        assert_eq!(u8::from_value(&Value::Int(300)), None);
+        assert_eq!(u32::from_value(&Value::Int(-1)), None);
+        assert_eq!(i32::from_value(&Value::Float(1.0)), None);
+        assert_eq!(Option::<u8>::from_value(&Value::String("three".to_owned())), None);
+        assert_eq!(<[f64; 2]>::from_value(&[1.0, 2.0, 3.0].to_value()), None);
+        assert_eq!(<Vec<u8>>::from_value(&Value::List(vec![Value::Int(1), Value::Bool(true)])), None);
+        assert_eq!(<(f32, i32)>::from_value(&Value::List(vec![Value::Float(0.5)])), None);
+        assert_eq!(Alignment::from_value(&Value::String("justified".to_owned())), None);
+    }
+
+    #[test]
+    fn derived_reflection_lists_every_field_but_skipped_ones() {
+        assert_eq!(PlayerStats::type_name(), "player_stats");
+        assert_eq!(Health::type_name(), "hp");
+
+        assert_eq!(PlayerStats::fields(), vec![
+            Field::new("name", FieldType::String),
+            Field::new("nickname", FieldType::Optional(Box::new(FieldType::String))),
+            Field::new("offset", FieldType::Tuple(vec![FieldType::Float, FieldType::Int])),
+            Field::new("targets", FieldType::List(Box::new(FieldType::Array(Box::new(FieldType::Float), 2)))),
+        ]);
+
+        let stats = stats();
+        assert_eq!(stats.get_field("offset"), Some((0.5f32, -2).to_value()));
+        assert_eq!(stats.get_field("nickname"), Some(Value::None));
+        assert_eq!(stats.get_field("cache"), None);
+    }
+
+    #[test]
+    fn derived_reflection_sets_fields_of_the_right_type() {
+        let mut stats = stats();
+
+        stats.set_field("nickname", &Value::String("lefty".to_owned())).unwrap();
+        stats.set_field("targets", &Value::List(vec![])).unwrap();
+        assert_eq!(stats.nickname.as_deref(), Some("lefty"));
+        assert!(stats.targets.is_empty());
+
+        assert!(matches!(stats.set_field("name", &Value::Int(1)), Err(GompError::InvalidField(_))));
+        assert!(matches!(stats.set_field("cache", &Value::Int(1)), Err(GompError::InvalidField(_))));
+        assert!(matches!(stats.set_field("missing", &Value::Int(1)), Err(GompError::InvalidField(_))));
+
+        // Nothing changed on failure
+        assert_eq!(stats.name, "left");
+        assert_eq!(stats.cache.get(), 7);
+    }
+}
@@ -0,0 +1,441 @@
+//! The component registry.
+//!
+//! Maps the names of components to their types, so they can be built, described and edited by
+//! name (see `reflect`). The built-in components are always registered; register your own with
+//! `register_component` (or `AppBuilder::register_component`) before loading any scenes that use
+//! them.
+//!
+//! ```ignore
+//! registry::register_component::<Health>();
+//!
+//! let speed = registry::with_registry(|registry| {
+//!     registry.get_path(&*component.borrow(), "velocity.0")
+//! })?;
+//! ```
+//!
+//! Components live on a single thread (they're `Rc`s), so the registry is per thread too.
+
+use super::{Component, FriendlyComponent};
+use super::reflect::{Field, Reflect, Value, split_path};
+use super::{mesh::Mesh, persist::Persist, sprite::Sprite, text::Text, transform::Transform};
+use crate::ecs::entity::Entity;
+use crate::error::Error as GompError;
+
+use log::warn;
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+thread_local! {
+    static REGISTRY: RefCell<Rc<ComponentRegistry>> = RefCell::new(Rc::new(ComponentRegistry::new()));
+}
+
+/// Registers a component type with this thread's registry.
+pub fn register_component<T: Component + Reflect + 'static>() {
+    // Snapshots lent out by `with_registry` keep the registry as it was
+    REGISTRY.with(|registry| Rc::make_mut(&mut registry.borrow_mut()).register::<T>());
+}
+
+/// Gives access to this thread's registry. `f` gets a snapshot, so components can be registered
+/// while it runs (by a component's constructor, say), but only show up from the next call on.
+pub fn with_registry<R, F: FnOnce(&ComponentRegistry) -> R>(f: F) -> R {
+    let registry = REGISTRY.with(|registry| Rc::clone(&registry.borrow()));
+
+    f(&registry)
+}
+
+/// Everything the registry knows about a component type.
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct Registration {
+    name: &'static str,
+    type_id: TypeId,
+    fields: Vec<Field>,
+
+    #[derivative(Debug = "ignore")]
+    construct: fn(Weak<Entity>) -> Box<dyn Component>,
+    #[derivative(Debug = "ignore")]
+    get: fn(&dyn Component, &str) -> Option<Value>,
+    #[derivative(Debug = "ignore")]
+    set: fn(&mut dyn Component, &str, &Value) -> Result<(), GompError>,
+}
+
+impl Registration {
+    /// Gets the name the component is registered as.
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Gets the type of the component.
+    pub fn get_type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Gets the reflected fields of the component, in order.
+    pub fn get_fields(&self) -> &Vec<Field> {
+        &self.fields
+    }
+
+    /// Creates the component (with its defaults), for an entity.
+    pub fn construct(&self, parent: Weak<Entity>) -> Box<dyn Component> {
+        (self.construct)(parent)
+    }
+}
+
+/// Component types, by name and by type.
+#[derive(Debug, Clone)]
+pub struct ComponentRegistry {
+    registrations: Vec<Registration>,
+    by_name: HashMap<&'static str, usize>,
+    by_type: HashMap<TypeId, usize>,
+}
+
+impl ComponentRegistry {
+    /// Creates a registry with the built-in components registered.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            registrations: vec![],
+            by_name: HashMap::new(),
+            by_type: HashMap::new(),
+        };
+
+        registry.register::<Transform>();
+        registry.register::<Mesh>();
+        registry.register::<Text>();
+        registry.register::<Sprite>();
+        registry.register::<Persist>();
+
+        registry
+    }
+
+    /// Registers a component type. Registering another type under the same name replaces it.
+    pub fn register<T: Component + Reflect + 'static>(&mut self) {
+        let registration = Registration {
+            name: T::type_name(),
+            type_id: TypeId::of::<T>(),
+            fields: T::fields(),
+            construct: |parent| Box::new(<T as Component>::from(parent)),
+            get: |component, name| component.as_any().downcast_ref::<T>()?.get_field(name),
+            set: |component, name, value| match component.as_mut_any().downcast_mut::<T>() {
+                Some(component) => component.set_field(name, value),
+                None => Err(GompError::InvalidField(format!("not a {}", T::type_name()))),
+            },
+        };
+
+        if let Some(&index) = self.by_type.get(&registration.type_id) {
+            // Same type again, maybe under a new name
+            self.by_name.remove(self.registrations[index].name);
+            self.by_name.insert(registration.name, index);
+            self.registrations[index] = registration;
+        } else if let Some(&index) = self.by_name.get(registration.name) {
+            warn!("Replacing component {} with another type", registration.name);
+
+            self.by_type.remove(&self.registrations[index].type_id);
+            self.by_type.insert(registration.type_id, index);
+            self.registrations[index] = registration;
+        } else {
+            self.by_name.insert(registration.name, self.registrations.len());
+            self.by_type.insert(registration.type_id, self.registrations.len());
+            self.registrations.push(registration);
+        }
+    }
+
+    /// Gets a component type by name.
+    pub fn get(&self, name: &str) -> Option<&Registration> {
+        self.by_name.get(name).map(|&index| &self.registrations[index])
+    }
+
+    /// Gets the type of a component, if it's registered.
+    pub fn get_for(&self, component: &dyn Component) -> Option<&Registration> {
+        self.by_type.get(&component.as_any().type_id()).map(|&index| &self.registrations[index])
+    }
+
+    /// Gets every registered component type, in the order they were registered.
+    pub fn get_registrations(&self) -> &Vec<Registration> {
+        &self.registrations
+    }
+
+    /// Gets a field (or a value inside one) of a component, by path (see `reflect`).
+    pub fn get_path(&self, component: &dyn Component, path: &str) -> Result<Value, GompError> {
+        let registration = self.get_registered(component)?;
+        let (field, rest) = split_field(path);
+
+        let value = (registration.get)(component, field)
+            .ok_or_else(|| GompError::InvalidField(format!("{} has no field {}", registration.name, field)))?;
+
+        value.get_path(rest)
+            .cloned()
+            .ok_or_else(|| GompError::InvalidField(format!("{} has no field {}", registration.name, path)))
+    }
+
+    /// Sets a field (or a value inside one) of a component, by path (see `reflect`).
+    pub fn set_path(&self, component: &mut dyn Component, path: &str, value: Value) -> Result<(), GompError> {
+        let registration = self.get_registered(component)?;
+        let (field, rest) = split_field(path);
+
+        let field_value = if split_path(rest).next().is_none() {
+            value
+        } else {
+            // Change the part of the field, then set the whole thing
+            let mut field_value = (registration.get)(component, field)
+                .ok_or_else(|| GompError::InvalidField(format!("{} has no field {}", registration.name, field)))?;
+
+            let inner = field_value.get_mut_path(rest)
+                .ok_or_else(|| GompError::InvalidField(format!("{} has no field {}", registration.name, path)))?;
+            *inner = value;
+
+            field_value
+        };
+
+        (registration.set)(component, field, &field_value)
+    }
+
+    /// Builds a live component from its description, for an entity. Fields that aren't described
+    /// keep their defaults.
+    pub fn build(&self, friendly: &FriendlyComponent, parent: Weak<Entity>) -> Result<Box<dyn Component>, GompError> {
+        let registration = self.get(friendly.get_type_name())
+            .ok_or_else(|| GompError::InvalidField(format!("no component is registered as {}", friendly.get_type_name())))?;
+
+        let mut component = registration.construct(parent);
+
+        for (name, value) in friendly.get_fields() {
+            (registration.set)(&mut *component, name, value)?;
+        }
+
+        Ok(component)
+    }
+
+    /// Describes a live component, if it's registered.
+    pub fn describe(&self, component: &dyn Component) -> Option<FriendlyComponent> {
+        let registration = self.get_for(component)?;
+        let mut friendly = FriendlyComponent::new(registration.name);
+
+        for field in &registration.fields {
+            if let Some(value) = (registration.get)(component, field.get_name()) {
+                friendly.set_field(field.get_name(), value);
+            }
+        }
+
+        Some(friendly)
+    }
+
+    fn get_registered(&self, component: &dyn Component) -> Result<&Registration, GompError> {
+        self.get_for(component)
+            .ok_or_else(|| GompError::InvalidField("the component isn't registered".to_owned()))
+    }
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits a path into the field, and the path inside the field.
+fn split_field(path: &str) -> (&str, &str) {
+    path.split_once('.').unwrap_or((path, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::ComponentType;
+    use crate::ecs::component::reflect::ReflectValue;
+    use crate::time::Time;
+
+    use std::any::Any;
+    use std::rc::Rc;
+
+    /// Implements `Component` for a test component, made with its `new`.
+    macro_rules! test_component {
+        ($component:ident) => {
+            impl Component for $component {
+                fn from(parent: Weak<Entity>) -> Self {
+                    Self::new(parent)
+                }
+
+                fn type_of(&self) -> ComponentType {
+                    ComponentType::Custom(stringify!($component))
+                }
+
+                fn as_any(&self) -> &dyn Any {
+                    self
+                }
+
+                fn as_mut_any(&mut self) -> &mut dyn Any {
+                    self
+                }
+
+                fn get_weak_parent(&self) -> &Weak<Entity> {
+                    &self.parent
+                }
+
+                fn get_parent(&self) -> Rc<Entity> {
+                    self.parent.upgrade().expect("dangling weak pointer to parent")
+                }
+
+                fn on_update(&mut self, _time: &Time) {}
+
+                fn on_start(&mut self) {}
+
+                fn on_stop(&mut self) {}
+            }
+        };
+    }
+
+    #[derive(Reflect)]
+    struct Path {
+        #[reflect(skip)]
+        parent: Weak<Entity>,
+
+        name: Option<String>,
+        points: Vec<[f64; 2]>,
+        offset: (f32, i32),
+    }
+
+    impl Path {
+        fn new(parent: Weak<Entity>) -> Self {
+            Self {
+                parent,
+                name: None,
+                points: vec![[0.0, 0.0], [1.0, 2.0]],
+                offset: (0.5, 1),
+            }
+        }
+    }
+
+    test_component!(Path);
+
+    /// Registers `Late` when it's made.
+    #[derive(Reflect)]
+    struct Early {
+        #[reflect(skip)]
+        parent: Weak<Entity>,
+    }
+
+    impl Early {
+        fn new(parent: Weak<Entity>) -> Self {
+            register_component::<Late>();
+
+            Self { parent }
+        }
+    }
+
+    test_component!(Early);
+
+    #[derive(Reflect)]
+    struct Late {
+        #[reflect(skip)]
+        parent: Weak<Entity>,
+    }
+
+    impl Late {
+        fn new(parent: Weak<Entity>) -> Self {
+            Self { parent }
+        }
+    }
+
+    test_component!(Late);
+
+    fn registry() -> ComponentRegistry {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Path>();
+
+        registry
+    }
+
+    fn path() -> Box<dyn Component> {
+        Box::new(<Path as Component>::from(Weak::new()))
+    }
+
+    #[test]
+    fn the_built_in_components_are_registered() {
+        let registry = ComponentRegistry::new();
+        let names: Vec<_> = registry.get_registrations().iter().map(Registration::get_name).collect();
+
+        assert_eq!(names, vec!["transform", "mesh", "text", "sprite", "persist"]);
+        assert_eq!(registry.get("transform").unwrap().get_type_id(), TypeId::of::<Transform>());
+        assert!(registry.get("path").is_none());
+    }
+
+    #[test]
+    fn paths_get_fields_and_values_inside_them() {
+        let registry = registry();
+        let path = path();
+
+        assert_eq!(registry.get_path(&*path, "name").unwrap(), Value::None);
+        assert_eq!(registry.get_path(&*path, "points.1").unwrap(), [1.0, 2.0].to_value());
+        assert_eq!(registry.get_path(&*path, "points.1.1").unwrap(), Value::Float(2.0));
+        assert_eq!(registry.get_path(&*path, "offset.1").unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn paths_set_fields_and_values_inside_them() {
+        let registry = registry();
+        let mut path = path();
+
+        registry.set_path(&mut *path, "name", Value::String("patrol".to_owned())).unwrap();
+        registry.set_path(&mut *path, "points.1.0", Value::Float(5.0)).unwrap();
+        registry.set_path(&mut *path, "offset.0", Value::Int(3)).unwrap();
+
+        let path: &Path = path.as_any().downcast_ref().unwrap();
+        assert_eq!(path.name.as_deref(), Some("patrol"));
+        assert_eq!(path.points, vec![[0.0, 0.0], [5.0, 2.0]]);
+        assert_eq!(path.offset, (3.0, 1));
+    }
+
+    #[test]
+    fn bad_paths_and_values_are_errors() {
+        let registry = registry();
+        let mut path = path();
+
+        let errors = vec![
+            registry.get_path(&*path, "missing").err(),
+            registry.get_path(&*path, "points.2").err(),
+            registry.get_path(&*path, "offset.x").err(),
+            registry.set_path(&mut *path, "missing", Value::None).err(),
+            registry.set_path(&mut *path, "points.5.0", Value::Float(1.0)).err(),
+            registry.set_path(&mut *path, "points.0.0", Value::String("far".to_owned())).err(),
+            registry.set_path(&mut *path, "offset", Value::List(vec![Value::Float(1.0)])).err(),
+            ComponentRegistry::new().get_path(&*path, "name").err(),
+        ];
+
+        for error in errors {
+            assert!(matches!(error, Some(GompError::InvalidField(_))), "{:?}", error);
+        }
+
+        // Nothing was set along the way
+        let untouched = registry.build(&FriendlyComponent::new("path"), Weak::new()).unwrap();
+        assert_eq!(registry.describe(&*path), registry.describe(&*untouched));
+    }
+
+    #[test]
+    fn described_components_build_the_same_component() {
+        let registry = registry();
+        let friendly = FriendlyComponent::new("path")
+            .with_field("name", Some("patrol".to_owned()))
+            .with_field("points", vec![[3.0, 4.0]]);
+
+        let built = registry.build(&friendly, Weak::new()).unwrap();
+        let described = registry.describe(&*built).unwrap();
+
+        assert_eq!(described.get_field("name"), friendly.get_field("name"));
+        assert_eq!(described.get_field("points"), friendly.get_field("points"));
+        assert_eq!(described.get_field("offset"), Some(&(0.5f32, 1).to_value()));
+
+        assert!(registry.build(&FriendlyComponent::new("missing"), Weak::new()).is_err());
+        assert!(registry.build(&FriendlyComponent::new("path").with_field("name", 1), Weak::new()).is_err());
+    }
+
+    #[test]
+    fn constructors_can_register_components() {
+        register_component::<Early>();
+
+        let early = FriendlyComponent::new("early").build(Weak::new()).unwrap();
+
+        assert!(early.as_any().is::<Early>());
+        assert!(with_registry(|registry| registry.get("late").is_some()));
+    }
+}
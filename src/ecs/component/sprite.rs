@@ -0,0 +1,206 @@
+use super::{Component, ComponentType};
+use super::reflect::Reflect;
+use super::Entity;
+use crate::asset::atlas::TextureAtlas;
+use crate::asset::handle::Handle;
+use crate::asset::server::AssetServer;
+use crate::time::Time;
+
+use std::any::Any;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+
+/// A named region of a texture atlas (see `asset::atlas`). The atlas is given by the path of its
+/// `.atlas` file, and loaded with `load_atlas`, after which `get_uvs` finds where the region is.
+/// The renderer doesn't draw sprites itself yet, but a custom pass (see `renderer::pass`) can,
+/// from the atlas texture and the UVs.
+///
+/// ```ignore
+/// FriendlyEntity::new("player")
+///     .with_component(FriendlyComponent::transform([0.0, 0.0, 0.0]))
+///     .with_component(FriendlyComponent::sprite("sprites.atlas", "player/idle_0"))
+///
+/// // ... then, on the live component
+/// sprite.load_atlas(&mut assets);
+/// let uvs = sprite.get_uvs(&assets);
+/// ```
+#[derive(Reflect)]
+pub struct Sprite {
+    #[reflect(skip)]
+    parent: Weak<Entity>,
+
+    /// The `.atlas` file, relative to the asset directory.
+    atlas: String,
+
+    /// The name of the region in the atlas.
+    region: String,
+
+    /// The colour the region is tinted (RGBA).
+    colour: [f32; 4],
+
+    /// The atlas, once `load_atlas` has been called. Reflection can change `atlas` without
+    /// clearing this, so it's only used while it's still to the same file.
+    #[reflect(skip)]
+    handle: Option<Handle<TextureAtlas>>,
+}
+
+impl Sprite {
+    /// Gets the path of the atlas.
+    pub fn get_atlas(&self) -> &str {
+        &self.atlas
+    }
+
+    /// Sets the path of the atlas. Call `load_atlas` again afterwards.
+    pub fn set_atlas(&mut self, atlas: &str) {
+        self.atlas = atlas.to_owned();
+        self.handle = None;
+    }
+
+    /// Gets the name of the region.
+    pub fn get_region(&self) -> &str {
+        &self.region
+    }
+
+    /// Sets the name of the region.
+    pub fn set_region(&mut self, region: &str) {
+        self.region = region.to_owned();
+    }
+
+    /// Gets the colour.
+    pub fn get_colour(&self) -> [f32; 4] {
+        self.colour
+    }
+
+    /// Sets the colour.
+    pub fn set_colour(&mut self, colour: [f32; 4]) {
+        self.colour = colour;
+    }
+
+    /// Gets the atlas, if it has been loaded.
+    pub fn get_handle(&self) -> Option<&Handle<TextureAtlas>> {
+        self.handle.as_ref()
+    }
+
+    /// Starts loading the atlas, if it isn't already (or the atlas has changed since). Loading the
+    /// same file twice hands back the same atlas, so sprites can share one.
+    pub fn load_atlas(&mut self, assets: &mut AssetServer) {
+        if self.get_current_handle(assets).is_none() && !self.atlas.is_empty() {
+            self.handle = Some(assets.load(&self.atlas));
+        }
+    }
+
+    /// Gets the top left and bottom right of the region, in UV coordinates. `None` until the atlas
+    /// has loaded, or if it doesn't have the region.
+    pub fn get_uvs(&self, assets: &AssetServer) -> Option<([f32; 2], [f32; 2])> {
+        assets.get(self.get_current_handle(assets)?)?.get_uvs(&self.region)
+    }
+
+    /// Gets the handle, unless it's to an atlas other than `atlas`.
+    fn get_current_handle(&self, assets: &AssetServer) -> Option<&Handle<TextureAtlas>> {
+        self.handle.as_ref().filter(|handle| assets.get_path(handle) == Some(Path::new(&self.atlas)))
+    }
+}
+
+impl Component for Sprite {
+    /// Creates a new, white, sprite, with no atlas.
+    fn from(parent: Weak<Entity>) -> Self {
+        Sprite {
+            parent,
+            atlas: "".to_owned(),
+            region: "".to_owned(),
+            colour: [1.0, 1.0, 1.0, 1.0],
+            handle: None,
+        }
+    }
+
+    fn type_of(&self) -> ComponentType {
+        ComponentType::Sprite
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_weak_parent(&self) -> &Weak<Entity> {
+        &self.parent
+    }
+
+    fn get_parent(&self) -> Rc<Entity> {
+        self.parent.upgrade().expect("dangling weak pointer to parent")
+    }
+
+    fn on_update(&mut self, _time: &Time) {
+
+    }
+
+    fn on_start(&mut self) {
+
+    }
+
+    fn on_stop(&mut self) {
+
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::FriendlyComponent;
+    use crate::ecs::component::reflect::ReflectValue;
+    use crate::ecs::component::registry;
+
+    #[test]
+    fn sprites_are_built_from_their_fields() {
+        let friendly = FriendlyComponent::sprite("sprites.atlas", "player/idle_0");
+        let component = friendly.build(Weak::new()).unwrap();
+        let sprite: &Sprite = component.as_any().downcast_ref().unwrap();
+
+        assert_eq!(sprite.get_atlas(), "sprites.atlas");
+        assert_eq!(sprite.get_region(), "player/idle_0");
+        assert!(sprite.get_handle().is_none());
+
+        assert_eq!(FriendlyComponent::describe(&*component).unwrap().get_field("region"), friendly.get_field("region"));
+    }
+
+    #[test]
+    fn sprites_have_no_uvs_until_their_atlas_loads() {
+        let mut sprite = <Sprite as Component>::from(Weak::new());
+        sprite.set_atlas("missing.atlas");
+        sprite.set_region("player");
+
+        let mut assets = AssetServer::new();
+        assert!(sprite.get_uvs(&assets).is_none());
+
+        sprite.load_atlas(&mut assets);
+        assert!(sprite.get_handle().is_some());
+        assert!(sprite.get_uvs(&assets).is_none());
+    }
+
+    #[test]
+    fn reflected_atlas_changes_load_the_new_atlas() {
+        let mut component = FriendlyComponent::sprite("old.atlas", "player").build(Weak::new()).unwrap();
+        let mut assets = AssetServer::new();
+
+        let sprite: &mut Sprite = component.as_mut_any().downcast_mut().unwrap();
+        sprite.load_atlas(&mut assets);
+        let old = sprite.get_handle().unwrap().get_id();
+
+        // As a scene reload, save restore or prefab update would
+        registry::with_registry(|registry| registry.set_path(&mut *component, "atlas", "new.atlas".to_owned().to_value())).unwrap();
+
+        let sprite: &mut Sprite = component.as_mut_any().downcast_mut().unwrap();
+        sprite.load_atlas(&mut assets);
+
+        let new = sprite.get_handle().unwrap().get_id();
+        assert_ne!(new, old);
+        assert_eq!(assets.get_path(sprite.get_handle().unwrap()), Some(Path::new("new.atlas")));
+
+        // Loading again keeps the same handle
+        sprite.load_atlas(&mut assets);
+        assert_eq!(sprite.get_handle().unwrap().get_id(), new);
+    }
+}
@@ -0,0 +1,143 @@
+use super::{Component, ComponentType};
+use super::reflect::Reflect;
+use super::Entity;
+use crate::time::Time;
+use crate::renderer::text::{Alignment, font::Font};
+
+use std::any::Any;
+use std::rc::{Rc, Weak};
+
+/// A block of text, drawn at the position of the entity's transform.
+#[derive(Reflect)]
+pub struct Text {
+    #[reflect(skip)]
+    parent: Weak<Entity>,
+
+    /// The text to draw.
+    text: String,
+
+    /// The font to draw it in. Nothing is drawn without one.
+    #[reflect(skip)]
+    font: Option<Font>,
+
+    /// The size of the text, in pixels.
+    size: f32,
+
+    /// The colour of the text (RGBA).
+    colour: [f32; 4],
+
+    /// How the lines are aligned.
+    alignment: Alignment,
+
+    /// Lines are wrapped (between words) if they would be wider than this, in pixels.
+    wrap_width: Option<f32>,
+}
+
+impl Text {
+    /// Gets the text.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Sets the text.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_owned();
+    }
+
+    /// Gets the font.
+    pub fn get_font(&self) -> Option<&Font> {
+        self.font.as_ref()
+    }
+
+    /// Sets the font.
+    pub fn set_font(&mut self, font: Option<Font>) {
+        self.font = font;
+    }
+
+    /// Gets the size, in pixels.
+    pub fn get_size(&self) -> f32 {
+        self.size
+    }
+
+    /// Sets the size, in pixels.
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size;
+    }
+
+    /// Gets the colour.
+    pub fn get_colour(&self) -> [f32; 4] {
+        self.colour
+    }
+
+    /// Sets the colour.
+    pub fn set_colour(&mut self, colour: [f32; 4]) {
+        self.colour = colour;
+    }
+
+    /// Gets the alignment.
+    pub fn get_alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    /// Sets the alignment.
+    pub fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    /// Gets the wrapping width, in pixels.
+    pub fn get_wrap_width(&self) -> Option<f32> {
+        self.wrap_width
+    }
+
+    /// Sets the wrapping width, in pixels. `None` never wraps.
+    pub fn set_wrap_width(&mut self, wrap_width: Option<f32>) {
+        self.wrap_width = wrap_width;
+    }
+}
+
+impl Component for Text {
+    /// Creates new, empty, white text.
+    fn from(parent: Weak<Entity>) -> Self {
+        Text {
+            parent,
+            text: "".to_owned(),
+            font: None,
+            size: 24.0,
+            colour: [1.0, 1.0, 1.0, 1.0],
+            alignment: Alignment::default(),
+            wrap_width: None,
+        }
+    }
+
+    fn type_of(&self) -> ComponentType {
+        ComponentType::Text
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_weak_parent(&self) -> &Weak<Entity> {
+        &self.parent
+    }
+
+    fn get_parent(&self) -> Rc<Entity> {
+        self.parent.upgrade().expect("dangling weak pointer to parent")
+    }
+
+    fn on_update(&mut self, _time: &Time) {
+
+    }
+
+    fn on_start(&mut self) {
+
+    }
+
+    fn on_stop(&mut self) {
+
+    }
+}
@@ -1,22 +1,115 @@
 use super::{Component, ComponentType};
+use super::reflect::Reflect;
 use super::Entity;
+use crate::time::Time;
 
+use cgmath::{Matrix4, Rad, Vector3};
+
+use std::any::Any;
 use std::rc::{Rc, Weak};
 
+#[derive(Reflect)]
 pub struct Transform {
+    #[reflect(skip)]
     parent: Weak<Entity>,
-    x: f64,
-    y: f64,
-    z: f64,
+
+    position: [f64; 3],
+
+    /// Rotation around the z axis, in radians.
+    rotation: f64,
+
+    /// Scale along each axis.
+    scale: [f64; 3],
+
+    /// The position, rotation and scale before the last simulation step. Used to interpolate
+    /// between the last two steps when rendering.
+    #[reflect(skip)]
+    previous: Option<([f64; 3], f64, [f64; 3])>,
+}
+
+impl Transform {
+    /// Gets the position.
+    pub fn get_position(&self) -> [f64; 3] {
+        self.position
+    }
+
+    /// Sets the position.
+    pub fn set_position(&mut self, x: f64, y: f64, z: f64) {
+        self.position = [x, y, z];
+    }
+
+    /// Gets the rotation around the z axis, in radians.
+    pub fn get_rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    /// Sets the rotation around the z axis, in radians.
+    pub fn set_rotation(&mut self, rotation: f64) {
+        self.rotation = rotation;
+    }
+
+    /// Gets the scale.
+    pub fn get_scale(&self) -> [f64; 3] {
+        self.scale
+    }
+
+    /// Sets the scale.
+    pub fn set_scale(&mut self, x: f64, y: f64, z: f64) {
+        self.scale = [x, y, z];
+    }
+
+    /// Builds the model matrix (scale, then rotate, then translate).
+    pub fn model_matrix(&self) -> Matrix4<f32> {
+        build_model_matrix(self.get_position(), self.rotation, self.scale)
+    }
+
+    /// Gets the position, blended between the last two simulation steps. An `alpha` of 0 is the
+    /// previous step, and 1 is the current one.
+    pub fn interpolated_position(&self, alpha: f64) -> [f64; 3] {
+        match &self.previous {
+            Some((position, _, _)) => lerp3(*position, self.get_position(), alpha),
+            None => self.get_position(),
+        }
+    }
+
+    /// Builds the model matrix, blended between the last two simulation steps. See
+    /// `interpolated_position`.
+    pub fn interpolated_model_matrix(&self, alpha: f64) -> Matrix4<f32> {
+        match &self.previous {
+            Some((position, rotation, scale)) => build_model_matrix(
+                lerp3(*position, self.get_position(), alpha),
+                rotation + (self.rotation - rotation) * alpha,
+                lerp3(*scale, self.scale, alpha),
+            ),
+            None => self.model_matrix(),
+        }
+    }
+}
+
+/// Builds a model matrix (scale, then rotate, then translate).
+fn build_model_matrix(position: [f64; 3], rotation: f64, scale: [f64; 3]) -> Matrix4<f32> {
+    Matrix4::from_translation(Vector3::new(position[0] as f32, position[1] as f32, position[2] as f32))
+        * Matrix4::from_angle_z(Rad(rotation as f32))
+        * Matrix4::from_nonuniform_scale(scale[0] as f32, scale[1] as f32, scale[2] as f32)
+}
+
+/// Linearly interpolates between two vectors.
+fn lerp3(a: [f64; 3], b: [f64; 3], alpha: f64) -> [f64; 3] {
+    [
+        a[0] + (b[0] - a[0]) * alpha,
+        a[1] + (b[1] - a[1]) * alpha,
+        a[2] + (b[2] - a[2]) * alpha,
+    ]
 }
 
 impl Component for Transform {
     fn from(parent: Weak<Entity>) -> Self {
         Transform {
             parent,
-            x: 420.0,
-            y: 69.0,
-            z: 516.0,
+            position: [0.0, 0.0, 0.0],
+            rotation: 0.0,
+            scale: [1.0, 1.0, 1.0],
+            previous: None,
         }
     }
 
@@ -24,6 +117,14 @@ impl Component for Transform {
         ComponentType::Transform
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn get_weak_parent(&self) -> &Weak<Entity> {
         &self.parent
     }
@@ -32,7 +133,7 @@ impl Component for Transform {
         self.parent.upgrade().expect("dangling weak pointer to parent")
     }
 
-    fn on_update(&mut self) {
+    fn on_update(&mut self, _time: &Time) {
 
     }
 
@@ -43,5 +144,8 @@ impl Component for Transform {
     fn on_stop(&mut self) {
 
     }
-}
 
+    fn store_previous(&mut self) {
+        self.previous = Some((self.get_position(), self.rotation, self.scale));
+    }
+}
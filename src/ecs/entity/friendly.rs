@@ -1,14 +1,120 @@
 use crate::ecs::{
-    component::FriendlyComponent,
+    scene::Scene,
+    entity::Entity,
+    component::{ComponentRef, FriendlyComponent, registry::ComponentRegistry},
 };
 
+use serde::{Serialize, Deserialize};
+use log::warn;
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+/// A named entity, and data-only descriptions of its components. See `FriendlyScene`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FriendlyEntity {
-    
+    name: String,
+
+    /// Matches the entity up with its live version when the scene is reloaded, even if it's been
+    /// renamed. Entities without one are matched by name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+
+    #[serde(default)]
+    components: Vec<FriendlyComponent>,
 }
 
 impl FriendlyEntity {
-    pub fn get_components() -> Vec<FriendlyComponent> {
-        vec![]
+    /// Creates an entity with no components.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            id: None,
+            components: vec![],
+        }
+    }
+
+    /// Gives the entity a stable ID.
+    pub fn with_id(self, id: &str) -> Self { Self { id: Some(id.to_owned()), .. self } }
+
+    /// Adds a component.
+    pub fn with_component(mut self, component: FriendlyComponent) -> Self {
+        self.components.push(component);
+        self
+    }
+
+    /// Adds a component.
+    pub fn push_component(&mut self, component: FriendlyComponent) {
+        self.components.push(component);
+    }
+
+    /// Gets the name of the entity.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Sets the name of the entity.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_owned();
+    }
+
+    /// Gets the stable ID of the entity, if it has one.
+    pub fn get_id(&self) -> Option<&String> {
+        self.id.as_ref()
     }
-}
 
+    /// Gets what the entity is matched up by when its scene is reloaded: its ID, or its name.
+    pub fn get_key(&self) -> &String {
+        self.id.as_ref().unwrap_or(&self.name)
+    }
+
+    /// Gets the components of the entity.
+    pub fn get_components(&self) -> &Vec<FriendlyComponent> {
+        &self.components
+    }
+
+    /// Gets mutable references to the components of the entity.
+    pub fn get_mut_components(&mut self) -> &mut Vec<FriendlyComponent> {
+        &mut self.components
+    }
+
+    /// Describes a live entity. Components that aren't registered are left out.
+    pub fn from_entity(entity: &Entity, registry: &ComponentRegistry) -> Self {
+        Self {
+            name: entity.get_name().clone(),
+            id: entity.get_id().cloned(),
+            components: entity.get_weak_components().iter()
+                .filter_map(Weak::upgrade)
+                .filter_map(|component| registry.describe(&**component.borrow()))
+                .collect(),
+        }
+    }
+
+    /// Builds the live entity, in `scene`, adding its components to `components`. Components that
+    /// can't be built are left out.
+    pub(crate) fn build(&self, scene: Weak<Scene>, components: &mut Vec<ComponentRef>, registry: &ComponentRegistry) -> Rc<Entity> {
+        // Components point back at their entity, and the entity at its components, so the
+        // components have to be made while the entity is
+        Rc::new_cyclic(|parent| {
+            let entity = Entity::new(scene, &self.name).with_id(self.id.clone());
+
+            for friendly in &self.components {
+                let component = match registry.build(friendly, parent.clone()) {
+                    Ok(component) => component,
+                    Err(e) => {
+                        warn!("Leaving a component out of entity {}: {}", self.name, e);
+
+                        continue;
+                    },
+                };
+
+                let component: ComponentRef = Rc::new(RefCell::new(component));
+
+                entity.push_component(Rc::downgrade(&component));
+                components.push(component);
+            }
+
+            entity
+        })
+    }
+}
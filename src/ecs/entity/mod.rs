@@ -1,10 +1,12 @@
 pub mod friendly;
+pub mod reference;
 
-use std::rc::Weak;
+use std::cell::{Ref, RefCell};
+use std::rc::{Rc, Weak};
 
 use crate::ecs::{
     scene::Scene,
-    component::Component,
+    component::{ComponentRef, ComponentType, WeakComponentRef},
 };
 
 /// A component that belongs to an entity.
@@ -13,11 +15,14 @@ use crate::ecs::{
 pub struct Entity {
     scene: Weak<Scene>,
 
-    /// The name of the entity.
-    name: String,
+    /// The name of the entity. Can change when its scene is reloaded.
+    name: RefCell<String>,
+
+    /// An ID that stays the same when the entity is renamed (in a scene file, say).
+    id: Option<String>,
 
     /// The IDs of the components in the scene that "belong" to the entity.
-    components: Vec<Weak<Box<dyn Component>>>,
+    components: RefCell<Vec<WeakComponentRef>>,
 }
 
 impl Entity {
@@ -25,29 +30,67 @@ impl Entity {
     pub fn new(scene: Weak<Scene>, name: &str) -> Self {
         Self {
             scene,
-            name: name.to_owned(),
-            components: vec![],
+            name: RefCell::new(name.to_owned()),
+            id: None,
+            components: RefCell::new(vec![]),
         }
     }
 
+    /// Sets the stable ID of the entity.
+    pub fn with_id(self, id: Option<String>) -> Self { Self { id, .. self } }
+
     /// Gets the scene that the entity belongs to.
     pub fn get_scene(&self) -> &Weak<Scene> {
         &self.scene
     }
 
     /// Gets the name of the entity.
-    pub fn get_name(&self) -> &String {
-        &self.name
+    pub fn get_name(&self) -> Ref<'_, String> {
+        self.name.borrow()
+    }
+
+    /// Renames the entity.
+    pub fn set_name(&self, name: &str) {
+        *self.name.borrow_mut() = name.to_owned();
+    }
+
+    /// Gets the stable ID of the entity, if it has one.
+    pub fn get_id(&self) -> Option<&String> {
+        self.id.as_ref()
+    }
+
+    /// Is this what the entity is matched up by when its scene is reloaded (or a save game is
+    /// loaded): its ID, or its name if it doesn't have one.
+    pub fn has_key(&self, key: &str) -> bool {
+        match &self.id {
+            Some(id) => id == key,
+            None => *self.name.borrow() == key,
+        }
     }
 
     /// Gets weak references to every component that is "attached" to this entity.
-    pub fn get_weak_components(&self) -> &Vec<Weak<Box<dyn Component>>> {
-        &self.components
+    pub fn get_weak_components(&self) -> Ref<'_, Vec<WeakComponentRef>> {
+        self.components.borrow()
+    }
+
+    /// Finds the first (still alive) component of a type that is attached to this entity.
+    pub fn find_component(&self, component_type: ComponentType) -> Option<ComponentRef> {
+        self.components.borrow().iter()
+            .filter_map(Weak::upgrade)
+            .find(|component| component.borrow().type_of() == component_type)
     }
 
     /// Pushes a component onto the entity. The weak reference should be taken from the reference
     /// counter that is contained within the parent scene.
-    pub fn push_component(&mut self, component: Weak<Box<dyn Component>>) {
-        self.components.push(component);
+    pub fn push_component(&self, component: WeakComponentRef) {
+        self.components.borrow_mut().push(component);
+    }
+
+    /// Detaches a component from the entity (along with any that have been dropped). The
+    /// component stays in the scene until it's taken out of there too.
+    pub fn remove_component(&self, component: &ComponentRef) {
+        self.components.borrow_mut().retain(|weak| {
+            weak.upgrade().is_some_and(|attached| !Rc::ptr_eq(&attached, component))
+        });
     }
 }
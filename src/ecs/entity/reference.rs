@@ -0,0 +1,96 @@
+//! References from components to other entities.
+//!
+//! A component that needs to point at another entity (a homing missile's target, say) should use
+//! an `EntityRef` field, rather than a bare `Weak<Entity>`. They can be reflected, so save games
+//! (see `scene::save`) can write them down, and point them at the right entities when the save is
+//! loaded. Anywhere else (scene files, say) they're written as nothing, and read as nothing.
+
+use super::Entity;
+use crate::ecs::component::reflect::{FieldType, ReflectValue, Value};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::{Rc, Weak};
+
+/// A weak reference to an entity, that survives being saved and loaded.
+#[derive(Debug, Clone, Default)]
+pub struct EntityRef(Weak<Entity>);
+
+impl EntityRef {
+    /// Refers to an entity.
+    pub fn new(entity: &Rc<Entity>) -> Self {
+        Self(Rc::downgrade(entity))
+    }
+
+    /// Refers to nothing.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Gets the entity, if it's still alive.
+    pub fn get(&self) -> Option<Rc<Entity>> {
+        self.0.upgrade()
+    }
+
+    /// Does it refer to the same entity as `entity`.
+    pub fn is(&self, entity: &Rc<Entity>) -> bool {
+        std::ptr::eq(self.0.as_ptr(), Rc::as_ptr(entity))
+    }
+}
+
+/// How references are written down (or read back) while a save game is being made (or loaded).
+pub(crate) enum EntityMapping {
+    /// Each saved entity, to its place in the save.
+    Saving(HashMap<*const Entity, usize>),
+
+    /// Each place in the save, to the entity it was loaded as.
+    Loading(Vec<Weak<Entity>>),
+}
+
+thread_local! {
+    static MAPPING: RefCell<Option<EntityMapping>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with references written down (or read back) through `mapping`.
+pub(crate) fn with_mapping<R, F: FnOnce() -> R>(mapping: EntityMapping, f: F) -> R {
+    MAPPING.with(|current| *current.borrow_mut() = Some(mapping));
+    let result = f();
+    MAPPING.with(|current| *current.borrow_mut() = None);
+
+    result
+}
+
+impl ReflectValue for EntityRef {
+    fn field_type() -> FieldType {
+        FieldType::Entity
+    }
+
+    /// The entity's place in the save being made, or nothing.
+    fn to_value(&self) -> Value {
+        MAPPING.with(|mapping| match &*mapping.borrow() {
+            Some(EntityMapping::Saving(places)) => places.get(&self.0.as_ptr())
+                .map(|place| Value::Int(*place as i64))
+                .unwrap_or(Value::None),
+            _ => Value::None,
+        })
+    }
+
+    /// The entity loaded at a place in the save being loaded. Places that can't be found (or
+    /// aren't being loaded) refer to nothing.
+    fn from_value(value: &Value) -> Option<Self> {
+        let place = match *value {
+            Value::None => return Some(Self::none()),
+            Value::Int(place) => place,
+            _ => return None,
+        };
+
+        MAPPING.with(|mapping| match &*mapping.borrow() {
+            Some(EntityMapping::Loading(entities)) => Some(usize::try_from(place).ok()
+                .and_then(|place| entities.get(place))
+                .map(|entity| Self(entity.clone()))
+                .unwrap_or_default()),
+            _ => Some(Self::none()),
+        })
+    }
+}
@@ -0,0 +1,295 @@
+//! Scene files.
+//!
+//! Scenes can be written by hand (or by tools) as RON or JSON, and loaded as a `FriendlyScene`.
+//! Each entity lists its components by the name they're registered as (see
+//! `component::registry`), along with their fields. Fields that are left out get their defaults.
+//! Components are saved as maps (`{"type": "mesh", ...}`), but RON's structure syntax is easier
+//! to write by hand, and works too.
+//!
+//! ```ignore
+//! // pong.ron
+//! (
+//!     version: 1,
+//!     name: "pong",
+//!     entities: [
+//!         (
+//!             name: "ball",
+//!             components: [
+//!                 (type: "transform", position: (0.0, 0.0, 0.0)),
+//!                 (type: "mesh", colour: (1.0, 1.0, 1.0, 1.0)),
+//!             ],
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! ```ignore
+//! let scene: Scene = FriendlyScene::load("scenes/pong.ron")?.into();
+//! ```
+//!
+//! Entities can also be given an `id`, which keeps them matched up with their live versions when
+//! the file is reloaded after they've been renamed (see `scene::reload`).
+//!
+//! Every file has a format version. Files without one are taken to be version 1, and files from
+//! newer versions of the engine are refused rather than half loaded. Changes to the format only
+//! ever add fields with defaults, so older files keep loading; anything that can't be done that
+//! way bumps `FORMAT_VERSION`, and gets upgraded in `SceneFile::upgrade`.
+
+use super::friendly::FriendlyScene;
+use super::prefab::PrefabInstance;
+use crate::ecs::entity::friendly::FriendlyEntity;
+use crate::error::Error as GompError;
+use crate::versioned;
+
+use serde::{Serialize, Deserialize, Deserializer};
+use log::debug;
+
+use std::path::Path;
+
+/// The newest scene format version this engine can read (and the one it writes).
+pub const FORMAT_VERSION: u32 = 1;
+
+/// How deep RON files are laid out over several lines: down to each field of a component, with
+/// the field's value on one line.
+pub(super) const COMPONENT_DEPTH: usize = 5;
+
+/// What a scene file is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneFormat {
+    Ron,
+    Json,
+}
+
+impl SceneFormat {
+    /// Works out the format from a file's extension (`.ron` or `.json`).
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "ron" => Some(Self::Ron),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A scene file, as it's read.
+#[derive(Debug, Deserialize)]
+struct SceneFile {
+    #[serde(default = "legacy_version", deserialize_with = "deserialize_version")]
+    version: u32,
+    name: String,
+    #[serde(default)]
+    entities: Vec<FriendlyEntity>,
+    #[serde(default)]
+    prefabs: Vec<PrefabInstance>,
+}
+
+/// A scene file, as it's written.
+#[derive(Debug, Serialize)]
+struct SceneFileRef<'a> {
+    version: u32,
+    name: &'a str,
+    entities: &'a [FriendlyEntity],
+    #[serde(skip_serializing_if = "<[PrefabInstance]>::is_empty")]
+    prefabs: &'a [PrefabInstance],
+}
+
+impl SceneFile {
+    /// Brings a file from an older version of the format up to date, a version at a time. There
+    /// is only one version so far, so there's nothing to do yet.
+    fn upgrade(self) -> Self {
+        if self.version < FORMAT_VERSION {
+            debug!("Upgrading scene {} from format version {}", self.name, self.version);
+        }
+
+        Self { version: FORMAT_VERSION, .. self }
+    }
+
+    fn into_scene(self) -> FriendlyScene {
+        let file = self.upgrade();
+        let mut scene = FriendlyScene::new(&file.name);
+
+        for entity in file.entities {
+            scene.push_entity(entity);
+        }
+
+        for instance in file.prefabs {
+            scene.push_prefab(instance);
+        }
+
+        scene
+    }
+}
+
+/// Files from before versioning.
+fn legacy_version() -> u32 {
+    1
+}
+
+fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    versioned::deserialize_version(deserializer, "scene", FORMAT_VERSION)
+}
+
+impl FriendlyScene {
+    /// Loads a scene file, in the format given by its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GompError> {
+        let path = path.as_ref();
+        let format = SceneFormat::from_path(path).ok_or_else(|| unknown_format(path))?;
+
+        Self::from_str(&std::fs::read_to_string(path)?, format, &path.display().to_string())
+    }
+
+    /// Saves the scene to a file, in the format given by its extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GompError> {
+        let path = path.as_ref();
+        let format = SceneFormat::from_path(path).ok_or_else(|| unknown_format(path))?;
+
+        std::fs::write(path, self.to_string(format, &path.display().to_string())?)?;
+
+        Ok(())
+    }
+
+    /// Reads a scene from RON.
+    pub fn from_ron(source: &str) -> Result<Self, GompError> {
+        Self::from_str(source, SceneFormat::Ron, "<ron>")
+    }
+
+    /// Reads a scene from JSON.
+    pub fn from_json(source: &str) -> Result<Self, GompError> {
+        Self::from_str(source, SceneFormat::Json, "<json>")
+    }
+
+    /// Writes the scene as (pretty) RON.
+    pub fn to_ron(&self) -> Result<String, GompError> {
+        self.to_string(SceneFormat::Ron, "<ron>")
+    }
+
+    /// Writes the scene as (pretty) JSON.
+    pub fn to_json(&self) -> Result<String, GompError> {
+        self.to_string(SceneFormat::Json, "<json>")
+    }
+
+    /// Reads a scene. `file` is only used in errors.
+    fn from_str(source: &str, format: SceneFormat, file: &str) -> Result<Self, GompError> {
+        let scene_file: SceneFile = match format {
+            SceneFormat::Ron => ron_options().from_str(source).map_err(|e| GompError::InvalidScene {
+                file: file.to_owned(),
+                line: e.position.line,
+                column: e.position.col,
+                message: e.code.to_string(),
+            })?,
+            SceneFormat::Json => serde_json::from_str(source).map_err(|e| {
+                // serde_json puts the position on the end of its messages
+                let message = e.to_string();
+                let position = format!(" at line {} column {}", e.line(), e.column());
+
+                GompError::InvalidScene {
+                    file: file.to_owned(),
+                    line: e.line(),
+                    column: e.column(),
+                    message: message.strip_suffix(&position).unwrap_or(&message).to_owned(),
+                }
+            })?,
+        };
+
+        Ok(scene_file.into_scene())
+    }
+
+    /// Writes the scene. `file` is only used in errors.
+    fn to_string(&self, format: SceneFormat, file: &str) -> Result<String, GompError> {
+        let scene_file = SceneFileRef {
+            version: FORMAT_VERSION,
+            name: self.get_name(),
+            entities: self.get_entities(),
+            prefabs: self.get_prefabs(),
+        };
+
+        // Writing doesn't have a position, so errors point at the start of the file
+        let error = |message: String| GompError::InvalidScene {
+            file: file.to_owned(),
+            line: 1,
+            column: 1,
+            message,
+        };
+
+        match format {
+            SceneFormat::Ron => ron::ser::to_string_pretty(&scene_file, ron::ser::PrettyConfig::new().depth_limit(COMPONENT_DEPTH))
+                .map_err(|e| error(e.to_string())),
+            SceneFormat::Json => serde_json::to_string_pretty(&scene_file)
+                .map_err(|e| error(e.to_string())),
+        }
+    }
+}
+
+/// Optional fields (an entity's `id`, say) can be written without `Some(..)` around them.
+pub(super) fn ron_options() -> ron::Options {
+    ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+}
+
+fn unknown_format(path: &Path) -> GompError {
+    GompError::InvalidScene {
+        file: path.display().to_string(),
+        line: 1,
+        column: 1,
+        message: "unknown scene format (expected a .ron or .json file)".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::FriendlyComponent;
+
+    fn scene() -> FriendlyScene {
+        FriendlyScene::new("pong")
+            .with_entity(FriendlyEntity::new("ball")
+                .with_id("ball-1")
+                .with_component(FriendlyComponent::transform([1.0, -2.5, 0.0]))
+                .with_component(FriendlyComponent::mesh([1.0, 0.5, 0.25, 1.0])))
+            .with_entity(FriendlyEntity::new("score")
+                .with_component(FriendlyComponent::text("0 - 0", 24.0)))
+            .with_prefab(PrefabInstance::new("left_paddle", "prefabs/paddle.ron")
+                .with_override("paddle", FriendlyComponent::transform([-5.0, 0.0, 0.0])))
+    }
+
+    #[test]
+    fn scenes_round_trip_through_ron() {
+        let scene = scene();
+
+        assert_eq!(FriendlyScene::from_ron(&scene.to_ron().unwrap()).unwrap(), scene);
+    }
+
+    #[test]
+    fn scenes_round_trip_through_json() {
+        let scene = scene();
+
+        assert_eq!(FriendlyScene::from_json(&scene.to_json().unwrap()).unwrap(), scene);
+    }
+
+    #[test]
+    fn files_without_a_version_load() {
+        let scene = FriendlyScene::from_ron(r#"(name: "empty")"#).unwrap();
+
+        assert_eq!(scene, FriendlyScene::new("empty"));
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let ron = format!(r#"(version: {}, name: "future")"#, FORMAT_VERSION + 1);
+        let json = format!(r#"{{"version": {}, "name": "future"}}"#, FORMAT_VERSION + 1);
+
+        for result in [FriendlyScene::from_ron(&ron), FriendlyScene::from_json(&json)] {
+            match result {
+                Err(GompError::InvalidScene { line: 1, message, .. }) => assert!(message.contains("newer"), "{}", message),
+                other => panic!("expected a version error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn errors_say_where_they_are() {
+        match FriendlyScene::from_json("{\n  \"name\": 5\n}") {
+            Err(GompError::InvalidScene { file, line: 2, .. }) => assert_eq!(file, "<json>"),
+            other => panic!("expected a scene error, got {:?}", other),
+        }
+    }
+}
@@ -1,18 +1,38 @@
 use crate::ecs::{
-    scene::Scene,
-    entity::friendly::FriendlyEntity,
-    component::Component,
+    scene::{Scene, prefab::PrefabInstance},
+    entity::{Entity, friendly::FriendlyEntity},
+    component::registry,
 };
 
-use std::rc::Rc;
+use log::warn;
+
+use std::rc::{Rc, Weak};
 
 /// Never once is this used for actual rendering and updating inside the inner workings of the game
 /// engine. The only purpose that this struct has is to act as a transport medium between the
 /// optimised Scene, and the file/builder pattern that specifies (in more human readable terms) the
 /// layout of a scene.
+///
+/// Turn one into a `Scene` with `Scene::from` (or into a shared one, that its entities point back
+/// at, with `into_shared`), and back again with `Scene::to_friendly`. Scene
+/// files are read and written with `FriendlyScene::load` and `FriendlyScene::save` (see
+/// `scene::file`).
+/// Scenes with prefab instances have to be built with a `PrefabLibrary` (see `scene::prefab`).
+///
+/// # Examples
+///
+/// ```ignore
+/// let scene: Scene = FriendlyScene::new("pong")
+///     .with_entity(FriendlyEntity::new("ball")
+///         .with_component(FriendlyComponent::transform([0.0, 0.0, 0.0]))
+///         .with_component(FriendlyComponent::mesh([1.0, 1.0, 1.0, 1.0])))
+///     .into();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
 pub struct FriendlyScene {
     name: String,
     entities: Vec<FriendlyEntity>,
+    prefabs: Vec<PrefabInstance>,
 }
 
 impl FriendlyScene {
@@ -20,26 +40,156 @@ impl FriendlyScene {
         Self {
             name: name.to_owned(),
             entities: vec![],
+            prefabs: vec![],
         }
     }
+
+    /// Adds an entity.
+    pub fn with_entity(mut self, entity: FriendlyEntity) -> Self {
+        self.entities.push(entity);
+        self
+    }
+
+    /// Adds an entity.
+    pub fn push_entity(&mut self, entity: FriendlyEntity) {
+        self.entities.push(entity);
+    }
+
+    /// Gets the name of the scene.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Gets the entities in the scene.
+    pub fn get_entities(&self) -> &Vec<FriendlyEntity> {
+        &self.entities
+    }
+
+    /// Adds a prefab instance.
+    pub fn with_prefab(mut self, instance: PrefabInstance) -> Self {
+        self.prefabs.push(instance);
+        self
+    }
+
+    /// Adds a prefab instance.
+    pub fn push_prefab(&mut self, instance: PrefabInstance) {
+        self.prefabs.push(instance);
+    }
+
+    /// Gets the prefab instances in the scene.
+    pub fn get_prefabs(&self) -> &Vec<PrefabInstance> {
+        &self.prefabs
+    }
+
+    /// Builds the scene like `Scene::from`, but shared, so `Entity::get_scene` leads back to it.
+    pub fn into_shared(self) -> Rc<Scene> {
+        Rc::new_cyclic(|this| Scene::build(self, this.clone()))
+    }
 }
 
 impl From<FriendlyScene> for Scene {
-    /// Morphs a FriendlyScene into a Scene.
+    /// Morphs a FriendlyScene into a Scene, with this thread's component registry. Components that
+    /// aren't registered (or have bad fields) are left out, with a warning, and so are prefab
+    /// instances (use `PrefabLibrary::build_scene` for those). The components aren't started; that
+    /// happens when the scene enters the scene stack.
     fn from(w: FriendlyScene) -> Scene {
+        Scene::build(w, Weak::new())
+    }
+}
+
+impl Scene {
+    /// Builds a scene whose entities point back at `this`.
+    fn build(w: FriendlyScene, this: Weak<Scene>) -> Scene {
         let mut scene = Scene::new(&w.name);
-        let mut components: Vec<Rc<Box<dyn Component>>> = vec![];
+        scene.this = this;
+
+        if !w.prefabs.is_empty() {
+            warn!("Leaving {} prefab instances out of scene {}, build it with a PrefabLibrary", w.prefabs.len(), w.name);
+        }
 
         // Slow, but what can you do?
-        for entity in w.entities {
-            for friendly in entity.get_components() {
-                components.push(Rc::new(**friendly));
+        for entity in &w.entities {
+            scene.spawn(entity);
+        }
+
+        scene
+    }
+
+    /// Snapshots the scene as a FriendlyScene. Only components attached to an entity, that are in
+    /// this thread's component registry, are kept. Prefab instances are snapshotted as the
+    /// entities they spawned.
+    pub fn to_friendly(&self) -> FriendlyScene {
+        registry::with_registry(|registry| FriendlyScene {
+            name: self.get_name().clone(),
+            entities: self.get_entities().iter().map(|entity| FriendlyEntity::from_entity(entity, registry)).collect(),
+            prefabs: vec![],
+        })
+    }
+
+    /// Adds an entity (and its components) to the scene. If the scene has started, so do the
+    /// components.
+    pub fn spawn(&mut self, entity: &FriendlyEntity) -> Rc<Entity> {
+        let first = self.components.len();
+        let entity = registry::with_registry(|registry| entity.build(self.this.clone(), &mut self.components, registry));
+
+        if self.started.get() {
+            for component in &self.components[first..] {
+                component.borrow_mut().on_start();
             }
         }
 
-        scene.set_components(components);
+        self.entities.push(Rc::clone(&entity));
 
-        todo!()
+        entity
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::FriendlyComponent;
+
+    fn pong() -> FriendlyScene {
+        FriendlyScene::new("pong")
+            .with_entity(FriendlyEntity::new("ball").with_id("ball-1")
+                .with_component(FriendlyComponent::transform([0.5, 0.0, 0.0]))
+                .with_component(FriendlyComponent::mesh([1.0, 1.0, 1.0, 1.0])))
+            .with_entity(FriendlyEntity::new("paddle")
+                .with_component(FriendlyComponent::transform([-1.0, 0.0, 0.0])))
+    }
+
+    #[test]
+    fn scenes_survive_a_round_trip() {
+        let scene = Scene::from(pong());
+
+        assert_eq!(scene.get_entities().len(), 2);
+        assert_eq!(scene.get_components().len(), 3);
+
+        // Every component points back at its entity
+        for entity in scene.get_entities() {
+            for component in entity.get_weak_components().iter().filter_map(Weak::upgrade) {
+                assert!(Rc::ptr_eq(&component.borrow().get_parent(), entity));
+            }
+        }
+
+        let friendly = scene.to_friendly();
+        assert_eq!(friendly.get_name(), "pong");
+        assert_eq!(friendly.get_entities()[0].get_id().map(String::as_str), Some("ball-1"));
+        assert_eq!(Scene::from(friendly.clone()).to_friendly(), friendly);
+
+        let position = friendly.get_entities()[1].get_components()[0].get_field("position");
+        assert_eq!(position, pong().get_entities()[1].get_components()[0].get_field("position"));
+    }
+
+    #[test]
+    fn entities_point_back_at_shared_scenes() {
+        let scene = pong().into_shared();
+
+        for entity in scene.get_entities() {
+            assert!(Rc::ptr_eq(&entity.get_scene().upgrade().unwrap(), &scene));
+        }
+
+        // Scenes that aren't shared have nothing to point at
+        assert!(Scene::from(pong()).get_entities()[0].get_scene().upgrade().is_none());
+    }
+}
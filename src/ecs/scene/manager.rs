@@ -0,0 +1,489 @@
+//! The scene manager.
+//!
+//! Scenes live on a stack. Only the scene on top is active: it's the one that gets updated, and the
+//! one systems are given. Pushing a scene pauses the one under it (its components aren't updated,
+//! but they aren't stopped either), and popping it resumes the one under it again. This makes the
+//! usual title → game → pause menu flow straightforward.
+//!
+//! Scenes under an overlay scene (`Scene::with_overlay`) are still drawn, under it, so a pause menu
+//! can show the paused game behind it.
+//!
+//! A scene's components are started (then its `on_enter` hook is called) when it enters the stack,
+//! and its `on_exit` hook is called (then its components are stopped) when it leaves. Being covered
+//! or uncovered by another scene doesn't count.
+//!
+//! Changes are sent as `SceneChange` events, and happen at the end of the frame. They can have a
+//! `Transition`, which covers the screen (with a fade or a wipe), swaps the scenes while it's fully
+//! covered, then uncovers it again. Changes sent during a transition wait for it to finish.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! // On the title screen
+//! let fade = Transition::fade([0.0, 0.0, 0.0], Duration::from_millis(500));
+//! events.send(SceneChange::replace(game_scene()).with_transition(fade));
+//!
+//! // In game
+//! if actions.just_pressed("pause") {
+//!     events.send(SceneChange::push(Scene::new("pause").with_overlay(true)));
+//! }
+//!
+//! // In the pause menu
+//! if actions.just_pressed("resume") {
+//!     events.send(SceneChange::pop());
+//! }
+//! ```
+
+use super::Scene;
+use crate::application::resources::Resources;
+use crate::renderer::cover::Cover;
+
+use log::{debug, warn};
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Which way a wipe moves across the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// How a transition covers the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// Fades to a colour, then back again.
+    Fade,
+
+    /// A colour slides across the screen, then carries on off the other side.
+    Wipe(WipeDirection),
+}
+
+/// How to get from one scene to another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub colour: [f32; 3],
+
+    /// How long the whole transition takes. The scenes are swapped halfway through.
+    pub duration: Duration,
+}
+
+impl Transition {
+    /// A fade to a colour and back.
+    pub fn fade(colour: [f32; 3], duration: Duration) -> Self {
+        Self {
+            kind: TransitionKind::Fade,
+            colour,
+            duration,
+        }
+    }
+
+    /// A wipe across the screen.
+    pub fn wipe(direction: WipeDirection, colour: [f32; 3], duration: Duration) -> Self {
+        Self {
+            kind: TransitionKind::Wipe(direction),
+            colour,
+            duration,
+        }
+    }
+
+    /// Gets how the screen is covered `progress` (0 to 1) of the way through the transition.
+    pub fn get_cover(&self, progress: f32) -> Cover {
+        let progress = progress.clamp(0.0, 1.0);
+        let covering = progress < 0.5;
+
+        // How much of the screen is covered, peaking halfway through
+        let amount = 1.0 - (2.0 * progress - 1.0).abs();
+
+        // How far a wipe has come in from (or gone out of) an edge, in clip space
+        let from_low = -1.0 + 2.0 * amount;
+        let from_high = 1.0 - 2.0 * amount;
+
+        match self.kind {
+            TransitionKind::Fade => Cover::full_screen(self.colour, amount),
+            TransitionKind::Wipe(direction) => {
+                // Wipes come in from one edge, and go out of the opposite one
+                let ([min_x, max_x], [min_y, max_y]) = match (direction, covering) {
+                    (WipeDirection::Left, true) | (WipeDirection::Right, false) => ([from_high, 1.0], [-1.0, 1.0]),
+                    (WipeDirection::Left, false) | (WipeDirection::Right, true) => ([-1.0, from_low], [-1.0, 1.0]),
+                    (WipeDirection::Up, true) | (WipeDirection::Down, false) => ([-1.0, 1.0], [-1.0, from_low]),
+                    (WipeDirection::Up, false) | (WipeDirection::Down, true) => ([-1.0, 1.0], [from_high, 1.0]),
+                };
+
+                Cover {
+                    min: [min_x, min_y],
+                    max: [max_x, max_y],
+                    colour: self.colour,
+                    opacity: 1.0,
+                }
+            },
+        }
+    }
+}
+
+/// What to do to the scene stack.
+#[derive(Debug)]
+pub enum SceneCommand {
+    /// Puts a scene on top, pausing the one that was there.
+    Push(Scene),
+
+    /// Takes the top scene off, resuming the one under it. The last scene can't be popped.
+    Pop,
+
+    /// Swaps the top scene for another.
+    Replace(Scene),
+}
+
+/// A change to the scene stack, sent as an event (see the module docs).
+#[derive(Debug)]
+pub struct SceneChange {
+    pub command: SceneCommand,
+    pub transition: Option<Transition>,
+}
+
+impl SceneChange {
+    /// Puts a scene on top of the stack.
+    pub fn push(scene: Scene) -> Self {
+        Self::new(SceneCommand::Push(scene))
+    }
+
+    /// Takes the top scene off the stack.
+    pub fn pop() -> Self {
+        Self::new(SceneCommand::Pop)
+    }
+
+    /// Swaps the top scene for another.
+    pub fn replace(scene: Scene) -> Self {
+        Self::new(SceneCommand::Replace(scene))
+    }
+
+    /// Sets the transition to use.
+    pub fn with_transition(self, transition: Transition) -> Self { Self { transition: Some(transition), .. self } }
+
+    fn new(command: SceneCommand) -> Self {
+        Self {
+            command,
+            transition: None,
+        }
+    }
+}
+
+/// A transition that is under way.
+#[derive(Debug)]
+struct ActiveTransition {
+    transition: Transition,
+    elapsed: Duration,
+
+    /// Applied halfway through, once the screen is covered.
+    command: Option<SceneCommand>,
+}
+
+/// The scene stack.
+#[derive(Debug)]
+pub struct SceneManager {
+    /// Bottom first. Never empty.
+    stack: Vec<Scene>,
+
+    /// Changes waiting for the current transition to finish.
+    pending: VecDeque<SceneChange>,
+    transition: Option<ActiveTransition>,
+
+    /// Was a transition covering the screen when the cover was last looked at.
+    covering: bool,
+}
+
+impl SceneManager {
+    /// Creates a scene stack with a single scene in it.
+    pub fn new(scene: Scene) -> Self {
+        Self {
+            stack: vec![scene],
+            pending: VecDeque::new(),
+            transition: None,
+            covering: false,
+        }
+    }
+
+    /// Gets the active (top) scene.
+    pub fn get_active(&self) -> &Scene {
+        self.stack.last().expect("the scene stack is never empty")
+    }
+
+    /// Gets a mutable reference to the active (top) scene.
+    pub fn get_mut_active(&mut self) -> &mut Scene {
+        self.stack.last_mut().expect("the scene stack is never empty")
+    }
+
+    /// Gets every scene on the stack, bottom first.
+    pub fn get_scenes(&self) -> impl Iterator<Item = &Scene> {
+        self.stack.iter()
+    }
+
+    /// Gets every scene on the stack, bottom first, to change.
+    pub(crate) fn get_mut_scenes(&mut self) -> impl Iterator<Item = &mut Scene> {
+        self.stack.iter_mut()
+    }
+
+    /// How many scenes are on the stack.
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Is the stack empty. It never is, but clippy insists.
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Is a transition under way.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Queues up a change, to happen on the next update.
+    pub fn queue(&mut self, change: SceneChange) {
+        self.pending.push_back(change);
+    }
+
+    /// Gets how the screen is covered by the current transition, if there is one.
+    pub fn get_cover(&self) -> Option<Cover> {
+        self.transition.as_ref().map(|active| {
+            let progress = active.elapsed.as_secs_f32() / active.transition.duration.as_secs_f32();
+
+            active.transition.get_cover(progress)
+        })
+    }
+
+    /// Moves any transition along by `delta`, and applies whatever changes are due.
+    pub fn update(&mut self, delta: Duration, resources: &mut Resources) {
+        if let Some(active) = &mut self.transition {
+            active.elapsed += delta;
+
+            let due = active.elapsed >= active.transition.duration / 2;
+            let finished = active.elapsed >= active.transition.duration;
+            let command = if due { active.command.take() } else { None };
+
+            if let Some(command) = command {
+                self.apply(command, resources);
+            }
+
+            if finished {
+                self.transition = None;
+            }
+        }
+
+        while self.transition.is_none() {
+            let change = match self.pending.pop_front() {
+                Some(change) => change,
+                None => break,
+            };
+
+            match change.transition {
+                Some(transition) if transition.duration > Duration::ZERO => {
+                    self.transition = Some(ActiveTransition {
+                        transition,
+                        elapsed: Duration::ZERO,
+                        command: Some(change.command),
+                    });
+                },
+                _ => self.apply(change.command, resources),
+            }
+        }
+    }
+
+    /// Gets the cover to draw, if it changed: the transition's cover while there is one, then
+    /// `None` once, when it finishes.
+    pub(crate) fn take_cover_update(&mut self) -> Option<Option<Cover>> {
+        let cover = self.get_cover();
+        let was_covering = std::mem::replace(&mut self.covering, cover.is_some());
+
+        (cover.is_some() || was_covering).then_some(cover)
+    }
+
+    /// Enters every scene on the stack, bottom first. Called when the application starts.
+    pub(crate) fn start(&mut self, resources: &mut Resources) {
+        for scene in &mut self.stack {
+            scene.enter(resources);
+        }
+    }
+
+    /// Exits every scene on the stack, top first. Called when the application shuts down.
+    pub(crate) fn stop(&mut self, resources: &mut Resources) {
+        for scene in self.stack.iter_mut().rev() {
+            scene.exit(resources);
+        }
+    }
+
+    /// Splits the stack into the scenes that show under the active one (bottom first), and the
+    /// active scene itself, so the renderer can borrow both. `None` if the stack is empty.
+    pub(crate) fn split_layers(&mut self) -> Option<(&[Scene], &mut Scene)> {
+        let (active, under) = self.stack.split_last_mut()?;
+
+        // Every overlay lets the scene under it show through
+        let mut bottom = under.len();
+        let mut covering = &*active;
+
+        while bottom > 0 && covering.is_overlay() {
+            bottom -= 1;
+            covering = &under[bottom];
+        }
+
+        Some((&under[bottom..], active))
+    }
+
+    fn apply(&mut self, command: SceneCommand, resources: &mut Resources) {
+        match command {
+            SceneCommand::Push(mut scene) => {
+                debug!("Pushing scene {}", scene.get_name());
+
+                scene.enter(resources);
+                self.stack.push(scene);
+            },
+            SceneCommand::Pop if self.stack.len() == 1 => warn!("Tried to pop the last scene, ignoring it"),
+            SceneCommand::Pop => {
+                if let Some(mut scene) = self.stack.pop() {
+                    debug!("Popping scene {}", scene.get_name());
+
+                    scene.exit(resources);
+                }
+            },
+            SceneCommand::Replace(mut scene) => {
+                if let Some(mut old) = self.stack.pop() {
+                    debug!("Replacing scene {} with {}", old.get_name(), scene.get_name());
+
+                    old.exit(resources);
+                }
+
+                scene.enter(resources);
+                self.stack.push(scene);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// A scene that notes when it enters and exits the stack.
+    fn logged(name: &str, log: &Log) -> Scene {
+        let (enter_log, exit_log) = (log.clone(), log.clone());
+
+        Scene::new(name)
+            .with_on_enter(move |_, scene| enter_log.borrow_mut().push(format!("enter {}", scene.get_name())))
+            .with_on_exit(move |_, scene| exit_log.borrow_mut().push(format!("exit {}", scene.get_name())))
+    }
+
+    fn names(scenes: &SceneManager) -> Vec<&str> {
+        scenes.get_scenes().map(|scene| scene.get_name().as_str()).collect()
+    }
+
+    #[test]
+    fn changes_push_pop_and_replace_the_top_scene() {
+        let mut resources = Resources::new();
+        let mut scenes = SceneManager::new(Scene::new("title"));
+
+        scenes.queue(SceneChange::replace(Scene::new("game")));
+        scenes.queue(SceneChange::push(Scene::new("pause")));
+        scenes.update(Duration::ZERO, &mut resources);
+        assert_eq!(names(&scenes), ["game", "pause"]);
+        assert_eq!(scenes.get_active().get_name(), "pause");
+
+        scenes.queue(SceneChange::pop());
+        scenes.update(Duration::ZERO, &mut resources);
+        assert_eq!(names(&scenes), ["game"]);
+
+        // The last scene stays
+        scenes.queue(SceneChange::pop());
+        scenes.update(Duration::ZERO, &mut resources);
+        assert_eq!(names(&scenes), ["game"]);
+    }
+
+    #[test]
+    fn scenes_are_entered_and_exited_as_they_join_and_leave_the_stack() {
+        let log = Log::default();
+        let mut resources = Resources::new();
+        let mut scenes = SceneManager::new(logged("title", &log));
+
+        scenes.start(&mut resources);
+        scenes.queue(SceneChange::replace(logged("game", &log)));
+        scenes.queue(SceneChange::push(logged("pause", &log)));
+        scenes.queue(SceneChange::pop());
+        scenes.update(Duration::ZERO, &mut resources);
+        scenes.stop(&mut resources);
+
+        // Being covered by the pause menu doesn't exit the game
+        assert_eq!(*log.borrow(), [
+            "enter title", "exit title", "enter game", "enter pause", "exit pause", "exit game",
+        ]);
+    }
+
+    #[test]
+    fn transitions_swap_scenes_halfway_through() {
+        let mut resources = Resources::new();
+        let mut scenes = SceneManager::new(Scene::new("title"));
+        let fade = Transition::fade([0.0, 0.0, 0.0], Duration::from_secs(2));
+
+        scenes.queue(SceneChange::replace(Scene::new("game")).with_transition(fade));
+        scenes.queue(SceneChange::push(Scene::new("pause")));
+        scenes.update(Duration::ZERO, &mut resources);
+        assert!(scenes.is_transitioning());
+        assert_eq!(scenes.take_cover_update(), Some(Some(fade.get_cover(0.0))));
+
+        scenes.update(Duration::from_millis(900), &mut resources);
+        assert_eq!(names(&scenes), ["title"]);
+
+        // Fully covered, so the scenes are swapped, but the push waits for the transition
+        scenes.update(Duration::from_millis(100), &mut resources);
+        assert_eq!(names(&scenes), ["game"]);
+        assert_eq!(scenes.get_cover().map(|cover| cover.opacity), Some(1.0));
+
+        scenes.update(Duration::from_secs(1), &mut resources);
+        assert!(!scenes.is_transitioning());
+        assert_eq!(names(&scenes), ["game", "pause"]);
+
+        // The cover is cleared once, then left alone
+        assert_eq!(scenes.take_cover_update(), Some(None));
+        assert_eq!(scenes.take_cover_update(), None);
+    }
+
+    #[test]
+    fn wipes_cover_the_screen_from_one_edge() {
+        let wipe = Transition::wipe(WipeDirection::Right, [1.0, 1.0, 1.0], Duration::from_secs(1));
+
+        assert_eq!(wipe.get_cover(0.25).min, [-1.0, -1.0]);
+        assert_eq!(wipe.get_cover(0.25).max, [0.0, 1.0]);
+        assert_eq!(wipe.get_cover(0.5).max, [1.0, 1.0]);
+        assert_eq!(wipe.get_cover(0.75).min, [0.0, -1.0]);
+    }
+
+    #[test]
+    fn overlays_lend_out_the_scenes_under_them() {
+        let mut resources = Resources::new();
+        let mut scenes = SceneManager::new(Scene::new("title"));
+
+        let (layers, active) = scenes.split_layers().unwrap();
+        assert!(layers.is_empty());
+        assert_eq!(active.get_name(), "title");
+
+        scenes.queue(SceneChange::push(Scene::new("game")));
+        scenes.queue(SceneChange::push(Scene::new("pause").with_overlay(true)));
+        scenes.queue(SceneChange::push(Scene::new("options").with_overlay(true)));
+        scenes.update(Duration::ZERO, &mut resources);
+
+        // The title screen is under the (opaque) game, so it isn't drawn
+        let (layers, active) = scenes.split_layers().unwrap();
+        assert_eq!(layers.iter().map(|scene| scene.get_name().as_str()).collect::<Vec<_>>(), ["game", "pause"]);
+        assert_eq!(active.get_name(), "options");
+
+        // Nothing is taken out of the stack to lend them
+        assert_eq!(scenes.len(), 4);
+    }
+}
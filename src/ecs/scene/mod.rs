@@ -1,11 +1,27 @@
 pub mod friendly;
+pub mod manager;
+pub mod file;
+pub mod prefab;
+pub mod reload;
+pub mod save;
+pub mod plugin;
 
 use crate::ecs::{
     entity::Entity,
-    component::Component,
+    component::{ComponentRef, FriendlyComponent, registry},
 };
 
-use std::rc::Rc;
+use crate::application::resources::Resources;
+use crate::time::Time;
+use crate::error::Error as GompError;
+use prefab::SpawnedInstance;
+use reload::SceneSource;
+
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+/// Called when a scene enters or leaves the scene stack (see the `manager` module).
+pub type SceneHook = Box<dyn FnMut(&mut Resources, &mut Scene)>;
 
 /// A container that all the entities are stored in.
 /// May be swapped in and out.
@@ -16,12 +32,34 @@ pub struct Scene {
 
     /// A list of all the components we have.
     #[derivative(Debug="ignore")]
-    components: Vec<Rc<Box<dyn Component>>>,
+    components: Vec<ComponentRef>,
 
     /// All the entities we have. Should *never* be used to search though, use components for that
     /// instead.
     #[derivative(Debug="ignore")]
     entities: Vec<Rc<Entity>>,
+
+    /// Given to the scene's entities. Scenes on the scene stack aren't shared, so this only points
+    /// anywhere for scenes built with `FriendlyScene::into_shared`.
+    this: Weak<Scene>,
+
+    /// Do the scenes under this one still show through.
+    overlay: bool,
+
+    /// Have the components been started (and not stopped since).
+    started: Cell<bool>,
+
+    /// Prefab instances spawned into the scene, so they can be updated when their prefabs change.
+    #[derivative(Debug="ignore")]
+    prefab_instances: Vec<SpawnedInstance>,
+
+    /// The file the scene was loaded from, so it can be reloaded when it changes.
+    source: Option<SceneSource>,
+
+    #[derivative(Debug="ignore")]
+    on_enter: Option<SceneHook>,
+    #[derivative(Debug="ignore")]
+    on_exit: Option<SceneHook>,
 }
 
 impl Scene {
@@ -30,19 +68,52 @@ impl Scene {
             name: name.to_owned(),
             components: vec![],
             entities: vec![],
+            this: Weak::new(),
+            overlay: false,
+            started: Cell::new(false),
+            prefab_instances: vec![],
+            source: None,
+            on_enter: None,
+            on_exit: None,
         }
     }
 
+    /// Sets if the scenes under this one should still be drawn (a pause menu, say).
+    pub fn with_overlay(self, overlay: bool) -> Self { Self { overlay, .. self } }
+
+    /// Sets the function called when the scene enters the scene stack, after its components have
+    /// started.
+    pub fn with_on_enter<F>(self, on_enter: F) -> Self
+    where
+        F: FnMut(&mut Resources, &mut Scene) + 'static,
+    {
+        Self { on_enter: Some(Box::new(on_enter)), .. self }
+    }
+
+    /// Sets the function called when the scene leaves the scene stack, before its components are
+    /// stopped.
+    pub fn with_on_exit<F>(self, on_exit: F) -> Self
+    where
+        F: FnMut(&mut Resources, &mut Scene) + 'static,
+    {
+        Self { on_exit: Some(Box::new(on_exit)), .. self }
+    }
+
+    /// Do the scenes under this one still show through.
+    pub fn is_overlay(&self) -> bool {
+        self.overlay
+    }
+
     /// Gets all the components in the scene. Wondering why we don't just get all the entities?
     /// Check out a general overview on effient ECSs. In short, it's much more effect to be able to
     /// iterate over all our components, pick out the ones we need, and perform an action on them.
     /// Plus, all the memory is in one spot, so it's much faster.
-    pub fn get_components(&self) -> &Vec<Rc<Box<dyn Component>>> {
+    pub fn get_components(&self) -> &Vec<ComponentRef> {
         &self.components
     }
 
     /// Sets the components of a scene. Doesn't append or anything, just overwrites.
-    pub fn set_components(&mut self, components: Vec<Rc<Box<dyn Component>>>) {
+    pub fn set_components(&mut self, components: Vec<ComponentRef>) {
         self.components = components;
     }
 
@@ -58,6 +129,103 @@ impl Scene {
         self.entities = entities;
     }
 
+    /// Takes an entity (and its components) out of the scene. If the scene has started, the
+    /// components are stopped.
+    pub fn despawn(&mut self, entity: &Rc<Entity>) {
+        let components: Vec<ComponentRef> = entity.get_weak_components().iter()
+            .filter_map(std::rc::Weak::upgrade)
+            .collect();
+
+        for component in &components {
+            self.detach(component);
+        }
+
+        self.entities.retain(|existing| !Rc::ptr_eq(existing, entity));
+    }
+
+    /// Builds a component onto an entity that's already in the scene, with this thread's
+    /// component registry. If the scene has started, so does the component.
+    pub fn attach(&mut self, entity: &Rc<Entity>, component: &FriendlyComponent) -> Result<ComponentRef, GompError> {
+        let component = registry::with_registry(|registry| registry.build(component, Rc::downgrade(entity)))?;
+        let component: ComponentRef = Rc::new(RefCell::new(component));
+
+        if self.started.get() {
+            component.borrow_mut().on_start();
+        }
+
+        entity.push_component(Rc::downgrade(&component));
+        self.components.push(Rc::clone(&component));
+
+        Ok(component)
+    }
+
+    /// Takes a component out of the scene (and off its entity). If the scene has started, the
+    /// component is stopped.
+    pub fn detach(&mut self, component: &ComponentRef) {
+        if self.started.get() {
+            component.borrow_mut().on_stop();
+        }
+
+        let parent = component.borrow().get_weak_parent().upgrade();
+
+        if let Some(parent) = parent {
+            parent.remove_component(component);
+        }
+
+        self.components.retain(|existing| !Rc::ptr_eq(existing, component));
+    }
+
+    /// Runs a single simulation step. Every component remembers its current state (for
+    /// interpolation) before any of them are updated.
+    pub fn update(&self, time: &Time) {
+        for component in &self.components {
+            component.borrow_mut().store_previous();
+        }
+
+        for component in &self.components {
+            component.borrow_mut().on_update(time);
+        }
+    }
+
+    /// Starts every component in the scene (`on_start`).
+    pub fn start(&self) {
+        for component in &self.components {
+            component.borrow_mut().on_start();
+        }
+
+        self.started.set(true);
+    }
+
+    /// Stops every component in the scene (`on_stop`).
+    pub fn stop(&self) {
+        for component in &self.components {
+            component.borrow_mut().on_stop();
+        }
+
+        self.started.set(false);
+    }
+
+    /// Starts every component, then calls the enter hook.
+    pub(crate) fn enter(&mut self, resources: &mut Resources) {
+        self.start();
+
+        // The hook needs the scene too, so take it out while it runs
+        if let Some(mut on_enter) = self.on_enter.take() {
+            on_enter(resources, self);
+            self.on_enter.get_or_insert(on_enter);
+        }
+    }
+
+    /// Calls the exit hook, then stops every component.
+    pub(crate) fn exit(&mut self, resources: &mut Resources) {
+        if let Some(mut on_exit) = self.on_exit.take() {
+            on_exit(resources, self);
+            self.on_exit.get_or_insert(on_exit);
+        }
+
+        self.stop();
+    }
+
     /// Gets the name of the scene.
     pub fn get_name(&self) -> &String {
         &self.name
@@ -0,0 +1,24 @@
+//! The scene plugin.
+
+use super::prefab::PrefabLibrary;
+use super::reload::SceneReloader;
+use crate::application::builder::AppBuilder;
+use crate::application::plugin::Plugin;
+
+/// Adds the `PrefabLibrary` resource (unless there already is one) and, if
+/// `AppConfig::hot_reload` is on, the `SceneReloader` resource. The application has the reloader
+/// apply changed scene files at the start of every frame.
+#[derive(Debug, Default)]
+pub struct SceneReloadPlugin;
+
+impl Plugin for SceneReloadPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        if !app.get_resources().contains::<PrefabLibrary>() {
+            app.insert_resource(PrefabLibrary::new());
+        }
+
+        if app.get_config().hot_reload {
+            app.insert_resource(SceneReloader::new());
+        }
+    }
+}
@@ -0,0 +1,557 @@
+//! Prefabs.
+//!
+//! A prefab is a set of entities saved as a scene file (see `scene::file`), to be spawned many
+//! times over. Scenes (and other prefabs) use them through `PrefabInstance`s: the path of the
+//! prefab, a name for the instance, and any fields it overrides. Overrides are written like
+//! entities, holding only the fields that change:
+//!
+//! ```ignore
+//! // level.ron
+//! (
+//!     name: "level",
+//!     prefabs: [
+//!         (name: "ball", prefab: "prefabs/ball.ron"),
+//!         (
+//!             name: "left_paddle",
+//!             prefab: "prefabs/paddle.ron",
+//!             overrides: [(name: "paddle", components: [(type: "transform", position: (-0.9, 0.0, 0.0))])],
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! Each entity of an instance is named after the instance and the prefab's entity, so the paddle
+//! above is `left_paddle/paddle`. Prefabs can hold instances of other prefabs, which are named the
+//! same way (`wall/row_1/brick`).
+//!
+//! Prefabs are loaded (and kept) by a `PrefabLibrary`, which spawns them into scenes. Scenes
+//! remember the instances spawned into them, so when a prefab changes (`PrefabLibrary::reload`),
+//! every instance picks up the changed fields, apart from those it overrides. Entities and
+//! components added to (or taken out of) a prefab only show up in new instances.
+//!
+//! ```ignore
+//! let mut prefabs = PrefabLibrary::new();
+//! let mut scene = prefabs.build_scene(&FriendlyScene::load("level.ron")?)?;
+//!
+//! for i in 0..10 {
+//!     let brick = PrefabInstance::new(&format!("brick_{}", i), "prefabs/brick.ron")
+//!         .with_override("brick", FriendlyComponent::transform([i as f64 * 0.2, 0.5, 0.0]));
+//!
+//!     prefabs.instantiate(&brick, &mut scene)?;
+//! }
+//! ```
+
+use super::Scene;
+use super::friendly::FriendlyScene;
+use crate::ecs::entity::{Entity, friendly::FriendlyEntity};
+use crate::ecs::component::{Component, ComponentRef, FriendlyComponent, registry::{self, ComponentRegistry}};
+use crate::error::Error as GompError;
+
+use serde::{Serialize, Deserialize};
+use log::{debug, warn};
+
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+/// A prefab, used in a scene (or another prefab).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefabInstance {
+    name: String,
+
+    /// The path of the prefab's scene file.
+    prefab: String,
+
+    /// Entities of the prefab (by name), with the fields the instance overrides.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    overrides: Vec<FriendlyEntity>,
+}
+
+impl PrefabInstance {
+    /// An instance of the prefab at `prefab`, overriding nothing.
+    pub fn new(name: &str, prefab: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            prefab: prefab.to_owned(),
+            overrides: vec![],
+        }
+    }
+
+    /// Overrides the fields given in `component`, on an entity of the prefab.
+    pub fn with_override(mut self, entity: &str, component: FriendlyComponent) -> Self {
+        self.push_override(entity, component);
+        self
+    }
+
+    /// Overrides the fields given in `component`, on an entity of the prefab.
+    pub fn push_override(&mut self, entity: &str, component: FriendlyComponent) {
+        match self.overrides.iter_mut().find(|overrides| overrides.get_name() == entity) {
+            Some(overrides) => overrides.push_component(component),
+            None => self.overrides.push(FriendlyEntity::new(entity).with_component(component)),
+        }
+    }
+
+    /// Gets the name of the instance.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Gets the path of the prefab.
+    pub fn get_prefab(&self) -> &String {
+        &self.prefab
+    }
+
+    /// Gets the overrides, by entity.
+    pub fn get_overrides(&self) -> &Vec<FriendlyEntity> {
+        &self.overrides
+    }
+}
+
+/// An instance that has been spawned into a scene.
+#[derive(Debug)]
+pub(crate) struct SpawnedInstance {
+    instance: PrefabInstance,
+
+    /// Every prefab the instance is made from (its own, and any inside it).
+    dependencies: Vec<String>,
+
+    /// The spawned entities, by their name in the prefab.
+    entities: Vec<(String, Weak<Entity>)>,
+}
+
+/// Loads prefabs, and spawns them into scenes.
+#[derive(Debug, Default)]
+pub struct PrefabLibrary {
+    /// By path.
+    prefabs: HashMap<String, FriendlyScene>,
+}
+
+impl PrefabLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets a prefab, loading it if it hasn't been yet.
+    pub fn load(&mut self, path: &str) -> Result<&FriendlyScene, GompError> {
+        if !self.prefabs.contains_key(path) {
+            debug!("Loading prefab {}", path);
+
+            self.prefabs.insert(path.to_owned(), FriendlyScene::load(path)?);
+        }
+
+        Ok(&self.prefabs[path])
+    }
+
+    /// Adds a prefab that doesn't come from a file (or replaces one), without touching scenes.
+    pub fn insert(&mut self, path: &str, prefab: FriendlyScene) {
+        self.prefabs.insert(path.to_owned(), prefab);
+    }
+
+    /// Gets a prefab, if it's loaded.
+    pub fn get(&self, path: &str) -> Option<&FriendlyScene> {
+        self.prefabs.get(path)
+    }
+
+    /// Builds a scene, spawning its prefab instances.
+    pub fn build_scene(&mut self, friendly: &FriendlyScene) -> Result<Scene, GompError> {
+        let mut scene = Scene::new(friendly.get_name());
+
+        for entity in friendly.get_entities() {
+            scene.spawn(entity);
+        }
+
+        for instance in friendly.get_prefabs() {
+            self.instantiate(instance, &mut scene)?;
+        }
+
+        Ok(scene)
+    }
+
+    /// Spawns an instance of a prefab into a scene, and gets its entities.
+    pub fn instantiate(&mut self, instance: &PrefabInstance, scene: &mut Scene) -> Result<Vec<Rc<Entity>>, GompError> {
+        let mut dependencies = vec![];
+        let mut entities = self.flatten(&instance.prefab, &mut vec![], &mut dependencies)?;
+        apply_overrides(&mut entities, &instance.overrides);
+
+        let spawned: Vec<(String, Rc<Entity>)> = entities.into_iter()
+            .map(|mut entity| {
+                let name = entity.get_name().clone();
+                entity.set_name(&format!("{}/{}", instance.name, name));
+
+                (name, scene.spawn(&entity))
+            })
+            .collect();
+
+        scene.prefab_instances.push(SpawnedInstance {
+            instance: instance.clone(),
+            dependencies,
+            entities: spawned.iter().map(|(name, entity)| (name.clone(), Rc::downgrade(entity))).collect(),
+        });
+
+        Ok(spawned.into_iter().map(|(_, entity)| entity).collect())
+    }
+
+    /// Loads a prefab from its file again, and updates its instances in `scenes`.
+    pub fn reload(&mut self, path: &str, scenes: &[&Scene]) -> Result<(), GompError> {
+        let prefab = FriendlyScene::load(path)?;
+
+        self.replace(path, prefab, scenes)
+    }
+
+    /// Replaces a prefab, and updates its instances in `scenes`. Fields an instance overrides are
+    /// left alone.
+    pub fn replace(&mut self, path: &str, prefab: FriendlyScene, scenes: &[&Scene]) -> Result<(), GompError> {
+        let affected: Vec<&SpawnedInstance> = scenes.iter()
+            .flat_map(|scene| scene.prefab_instances.iter())
+            .filter(|spawned| spawned.dependencies.iter().any(|dependency| dependency == path))
+            .collect();
+
+        // What each affected prefab looked like before, and after
+        let mut before = HashMap::new();
+
+        for spawned in &affected {
+            if !before.contains_key(&spawned.instance.prefab) {
+                before.insert(spawned.instance.prefab.clone(), self.flatten_root(&spawned.instance.prefab)?);
+            }
+        }
+
+        self.prefabs.insert(path.to_owned(), prefab);
+
+        let mut after = HashMap::new();
+
+        for prefab in before.keys() {
+            after.insert(prefab.clone(), self.flatten_root(prefab)?);
+        }
+
+        debug!("Updating {} instances of prefab {}", affected.len(), path);
+
+        registry::with_registry(|registry| {
+            for spawned in affected {
+                let prefab = &spawned.instance.prefab;
+
+                spawned.update(&before[prefab], &after[prefab], registry);
+            }
+        });
+
+        Ok(())
+    }
+
+    fn flatten_root(&mut self, path: &str) -> Result<Vec<FriendlyEntity>, GompError> {
+        self.flatten(path, &mut vec![], &mut vec![])
+    }
+
+    /// Gets every entity of a prefab, with the instances inside it spawned (and their overrides
+    /// applied). `stack` is the prefabs being flattened, to catch prefabs that contain themselves.
+    fn flatten(&mut self, path: &str, stack: &mut Vec<String>, dependencies: &mut Vec<String>) -> Result<Vec<FriendlyEntity>, GompError> {
+        if stack.iter().any(|parent| parent == path) {
+            return Err(GompError::InvalidPrefab(format!("{} contains itself (through {})", path, stack.join(", "))));
+        }
+
+        if !dependencies.iter().any(|dependency| dependency == path) {
+            dependencies.push(path.to_owned());
+        }
+
+        let prefab = self.load(path)?.clone();
+        let mut entities = prefab.get_entities().clone();
+
+        stack.push(path.to_owned());
+
+        for instance in prefab.get_prefabs() {
+            let mut inner = self.flatten(&instance.prefab, stack, dependencies)?;
+            apply_overrides(&mut inner, &instance.overrides);
+
+            for mut entity in inner {
+                let name = format!("{}/{}", instance.name, entity.get_name());
+                entity.set_name(&name);
+                entities.push(entity);
+            }
+        }
+
+        stack.pop();
+
+        Ok(entities)
+    }
+}
+
+impl SpawnedInstance {
+    /// Gets the instance, as it was spawned.
+    pub(super) fn get_instance(&self) -> &PrefabInstance {
+        &self.instance
+    }
+
+    /// Gets the paths of every prefab the instance is made from.
+    pub(super) fn get_dependencies(&self) -> &Vec<String> {
+        &self.dependencies
+    }
+
+    /// Gets the spawned entities that are still alive.
+    pub(super) fn get_live_entities(&self) -> Vec<Rc<Entity>> {
+        self.entities.iter().filter_map(|(_, entity)| entity.upgrade()).collect()
+    }
+
+    /// Sets the fields that changed between two versions of the prefab, apart from the overridden
+    /// ones.
+    fn update(&self, before: &[FriendlyEntity], after: &[FriendlyEntity], registry: &ComponentRegistry) {
+        for (name, entity) in &self.entities {
+            let (entity, after) = match (entity.upgrade(), find_entity(after, name)) {
+                (Some(entity), Some(after)) => (entity, after),
+                _ => continue,
+            };
+
+            let before = find_entity(before, name);
+            let overrides = find_entity(&self.instance.overrides, name);
+
+            for (component, nth) in with_occurrences(after.get_components()) {
+                let type_name = component.get_type_name();
+                let previous = before.and_then(|before| nth_of_type(before.get_components(), type_name, nth));
+                let overridden = overrides.and_then(|overrides| nth_of_type(overrides.get_components(), type_name, nth));
+
+                let live = match nth_live(&entity, type_name, nth, registry) {
+                    Some(live) => live,
+                    None => continue,
+                };
+
+                let is_overridden = |field: &str| overridden.is_some_and(|overridden| overridden.get_field(field).is_some());
+
+                update_fields(&entity, &live, previous, component, is_overridden, registry);
+            }
+        }
+    }
+}
+
+/// Merges overrides into a prefab's entities.
+fn apply_overrides(entities: &mut [FriendlyEntity], overrides: &[FriendlyEntity]) {
+    for overrides in overrides {
+        let entity = match entities.iter_mut().find(|entity| entity.get_name() == overrides.get_name()) {
+            Some(entity) => entity,
+            None => {
+                warn!("Prefab has no entity {} to override", overrides.get_name());
+
+                continue;
+            },
+        };
+
+        for (component, nth) in with_occurrences(overrides.get_components()) {
+            let type_name = component.get_type_name();
+            let index = entity.get_components().iter()
+                .enumerate()
+                .filter(|(_, existing)| existing.get_type_name() == type_name)
+                .nth(nth)
+                .map(|(index, _)| index);
+
+            match index {
+                Some(index) => {
+                    let existing = &mut entity.get_mut_components()[index];
+
+                    for (field, value) in component.get_fields() {
+                        existing.set_field(field, value.clone());
+                    }
+                },
+                None => entity.push_component(component.clone()),
+            }
+        }
+    }
+}
+
+/// Pairs each component with how many of the same type came before it, so components of the same
+/// type can be told apart.
+pub(super) fn with_occurrences(components: &[FriendlyComponent]) -> impl Iterator<Item = (&FriendlyComponent, usize)> {
+    components.iter().enumerate().map(move |(index, component)| {
+        let nth = components[..index].iter()
+            .filter(|before| before.get_type_name() == component.get_type_name())
+            .count();
+
+        (component, nth)
+    })
+}
+
+pub(super) fn nth_of_type<'a>(components: &'a [FriendlyComponent], type_name: &str, nth: usize) -> Option<&'a FriendlyComponent> {
+    components.iter().filter(|component| component.get_type_name() == type_name).nth(nth)
+}
+
+pub(super) fn find_entity<'a>(entities: &'a [FriendlyEntity], name: &str) -> Option<&'a FriendlyEntity> {
+    entities.iter().find(|entity| entity.get_name() == name)
+}
+
+/// Finds the nth live component of a type on an entity.
+pub(super) fn nth_live(entity: &Entity, type_name: &str, nth: usize, registry: &ComponentRegistry) -> Option<ComponentRef> {
+    entity.get_weak_components().iter()
+        .filter_map(Weak::upgrade)
+        .filter(|component| {
+            registry.get_for(&**component.borrow()).is_some_and(|registration| registration.get_name() == type_name)
+        })
+        .nth(nth)
+}
+
+/// Sets the fields of a live component that changed from `before` to `after`, and sets those
+/// `after` doesn't have any more back to their defaults. Fields that `skip` says no to are left
+/// alone.
+pub(super) fn update_fields<F>(
+    entity: &Entity,
+    live: &ComponentRef,
+    before: Option<&FriendlyComponent>,
+    after: &FriendlyComponent,
+    skip: F,
+    registry: &ComponentRegistry,
+)
+where
+    F: Fn(&str) -> bool,
+{
+    for (field, value) in after.get_fields() {
+        if skip(field) || before.and_then(|before| before.get_field(field)) == Some(value) {
+            continue;
+        }
+
+        if let Err(e) = registry.set_path(&mut **live.borrow_mut(), field, value.clone()) {
+            warn!("Couldn't update {}: {}", entity.get_name(), e);
+        }
+    }
+
+    let removed = before.into_iter()
+        .flat_map(|before| before.get_fields())
+        .filter(|(field, _)| after.get_field(field).is_none() && !skip(field));
+
+    for (field, _) in removed {
+        if let Err(e) = reset_field(live, after.get_type_name(), field, registry) {
+            warn!("Couldn't update {}: {}", entity.get_name(), e);
+        }
+    }
+}
+
+/// Sets a field back to the component's default.
+fn reset_field(component: &ComponentRef, type_name: &str, field: &str, registry: &ComponentRegistry) -> Result<(), GompError> {
+    let default: Box<dyn Component> = registry.get(type_name)
+        .ok_or_else(|| GompError::InvalidField(format!("no component is registered as {}", type_name)))?
+        .construct(Weak::new());
+
+    let value = registry.get_path(&*default, field)?;
+
+    registry.set_path(&mut **component.borrow_mut(), field, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::ComponentType;
+    use crate::ecs::component::transform::Transform;
+
+    fn paddle(component: FriendlyComponent) -> FriendlyScene {
+        FriendlyScene::new("paddle").with_entity(FriendlyEntity::new("paddle").with_component(component))
+    }
+
+    fn find(scene: &Scene, name: &str) -> Rc<Entity> {
+        scene.get_entities().iter()
+            .find(|entity| *entity.get_name() == name)
+            .cloned()
+            .unwrap_or_else(|| panic!("no entity called {}", name))
+    }
+
+    /// Gets the position, rotation and scale of an entity's transform.
+    fn transform(entity: &Entity) -> ([f64; 3], f64, [f64; 3]) {
+        let component = entity.find_component(ComponentType::Transform).unwrap();
+        let component = component.borrow();
+        let transform: &Transform = component.as_any().downcast_ref().unwrap();
+
+        (transform.get_position(), transform.get_rotation(), transform.get_scale())
+    }
+
+    /// A library with a paddle prefab, and a scene with a `left` instance of it that overrides the
+    /// position.
+    fn left_paddle(prefab: FriendlyComponent) -> (PrefabLibrary, Scene) {
+        let mut prefabs = PrefabLibrary::new();
+        prefabs.insert("paddle.ron", paddle(prefab));
+
+        let mut scene = Scene::new("level");
+        let instance = PrefabInstance::new("left", "paddle.ron")
+            .with_override("paddle", FriendlyComponent::transform([-0.9, 0.0, 0.0]));
+
+        prefabs.instantiate(&instance, &mut scene).unwrap();
+
+        (prefabs, scene)
+    }
+
+    #[test]
+    fn instances_are_spawned_with_their_overrides() {
+        let (_, scene) = left_paddle(FriendlyComponent::transform([0.0, 0.0, 0.0]).with_field("rotation", 0.5));
+
+        assert_eq!(scene.get_entities().len(), 1);
+        assert_eq!(transform(&find(&scene, "left/paddle")), ([-0.9, 0.0, 0.0], 0.5, [1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn overridden_fields_survive_prefab_changes() {
+        let (mut prefabs, scene) = left_paddle(FriendlyComponent::transform([0.0, 0.0, 0.0]));
+
+        prefabs.replace("paddle.ron", paddle(FriendlyComponent::transform([0.0, 1.0, 0.0])), &[&scene]).unwrap();
+
+        assert_eq!(transform(&find(&scene, "left/paddle")).0, [-0.9, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn changed_fields_reach_instances() {
+        let (mut prefabs, scene) = left_paddle(FriendlyComponent::transform([0.0, 0.0, 0.0]).with_field("rotation", 0.5));
+
+        let changed = FriendlyComponent::transform([0.0, 0.0, 0.0])
+            .with_field("rotation", 1.0)
+            .with_field("scale", [2.0, 2.0, 1.0]);
+        prefabs.replace("paddle.ron", paddle(changed), &[&scene]).unwrap();
+
+        assert_eq!(transform(&find(&scene, "left/paddle")), ([-0.9, 0.0, 0.0], 1.0, [2.0, 2.0, 1.0]));
+    }
+
+    #[test]
+    fn removed_fields_go_back_to_their_defaults() {
+        let (mut prefabs, scene) = left_paddle(
+            FriendlyComponent::transform([0.0, 0.0, 0.0])
+                .with_field("rotation", 0.5)
+                .with_field("scale", [2.0, 2.0, 1.0]),
+        );
+
+        prefabs.replace("paddle.ron", paddle(FriendlyComponent::transform([0.0, 0.0, 0.0]).with_field("rotation", 0.5)), &[&scene]).unwrap();
+
+        assert_eq!(transform(&find(&scene, "left/paddle")), ([-0.9, 0.0, 0.0], 0.5, [1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn nested_instances_are_named_after_every_instance() {
+        let mut prefabs = PrefabLibrary::new();
+        prefabs.insert("brick.ron", FriendlyScene::new("brick").with_entity(
+            FriendlyEntity::new("brick").with_component(FriendlyComponent::transform([0.0, 0.0, 0.0])),
+        ));
+        prefabs.insert("wall.ron", FriendlyScene::new("wall")
+            .with_prefab(PrefabInstance::new("row_1", "brick.ron"))
+            .with_prefab(PrefabInstance::new("row_2", "brick.ron")
+                .with_override("brick", FriendlyComponent::transform([0.0, 0.2, 0.0]))));
+
+        let mut scene = Scene::new("level");
+        prefabs.instantiate(&PrefabInstance::new("wall", "wall.ron"), &mut scene).unwrap();
+
+        let names: Vec<String> = scene.get_entities().iter().map(|entity| entity.get_name().clone()).collect();
+        assert_eq!(names, vec!["wall/row_1/brick", "wall/row_2/brick"]);
+
+        // Changing the inner prefab reaches through the outer one, overrides and all
+        prefabs.replace("brick.ron", FriendlyScene::new("brick").with_entity(
+            FriendlyEntity::new("brick").with_component(FriendlyComponent::transform([0.0, 0.0, 0.0]).with_field("rotation", 1.0)),
+        ), &[&scene]).unwrap();
+
+        assert_eq!(transform(&find(&scene, "wall/row_1/brick")).1, 1.0);
+        assert_eq!(transform(&find(&scene, "wall/row_2/brick")), ([0.0, 0.2, 0.0], 1.0, [1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn prefabs_that_contain_themselves_are_refused() {
+        let mut prefabs = PrefabLibrary::new();
+        prefabs.insert("a.ron", FriendlyScene::new("a").with_prefab(PrefabInstance::new("b", "b.ron")));
+        prefabs.insert("b.ron", FriendlyScene::new("b").with_prefab(PrefabInstance::new("a", "a.ron")));
+        prefabs.insert("loop.ron", FriendlyScene::new("loop").with_prefab(PrefabInstance::new("again", "loop.ron")));
+
+        let mut scene = Scene::new("level");
+
+        for prefab in &["a.ron", "loop.ron"] {
+            let result = prefabs.instantiate(&PrefabInstance::new("instance", prefab), &mut scene);
+
+            assert!(matches!(result, Err(GompError::InvalidPrefab(_))));
+        }
+
+        assert!(scene.get_entities().is_empty());
+    }
+}
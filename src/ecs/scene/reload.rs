@@ -0,0 +1,462 @@
+//! Scene hot-reloading.
+//!
+//! Scenes loaded with `PrefabLibrary::load_scene` (or `Application::load_scene`) remember the file
+//! they came from. With the `SceneReloadPlugin` (one of the default plugins) and
+//! `AppConfig::hot_reload` on, those files, and the prefabs the scenes use, are watched. At the
+//! start of every frame, whatever changed is read again and applied to the live scenes in place,
+//! without restarting them.
+//!
+//! Entities are matched up by their `id`, or by name if they don't have one. Only what changed in
+//! the file is applied, so a paddle that has moved keeps its place when its speed is tuned:
+//!
+//! - entities added to the file are spawned, and entities taken out of it are despawned
+//! - components added to an entity are attached, and components taken out are detached
+//!   (components of the same type are matched up in order)
+//! - fields that changed are set, and fields that aren't set any more go back to their defaults
+//! - prefab instances that were added or taken out are spawned or despawned, and instances that
+//!   changed are spawned again
+//!
+//! Entities and components that the game has despawned aren't brought back. If the file doesn't
+//! parse, the error is logged and the scene is left as it was, so a typo won't bring down your
+//! application.
+//!
+//! ```ignore
+//! let scene = app.load_scene("scenes/level.ron")?;
+//! app.replace_scene(scene);
+//! ```
+
+use super::Scene;
+use super::friendly::FriendlyScene;
+use super::manager::SceneManager;
+use super::prefab::{PrefabInstance, PrefabLibrary, nth_live, nth_of_type, update_fields, with_occurrences};
+use crate::ecs::entity::{Entity, friendly::FriendlyEntity};
+use crate::ecs::component::{ComponentRef, registry::{self, ComponentRegistry}};
+use crate::error::Error as GompError;
+use crate::watch::FileWatcher;
+
+use log::{debug, info, warn, error};
+
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// The file a scene was loaded from, and what was in it.
+#[derive(Debug)]
+pub(crate) struct SceneSource {
+    path: PathBuf,
+    loaded: FriendlyScene,
+}
+
+/// Watches the files scenes were loaded from, and the prefabs they use, and applies any changes.
+/// The application updates it at the start of every frame.
+#[derive(Debug)]
+pub struct SceneReloader {
+    watcher: FileWatcher,
+}
+
+impl SceneReloader {
+    /// Creates a reloader that checks for changes (at most) once every quarter of a second.
+    pub fn new() -> Self {
+        Self { watcher: FileWatcher::new() }
+    }
+
+    /// Creates a reloader that checks for changes (at most) once every `interval`.
+    pub fn with_interval(interval: Duration) -> Self {
+        Self { watcher: FileWatcher::with_interval(interval) }
+    }
+
+    /// Watches the files of every scene on the stack, then reloads any prefabs and scenes that
+    /// changed. Prefabs go first, so reloaded scenes spawn their new versions.
+    pub fn update(&mut self, scenes: &mut SceneManager, prefabs: &mut PrefabLibrary) {
+        // Scenes come and go, so keep up with them
+        for scene in scenes.get_scenes() {
+            if let Some(path) = scene.get_source() {
+                self.watcher.watch(path);
+            }
+
+            for spawned in &scene.prefab_instances {
+                for dependency in spawned.get_dependencies() {
+                    self.watcher.watch(dependency);
+                }
+            }
+        }
+
+        let changed = self.watcher.poll();
+
+        for path in &changed {
+            let path = match path.to_str() {
+                Some(path) if prefabs.get(path).is_some() => path,
+                _ => continue,
+            };
+
+            let live: Vec<&Scene> = scenes.get_scenes().collect();
+
+            match prefabs.reload(path, &live) {
+                Ok(()) => info!("Reloaded prefab {}", path),
+                Err(e) => error!("Failed to reload prefab, keeping the last good version: {}", e),
+            }
+        }
+
+        for scene in scenes.get_mut_scenes() {
+            if !scene.get_source().is_some_and(|source| changed.iter().any(|path| path == source)) {
+                continue;
+            }
+
+            match scene.reload(prefabs) {
+                Ok(()) => info!("Reloaded scene {}", scene.get_name()),
+                Err(e) => error!("Failed to reload scene, keeping the last good version: {}", e),
+            }
+        }
+    }
+}
+
+impl Default for SceneReloader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrefabLibrary {
+    /// Loads a scene file and builds it, spawning its prefab instances. The scene remembers the
+    /// file, so it can be reloaded.
+    pub fn load_scene<P: AsRef<Path>>(&mut self, path: P) -> Result<Scene, GompError> {
+        let path = path.as_ref();
+        let friendly = FriendlyScene::load(path)?;
+        let mut scene = self.build_scene(&friendly)?;
+
+        scene.source = Some(SceneSource {
+            path: path.to_path_buf(),
+            loaded: friendly,
+        });
+
+        Ok(scene)
+    }
+}
+
+impl Scene {
+    /// Gets the path of the file the scene was loaded from, if it was.
+    pub fn get_source(&self) -> Option<&Path> {
+        self.source.as_ref().map(|source| source.path.as_path())
+    }
+
+    /// Reads the scene's file again, and applies whatever changed since it was last read (see the
+    /// module docs). Scenes that weren't loaded from a file are left alone.
+    pub fn reload(&mut self, prefabs: &mut PrefabLibrary) -> Result<(), GompError> {
+        let source = match self.source.take() {
+            Some(source) => source,
+            None => return Ok(()),
+        };
+
+        let after = match FriendlyScene::load(&source.path) {
+            Ok(after) => after,
+            Err(e) => {
+                self.source = Some(source);
+
+                return Err(e);
+            },
+        };
+
+        debug!("Applying changes to scene {} from {}", self.get_name(), source.path.display());
+
+        registry::with_registry(|registry| {
+            self.apply_entity_changes(source.loaded.get_entities(), after.get_entities(), registry);
+        });
+
+        self.apply_instance_changes(source.loaded.get_prefabs(), after.get_prefabs(), prefabs);
+
+        self.source = Some(SceneSource {
+            loaded: after,
+            .. source
+        });
+
+        Ok(())
+    }
+
+    fn apply_entity_changes(&mut self, before: &[FriendlyEntity], after: &[FriendlyEntity], registry: &ComponentRegistry) {
+        for entity in before {
+            if find_key(after, entity.get_key()).is_some() {
+                continue;
+            }
+
+            if let Some(live) = self.find_live(entity.get_key()) {
+                debug!("Despawning {}", entity.get_name());
+                self.despawn(&live);
+            }
+        }
+
+        for entity in after {
+            let previous = find_key(before, entity.get_key());
+
+            match (self.find_live(entity.get_key()), previous) {
+                (Some(live), previous) => self.update_entity(&live, previous, entity, registry),
+                (None, None) => {
+                    debug!("Spawning {}", entity.get_name());
+                    self.spawn(entity);
+                },
+                // The game despawned it
+                (None, Some(_)) => {},
+            }
+        }
+    }
+
+    fn update_entity(&mut self, live: &Rc<Entity>, before: Option<&FriendlyEntity>, after: &FriendlyEntity, registry: &ComponentRegistry) {
+        if *live.get_name() != *after.get_name() {
+            live.set_name(after.get_name());
+        }
+
+        let before = before.map(|before| before.get_components().as_slice()).unwrap_or_default();
+
+        // Found before anything is attached or detached, so the live components don't shift
+        let removed: Vec<ComponentRef> = with_occurrences(before)
+            .filter(|(component, nth)| nth_of_type(after.get_components(), component.get_type_name(), *nth).is_none())
+            .filter_map(|(component, nth)| nth_live(live, component.get_type_name(), nth, registry))
+            .collect();
+
+        for (component, nth) in with_occurrences(after.get_components()) {
+            let type_name = component.get_type_name();
+            let previous = nth_of_type(before, type_name, nth);
+
+            match (nth_live(live, type_name, nth, registry), previous) {
+                (Some(existing), previous) => update_fields(live, &existing, previous, component, |_| false, registry),
+                (None, None) => {
+                    if let Err(e) = self.attach(live, component) {
+                        warn!("Couldn't add a component to {}: {}", after.get_name(), e);
+                    }
+                },
+                // The game took it off
+                (None, Some(_)) => {},
+            }
+        }
+
+        for component in &removed {
+            self.detach(component);
+        }
+    }
+
+    fn apply_instance_changes(&mut self, before: &[PrefabInstance], after: &[PrefabInstance], prefabs: &mut PrefabLibrary) {
+        for instance in before {
+            if after.iter().find(|after| after.get_name() == instance.get_name()) != Some(instance) {
+                self.despawn_instance(instance.get_name());
+            }
+        }
+
+        for instance in after {
+            if before.iter().find(|before| before.get_name() == instance.get_name()) == Some(instance) {
+                continue;
+            }
+
+            debug!("Spawning prefab instance {}", instance.get_name());
+
+            if let Err(e) = prefabs.instantiate(instance, self) {
+                warn!("Couldn't spawn prefab instance {}: {}", instance.get_name(), e);
+            }
+        }
+    }
+
+    /// Despawns every entity of a prefab instance, and forgets the instance.
+    fn despawn_instance(&mut self, name: &str) {
+        let (despawned, kept) = std::mem::take(&mut self.prefab_instances)
+            .into_iter()
+            .partition(|spawned| spawned.get_instance().get_name() == name);
+
+        self.prefab_instances = kept;
+
+        for spawned in despawned {
+            for entity in spawned.get_live_entities() {
+                self.despawn(&entity);
+            }
+        }
+    }
+
+    /// Finds a live entity by its ID, or its name if it doesn't have one.
+    fn find_live(&self, key: &str) -> Option<Rc<Entity>> {
+        self.entities.iter().find(|entity| entity.has_key(key)).cloned()
+    }
+}
+
+fn find_key<'a>(entities: &'a [FriendlyEntity], key: &str) -> Option<&'a FriendlyEntity> {
+    entities.iter().find(|entity| entity.get_key() == key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::FriendlyComponent;
+    use crate::ecs::component::transform::Transform;
+
+    /// A scene file in the temp directory, deleted when it's dropped.
+    struct TempScene(PathBuf);
+
+    impl TempScene {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("gomp-{}-{}.ron", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempScene {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn at(name: &str, position: [f64; 3]) -> FriendlyEntity {
+        FriendlyEntity::new(name).with_component(FriendlyComponent::transform(position))
+    }
+
+    fn find(scene: &Scene, name: &str) -> Option<Rc<Entity>> {
+        scene.get_entities().iter().find(|entity| *entity.get_name() == name).cloned()
+    }
+
+    fn names(scene: &Scene) -> Vec<String> {
+        scene.get_entities().iter().map(|entity| entity.get_name().clone()).collect()
+    }
+
+    /// Gets the positions of every transform on an entity, in order.
+    fn positions(entity: &Entity) -> Vec<[f64; 3]> {
+        entity.get_weak_components().iter()
+            .filter_map(|component| component.upgrade())
+            .filter_map(|component| component.borrow().as_any().downcast_ref::<Transform>().map(Transform::get_position))
+            .collect()
+    }
+
+    /// Builds a scene from `before`, then applies the changes to `after`.
+    fn reloaded(before: &[FriendlyEntity], after: &[FriendlyEntity]) -> Scene {
+        let mut scene = Scene::new("level");
+
+        for entity in before {
+            scene.spawn(entity);
+        }
+
+        apply(&mut scene, before, after);
+
+        scene
+    }
+
+    fn apply(scene: &mut Scene, before: &[FriendlyEntity], after: &[FriendlyEntity]) {
+        registry::with_registry(|registry| scene.apply_entity_changes(before, after, registry));
+    }
+
+    #[test]
+    fn entities_are_spawned_and_despawned_with_the_file() {
+        let before = [at("ball", [0.0; 3]), at("left", [-0.9, 0.0, 0.0])];
+        let after = [at("ball", [0.0; 3]), at("right", [0.9, 0.0, 0.0])];
+
+        let scene = reloaded(&before, &after);
+
+        assert_eq!(names(&scene), vec!["ball", "right"]);
+        assert_eq!(positions(&find(&scene, "right").unwrap()), vec![[0.9, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn entities_are_matched_by_id_then_name() {
+        let before = [at("player", [0.0; 3]).with_id("p1"), at("ball", [0.0; 3])];
+        let after = [at("hero", [1.0, 0.0, 0.0]).with_id("p1"), at("ball", [0.0, 2.0, 0.0])];
+
+        let mut scene = Scene::new("level");
+        let player = scene.spawn(&before[0]);
+        let ball = scene.spawn(&before[1]);
+
+        apply(&mut scene, &before, &after);
+
+        // Updated in place, not spawned again
+        assert_eq!(scene.get_entities().len(), 2);
+        assert!(Rc::ptr_eq(&find(&scene, "hero").unwrap(), &player));
+        assert!(Rc::ptr_eq(&find(&scene, "ball").unwrap(), &ball));
+
+        assert_eq!(positions(&player), vec![[1.0, 0.0, 0.0]]);
+        assert_eq!(positions(&ball), vec![[0.0, 2.0, 0.0]]);
+    }
+
+    #[test]
+    fn components_of_the_same_type_are_matched_in_order() {
+        let before = [at("wall", [0.0; 3]).with_component(FriendlyComponent::transform([1.0, 0.0, 0.0]))];
+        let moved = [at("wall", [0.0; 3]).with_component(FriendlyComponent::transform([2.0, 0.0, 0.0]))];
+
+        let mut scene = reloaded(&before, &moved);
+        let wall = find(&scene, "wall").unwrap();
+        assert_eq!(positions(&wall), vec![[0.0; 3], [2.0, 0.0, 0.0]]);
+
+        // Taking the second one out of the file detaches the second one
+        let fewer = [at("wall", [0.0; 3])];
+        apply(&mut scene, &moved, &fewer);
+        assert_eq!(positions(&wall), vec![[0.0; 3]]);
+
+        // And putting it back attaches a new one
+        apply(&mut scene, &fewer, &moved);
+        assert_eq!(positions(&wall), vec![[0.0; 3], [2.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn changed_fields_are_set_and_removed_ones_reset() {
+        let before = [FriendlyEntity::new("paddle").with_component(
+            FriendlyComponent::transform([0.0; 3]).with_field("rotation", 0.5),
+        )];
+        let after = [FriendlyEntity::new("paddle").with_component(
+            FriendlyComponent::transform([0.0; 3]).with_field("scale", [2.0, 2.0, 1.0]),
+        )];
+
+        let mut scene = Scene::new("level");
+        let paddle = scene.spawn(&before[0]);
+
+        // The game moved the paddle, which the file didn't change
+        let component = paddle.get_weak_components()[0].upgrade().unwrap();
+        component.borrow_mut().as_mut_any().downcast_mut::<Transform>().unwrap().set_position(0.0, 3.0, 0.0);
+
+        apply(&mut scene, &before, &after);
+
+        let component = component.borrow();
+        let transform: &Transform = component.as_any().downcast_ref().unwrap();
+        assert_eq!(transform.get_position(), [0.0, 3.0, 0.0]);
+        assert_eq!(transform.get_rotation(), 0.0);
+        assert_eq!(transform.get_scale(), [2.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn what_the_game_despawned_stays_despawned() {
+        let before = [at("ball", [0.0; 3]), at("brick", [0.0; 3])];
+        let after = [
+            at("ball", [0.0, 1.0, 0.0]).with_component(FriendlyComponent::transform([1.0, 0.0, 0.0])),
+            at("brick", [0.0, 1.0, 0.0]),
+        ];
+
+        let mut scene = Scene::new("level");
+        let ball = scene.spawn(&before[0]);
+        let brick = scene.spawn(&before[1]);
+
+        // The ball broke the brick, and lost its transform somehow
+        scene.despawn(&brick);
+        let transform = ball.get_weak_components()[0].upgrade().unwrap();
+        scene.detach(&transform);
+
+        apply(&mut scene, &before, &after);
+
+        assert_eq!(names(&scene), vec!["ball"]);
+
+        // Only the new transform is attached
+        assert_eq!(positions(&ball), vec![[1.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn scenes_that_dont_parse_are_left_alone() {
+        let file = TempScene::new("reload-parse-error");
+        let friendly = FriendlyScene::new("level").with_entity(at("ball", [0.0; 3]));
+        friendly.save(&file.0).unwrap();
+
+        let mut prefabs = PrefabLibrary::new();
+        let mut scene = prefabs.load_scene(&file.0).unwrap();
+        assert_eq!(scene.get_source(), Some(file.0.as_path()));
+
+        std::fs::write(&file.0, "(name: \"level\", entities: [(name: ").unwrap();
+
+        assert!(scene.reload(&mut prefabs).is_err());
+        assert_eq!(names(&scene), vec!["ball"]);
+        assert_eq!(scene.get_source(), Some(file.0.as_path()));
+        assert_eq!(scene.source.as_ref().unwrap().loaded, friendly);
+
+        // Fixing the file picks up from what was last loaded
+        FriendlyScene::new("level").with_entity(at("ball", [1.0, 0.0, 0.0])).save(&file.0).unwrap();
+
+        scene.reload(&mut prefabs).unwrap();
+        assert_eq!(positions(&find(&scene, "ball").unwrap()), vec![[1.0, 0.0, 0.0]]);
+    }
+}
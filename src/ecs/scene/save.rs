@@ -0,0 +1,785 @@
+//! Save games.
+//!
+//! A `SaveGame` is a snapshot of the parts of a running scene worth keeping: every entity with a
+//! `Persist` component (and the components it picks), along with any resources registered with
+//! the `SaveRegistry`. Resources are saved through reflection, so derive `Reflect` for them, like
+//! components.
+//!
+//! Loading a save puts the scene back the way it was. Saved entities are matched up with the
+//! persisted entities already in the scene (by `id`, or name), and their saved fields are set. Any
+//! that can't be matched are spawned, and persisted entities that weren't in the save are
+//! despawned. Everything else in the scene (the level itself, say) is left alone. References to
+//! other entities (`EntityRef` fields) are pointed at the entities they were loaded as.
+//!
+//! ```ignore
+//! #[derive(Reflect, Default)]
+//! struct Score {
+//!     left: u32,
+//!     right: u32,
+//! }
+//!
+//! app.insert_resource(SaveRegistry::new()
+//!     .with_version(2)
+//!     .with_resource::<Score>()
+//!     // Version 1 called the score "points"
+//!     .with_migration(1, |save| {
+//!         save.rename_resource("points", "score");
+//!         Ok(())
+//!     }));
+//!
+//! // ... then, later on
+//! app.save_game("saves/slot_1.ron")?;
+//! app.load_game("saves/slot_1.ron")?;
+//! ```
+//!
+//! Saves are written as RON if the file ends in `.ron`, and as compact binary otherwise. Every
+//! save has two versions: the version of the file format, which only the engine bumps, and the
+//! version of the game's own save data, set with `SaveRegistry::with_version`. Saves from older
+//! versions of the game are upgraded, a version at a time, by the migrations registered for them;
+//! saves from newer versions are refused.
+
+use super::Scene;
+use super::file::{COMPONENT_DEPTH, ron_options};
+use super::prefab::{nth_live, with_occurrences};
+use crate::ecs::entity::{Entity, friendly::FriendlyEntity, reference::{self, EntityMapping}};
+use crate::ecs::component::{ComponentType, FriendlyComponent, persist::Persist};
+use crate::ecs::component::reflect::{Reflect, Value};
+use crate::ecs::component::registry::{self, ComponentRegistry};
+use crate::application::resources::Resources;
+use crate::error::Error as GompError;
+use crate::versioned;
+
+use bincode::Options;
+use serde::{Serialize, Deserialize, Deserializer};
+use log::{debug, warn};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::rc::Rc;
+
+/// The first bytes of every binary save.
+const MAGIC: &[u8; 8] = b"GOMPSAVE";
+
+/// The newest save file format this engine can read (and the one it writes).
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+/// Upgrades a save from one version of the game's save data to the next.
+pub type Migration = Box<dyn Fn(&mut SaveGame) -> Result<(), GompError>>;
+
+/// What a save is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    Ron,
+    Binary,
+}
+
+impl SaveFormat {
+    /// Works out the format from a file's extension: RON for `.ron`, binary for anything else.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some("ron") => Self::Ron,
+            _ => Self::Binary,
+        }
+    }
+}
+
+/// A snapshot of a scene's persisted entities, and the saved resources.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    #[serde(deserialize_with = "deserialize_format")]
+    format: u32,
+
+    /// The version of the game's save data.
+    version: u32,
+
+    /// The name of the scene the save was made in.
+    scene: String,
+
+    #[serde(default)]
+    entities: Vec<FriendlyEntity>,
+
+    /// Written like components: the name of the resource, and its fields.
+    #[serde(default)]
+    resources: Vec<FriendlyComponent>,
+}
+
+impl SaveGame {
+    /// Gets the version of the game's save data the save was made with.
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Gets the name of the scene the save was made in.
+    pub fn get_scene(&self) -> &String {
+        &self.scene
+    }
+
+    /// Gets the saved entities.
+    pub fn get_entities(&self) -> &Vec<FriendlyEntity> {
+        &self.entities
+    }
+
+    /// Gets mutable references to the saved entities, to migrate them.
+    pub fn get_mut_entities(&mut self) -> &mut Vec<FriendlyEntity> {
+        &mut self.entities
+    }
+
+    /// Gets the saved resources.
+    pub fn get_resources(&self) -> &Vec<FriendlyComponent> {
+        &self.resources
+    }
+
+    /// Gets mutable references to the saved resources, to migrate them.
+    pub fn get_mut_resources(&mut self) -> &mut Vec<FriendlyComponent> {
+        &mut self.resources
+    }
+
+    /// Gets a saved resource, by the name it's registered as.
+    pub fn get_resource(&self, name: &str) -> Option<&FriendlyComponent> {
+        self.resources.iter().find(|resource| resource.get_type_name() == name)
+    }
+
+    /// Renames a saved resource, for when it's been renamed in the game.
+    pub fn rename_resource(&mut self, from: &str, to: &str) {
+        for resource in self.resources.iter_mut().filter(|resource| resource.get_type_name() == from) {
+            let mut renamed = FriendlyComponent::new(to);
+
+            for (field, value) in resource.get_fields() {
+                renamed.set_field(field, value.clone());
+            }
+
+            *resource = renamed;
+        }
+    }
+
+    /// Loads a save, in the format given by its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GompError> {
+        let path = path.as_ref();
+        let file = path.display().to_string();
+
+        match SaveFormat::from_path(path) {
+            SaveFormat::Ron => Self::from_str(&std::fs::read_to_string(path)?, &file),
+            SaveFormat::Binary => Self::from_bytes(&std::fs::read(path)?),
+        }
+    }
+
+    /// Saves to a file, in the format given by its extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GompError> {
+        let path = path.as_ref();
+
+        match SaveFormat::from_path(path) {
+            SaveFormat::Ron => std::fs::write(path, self.to_ron()?)?,
+            SaveFormat::Binary => std::fs::write(path, self.to_bytes()?)?,
+        }
+
+        Ok(())
+    }
+
+    /// Reads a save from RON.
+    pub fn from_ron(source: &str) -> Result<Self, GompError> {
+        Self::from_str(source, "<ron>")
+    }
+
+    /// Writes the save as (pretty) RON.
+    pub fn to_ron(&self) -> Result<String, GompError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new().depth_limit(COMPONENT_DEPTH))
+            .map_err(|e| GompError::InvalidSave(e.to_string()))
+    }
+
+    /// Encodes the save as binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GompError> {
+        let mut bytes = versioned::write_header(MAGIC, self.format);
+
+        bincode::DefaultOptions::new()
+            .serialize_into(&mut bytes, &BinarySave::from(self))
+            .map_err(|e| GompError::InvalidSave(e.to_string()))?;
+
+        Ok(bytes)
+    }
+
+    /// Decodes a binary save.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GompError> {
+        let (format, body) = versioned::read_header(bytes, MAGIC, "save", SAVE_FORMAT_VERSION)
+            .map_err(GompError::InvalidSave)?;
+
+        let save: BinarySave = bincode::DefaultOptions::new()
+            .deserialize(body)
+            .map_err(|e| GompError::InvalidSave(e.to_string()))?;
+
+        Ok(save.into_save(format))
+    }
+
+    /// Reads a save from RON. `file` is only used in errors.
+    fn from_str(source: &str, file: &str) -> Result<Self, GompError> {
+        ron_options().from_str(source).map_err(|e| {
+            GompError::InvalidSave(format!("{}:{}:{}: {}", file, e.position.line, e.position.col, e.code))
+        })
+    }
+}
+
+fn deserialize_format<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    versioned::deserialize_version(deserializer, "save", SAVE_FORMAT_VERSION)
+}
+
+/// A resource that can be saved.
+struct SavedResource {
+    name: &'static str,
+    capture: fn(&Resources) -> Option<FriendlyComponent>,
+    restore: fn(&mut Resources, &FriendlyComponent),
+}
+
+/// Which resources go in save games, and how to upgrade old saves. The application uses the one
+/// in its resources (or an empty one) for `Application::save_game` and `Application::load_game`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SaveRegistry {
+    /// The version of the game's save data.
+    version: u32,
+
+    #[derivative(Debug="ignore")]
+    resources: Vec<SavedResource>,
+
+    /// By the version they upgrade from.
+    #[derivative(Debug="ignore")]
+    migrations: BTreeMap<u32, Migration>,
+}
+
+impl SaveRegistry {
+    /// Saves no resources, at version 1.
+    pub fn new() -> Self {
+        Self {
+            version: 1,
+            resources: vec![],
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the version of the game's save data. Bump it whenever saves need migrating.
+    pub fn with_version(self, version: u32) -> Self { Self { version, .. self } }
+
+    /// Saves a resource.
+    pub fn with_resource<R: Reflect + Default + 'static>(mut self) -> Self {
+        self.register_resource::<R>();
+        self
+    }
+
+    /// Saves a resource. Registering the same one twice does nothing.
+    pub fn register_resource<R: Reflect + Default + 'static>(&mut self) {
+        if self.resources.iter().any(|resource| resource.name == R::type_name()) {
+            return;
+        }
+
+        self.resources.push(SavedResource {
+            name: R::type_name(),
+            capture: capture_resource::<R>,
+            restore: restore_resource::<R>,
+        });
+    }
+
+    /// Upgrades saves from version `from` to `from + 1`. Versions that don't change anything
+    /// don't need one.
+    pub fn with_migration<F>(mut self, from: u32, migration: F) -> Self
+    where
+        F: Fn(&mut SaveGame) -> Result<(), GompError> + 'static,
+    {
+        self.migrations.insert(from, Box::new(migration));
+        self
+    }
+
+    /// Gets the version of the game's save data.
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Snapshots the persisted entities of a scene, and the saved resources.
+    pub fn capture(&self, scene: &Scene, resources: &Resources) -> SaveGame {
+        let persisted: Vec<&Rc<Entity>> = scene.get_entities().iter()
+            .filter(|entity| entity.find_component(ComponentType::Persist).is_some())
+            .collect();
+
+        let places = persisted.iter()
+            .enumerate()
+            .map(|(place, entity)| (Rc::as_ptr(entity), place))
+            .collect();
+
+        let entities = reference::with_mapping(EntityMapping::Saving(places), || {
+            registry::with_registry(|registry| persisted.iter().map(|entity| describe_persisted(entity, registry)).collect())
+        });
+
+        SaveGame {
+            format: SAVE_FORMAT_VERSION,
+            version: self.version,
+            scene: scene.get_name().clone(),
+            entities,
+            resources: self.resources.iter().filter_map(|resource| (resource.capture)(resources)).collect(),
+        }
+    }
+
+    /// Upgrades a save to the current version of the game's save data.
+    pub fn migrate(&self, save: &mut SaveGame) -> Result<(), GompError> {
+        if save.version > self.version {
+            return Err(GompError::InvalidSave(format!(
+                "save version {} is newer than the game's ({})",
+                save.version,
+                self.version,
+            )));
+        }
+
+        while save.version < self.version {
+            debug!("Migrating save from version {}", save.version);
+
+            if let Some(migration) = self.migrations.get(&save.version) {
+                migration(save)?;
+            }
+
+            save.version += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Upgrades a save, then puts it back into a scene and the resources (see the module docs).
+    pub fn restore(&self, mut save: SaveGame, scene: &mut Scene, resources: &mut Resources) -> Result<(), GompError> {
+        self.migrate(&mut save)?;
+
+        if *scene.get_name() != save.scene {
+            warn!("Loading a save made in scene {} into scene {}", save.scene, scene.get_name());
+        }
+
+        let mut unmatched: Vec<Rc<Entity>> = scene.get_entities().iter()
+            .filter(|entity| entity.find_component(ComponentType::Persist).is_some())
+            .cloned()
+            .collect();
+
+        // Every entity has to be there before references to them can be pointed anywhere
+        let loaded: Vec<Rc<Entity>> = save.entities.iter()
+            .map(|saved| match unmatched.iter().position(|entity| entity.has_key(saved.get_key())) {
+                Some(index) => unmatched.remove(index),
+                None => scene.spawn(saved),
+            })
+            .collect();
+
+        for entity in &unmatched {
+            scene.despawn(entity);
+        }
+
+        let places = loaded.iter().map(Rc::downgrade).collect();
+
+        reference::with_mapping(EntityMapping::Loading(places), || {
+            registry::with_registry(|registry| {
+                for (saved, entity) in save.entities.iter().zip(&loaded) {
+                    restore_entity(scene, entity, saved, registry);
+                }
+            });
+        });
+
+        for saved in &save.resources {
+            match self.resources.iter().find(|resource| resource.name == saved.get_type_name()) {
+                Some(resource) => (resource.restore)(resources, saved),
+                None => warn!("Leaving out saved resource {}, it isn't registered", saved.get_type_name()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SaveRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Describes a persisted entity, with only the components it persists.
+fn describe_persisted(entity: &Entity, registry: &ComponentRegistry) -> FriendlyEntity {
+    let mut friendly = FriendlyEntity::from_entity(entity, registry);
+
+    if let Some(persist) = entity.find_component(ComponentType::Persist) {
+        if let Some(persist) = persist.borrow().as_any().downcast_ref::<Persist>() {
+            friendly.get_mut_components().retain(|component| persist.persists(component.get_type_name()));
+        }
+    }
+
+    friendly
+}
+
+/// Sets every saved field of a loaded entity, attaching any components it's missing.
+fn restore_entity(scene: &mut Scene, entity: &Rc<Entity>, saved: &FriendlyEntity, registry: &ComponentRegistry) {
+    for (component, nth) in with_occurrences(saved.get_components()) {
+        let live = match nth_live(entity, component.get_type_name(), nth, registry) {
+            Some(live) => live,
+            None => {
+                if let Err(e) = scene.attach(entity, component) {
+                    warn!("Couldn't load a component of {}: {}", saved.get_name(), e);
+                }
+
+                continue;
+            },
+        };
+
+        for (field, value) in component.get_fields() {
+            if let Err(e) = registry.set_path(&mut **live.borrow_mut(), field, value.clone()) {
+                warn!("Couldn't load {}: {}", saved.get_name(), e);
+            }
+        }
+    }
+}
+
+fn capture_resource<R: Reflect + 'static>(resources: &Resources) -> Option<FriendlyComponent> {
+    let resource = resources.get::<R>()?;
+    let mut friendly = FriendlyComponent::new(R::type_name());
+
+    for field in R::fields() {
+        if let Some(value) = resource.get_field(field.get_name()) {
+            friendly.set_field(field.get_name(), value);
+        }
+    }
+
+    Some(friendly)
+}
+
+fn restore_resource<R: Reflect + Default + 'static>(resources: &mut Resources, saved: &FriendlyComponent) {
+    let resource = resources.get_or_insert_with(R::default);
+
+    for (field, value) in saved.get_fields() {
+        if let Err(e) = resource.set_field(field, value) {
+            warn!("Couldn't load resource {}: {}", R::type_name(), e);
+        }
+    }
+}
+
+/// A save, as it's written in binary. Field values are normally read by working out what they
+/// look like, which binary formats can't do, so they're tagged instead.
+#[derive(Serialize, Deserialize)]
+struct BinarySave {
+    version: u32,
+    scene: String,
+    entities: Vec<BinaryEntity>,
+    resources: Vec<BinaryComponent>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryEntity {
+    name: String,
+    id: Option<String>,
+    components: Vec<BinaryComponent>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryComponent {
+    type_name: String,
+    fields: Vec<(String, BinaryValue)>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum BinaryValue {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<BinaryValue>),
+    Map(BTreeMap<String, BinaryValue>),
+}
+
+impl From<&SaveGame> for BinarySave {
+    fn from(save: &SaveGame) -> Self {
+        Self {
+            version: save.version,
+            scene: save.scene.clone(),
+            entities: save.entities.iter().map(BinaryEntity::from).collect(),
+            resources: save.resources.iter().map(BinaryComponent::from).collect(),
+        }
+    }
+}
+
+impl BinarySave {
+    fn into_save(self, format: u32) -> SaveGame {
+        SaveGame {
+            format,
+            version: self.version,
+            scene: self.scene,
+            entities: self.entities.into_iter().map(FriendlyEntity::from).collect(),
+            resources: self.resources.into_iter().map(FriendlyComponent::from).collect(),
+        }
+    }
+}
+
+impl From<&FriendlyEntity> for BinaryEntity {
+    fn from(entity: &FriendlyEntity) -> Self {
+        Self {
+            name: entity.get_name().clone(),
+            id: entity.get_id().cloned(),
+            components: entity.get_components().iter().map(BinaryComponent::from).collect(),
+        }
+    }
+}
+
+impl From<BinaryEntity> for FriendlyEntity {
+    fn from(binary: BinaryEntity) -> Self {
+        let mut entity = FriendlyEntity::new(&binary.name);
+
+        if let Some(id) = &binary.id {
+            entity = entity.with_id(id);
+        }
+
+        for component in binary.components {
+            entity.push_component(component.into());
+        }
+
+        entity
+    }
+}
+
+impl From<&FriendlyComponent> for BinaryComponent {
+    fn from(component: &FriendlyComponent) -> Self {
+        Self {
+            type_name: component.get_type_name().to_owned(),
+            fields: component.get_fields().iter().map(|(name, value)| (name.clone(), value.into())).collect(),
+        }
+    }
+}
+
+impl From<BinaryComponent> for FriendlyComponent {
+    fn from(binary: BinaryComponent) -> Self {
+        let mut component = FriendlyComponent::new(&binary.type_name);
+
+        for (name, value) in binary.fields {
+            component.set_field(&name, value.into());
+        }
+
+        component
+    }
+}
+
+impl From<&Value> for BinaryValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::None => Self::None,
+            Value::Bool(bool) => Self::Bool(*bool),
+            Value::Int(int) => Self::Int(*int),
+            Value::Float(float) => Self::Float(*float),
+            Value::String(string) => Self::String(string.clone()),
+            Value::List(items) => Self::List(items.iter().map(Self::from).collect()),
+            Value::Map(fields) => Self::Map(fields.iter().map(|(name, value)| (name.clone(), value.into())).collect()),
+        }
+    }
+}
+
+impl From<BinaryValue> for Value {
+    fn from(binary: BinaryValue) -> Self {
+        match binary {
+            BinaryValue::None => Self::None,
+            BinaryValue::Bool(bool) => Self::Bool(bool),
+            BinaryValue::Int(int) => Self::Int(int),
+            BinaryValue::Float(float) => Self::Float(float),
+            BinaryValue::String(string) => Self::String(string),
+            BinaryValue::List(items) => Self::List(items.into_iter().map(Self::from).collect()),
+            BinaryValue::Map(fields) => Self::Map(fields.into_iter().map(|(name, value)| (name, value.into())).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::scene::friendly::FriendlyScene;
+    use crate::ecs::component::Component;
+    use crate::ecs::entity::reference::EntityRef;
+    use crate::time::Time;
+
+    use std::any::Any;
+    use std::rc::Weak;
+
+    /// Points at another entity, like a homing missile.
+    #[derive(Reflect)]
+    struct Homing {
+        #[reflect(skip)]
+        parent: Weak<Entity>,
+
+        target: EntityRef,
+        speed: f64,
+    }
+
+    impl Component for Homing {
+        fn from(parent: Weak<Entity>) -> Self {
+            Self {
+                parent,
+                target: EntityRef::none(),
+                speed: 1.0,
+            }
+        }
+
+        fn type_of(&self) -> ComponentType {
+            ComponentType::Custom("homing")
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_mut_any(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn get_weak_parent(&self) -> &Weak<Entity> {
+            &self.parent
+        }
+
+        fn get_parent(&self) -> Rc<Entity> {
+            self.parent.upgrade().expect("dangling weak pointer to parent")
+        }
+
+        fn on_update(&mut self, _time: &Time) {}
+
+        fn on_start(&mut self) {}
+
+        fn on_stop(&mut self) {}
+    }
+
+    #[derive(Reflect, Default, Debug, PartialEq)]
+    struct Score {
+        left: u32,
+        right: u32,
+    }
+
+    fn persisted(name: &str) -> FriendlyEntity {
+        FriendlyEntity::new(name).with_component(FriendlyComponent::new("persist"))
+    }
+
+    fn find(scene: &Scene, name: &str) -> Rc<Entity> {
+        scene.get_entities().iter()
+            .find(|entity| *entity.get_name() == name)
+            .cloned()
+            .unwrap_or_else(|| panic!("no entity called {}", name))
+    }
+
+    fn with_homing<R>(entity: &Entity, f: impl FnOnce(&mut Homing) -> R) -> R {
+        let component = entity.find_component(ComponentType::Custom("homing")).expect("a homing component");
+        let mut component = component.borrow_mut();
+
+        f(component.as_mut_any().downcast_mut().unwrap())
+    }
+
+    /// A scene with a player, a missile aimed at it, and a wall that isn't saved.
+    fn scene() -> Scene {
+        registry::register_component::<Homing>();
+
+        let scene: Scene = FriendlyScene::new("level")
+            .with_entity(FriendlyEntity::new("wall").with_component(FriendlyComponent::transform([0.0; 3])))
+            .with_entity(persisted("player").with_id("p1").with_component(FriendlyComponent::transform([1.0, 2.0, 0.0])))
+            .with_entity(persisted("missile").with_component(FriendlyComponent::new("homing").with_field("speed", 5.0)))
+            .into();
+
+        let player = find(&scene, "player");
+        with_homing(&find(&scene, "missile"), |homing| homing.target = EntityRef::new(&player));
+
+        scene
+    }
+
+    fn registry() -> SaveRegistry {
+        SaveRegistry::new().with_resource::<Score>()
+    }
+
+    fn capture() -> SaveGame {
+        let mut resources = Resources::new();
+        resources.insert(Score { left: 3, right: 1 });
+
+        registry().capture(&scene(), &resources)
+    }
+
+    #[test]
+    fn only_persisted_entities_are_saved() {
+        let save = capture();
+        let names: Vec<&String> = save.get_entities().iter().map(FriendlyEntity::get_name).collect();
+
+        assert_eq!(names, ["player", "missile"]);
+        assert_eq!(save.get_resource("score").unwrap().get_field("left"), Some(&Value::Int(3)));
+    }
+
+    #[test]
+    fn references_are_saved_as_places() {
+        let save = capture();
+        let homing = save.get_entities()[1].get_components().iter()
+            .find(|component| component.get_type_name() == "homing")
+            .unwrap();
+
+        assert_eq!(homing.get_field("target"), Some(&Value::Int(0)));
+    }
+
+    #[test]
+    fn saves_round_trip() {
+        let save = capture();
+
+        assert_eq!(SaveGame::from_bytes(&save.to_bytes().unwrap()).unwrap(), save);
+        assert_eq!(SaveGame::from_ron(&save.to_ron().unwrap()).unwrap(), save);
+    }
+
+    #[test]
+    fn newer_formats_are_refused() {
+        let mut bytes = capture().to_bytes().unwrap();
+        bytes[8..12].copy_from_slice(&(SAVE_FORMAT_VERSION + 1).to_le_bytes());
+
+        assert!(matches!(SaveGame::from_bytes(&bytes), Err(GompError::InvalidSave(_))));
+    }
+
+    #[test]
+    fn restoring_matches_spawns_despawns_and_remaps() {
+        let save = capture();
+
+        // The player has moved on, the missile is gone, and something new turned up
+        registry::register_component::<Homing>();
+        let mut scene: Scene = FriendlyScene::new("level")
+            .with_entity(FriendlyEntity::new("wall"))
+            .with_entity(persisted("player (renamed)").with_id("p1").with_component(FriendlyComponent::transform([9.0, 9.0, 0.0])))
+            .with_entity(persisted("ghost"))
+            .into();
+        let player = find(&scene, "player (renamed)");
+
+        let mut resources = Resources::new();
+        registry().restore(save, &mut scene, &mut resources).unwrap();
+
+        let names: Vec<String> = scene.get_entities().iter().map(|entity| entity.get_name().clone()).collect();
+        assert_eq!(names, ["wall", "player (renamed)", "missile"]);
+
+        // The player was matched by its ID, and the missile aims at it, not at a copy
+        let transform = FriendlyComponent::describe(&**player.find_component(ComponentType::Transform).unwrap().borrow()).unwrap();
+        assert_eq!(transform.get_field("position"), Some(&Value::List(vec![Value::Float(1.0), Value::Float(2.0), Value::Float(0.0)])));
+
+        with_homing(&find(&scene, "missile"), |homing| {
+            assert!(homing.target.is(&player));
+            assert_eq!(homing.speed, 5.0);
+        });
+
+        assert_eq!(resources.get::<Score>(), Some(&Score { left: 3, right: 1 }));
+    }
+
+    #[test]
+    fn newer_game_versions_are_refused() {
+        let mut save = capture();
+        save.version = 2;
+
+        let mut scene = scene();
+        let result = registry().restore(save, &mut scene, &mut Resources::new());
+
+        assert!(matches!(result, Err(GompError::InvalidSave(_))));
+    }
+
+    #[test]
+    fn old_saves_are_migrated() {
+        let mut save = capture();
+        save.rename_resource("score", "points");
+
+        let registry = SaveRegistry::new()
+            .with_version(2)
+            .with_migration(1, |save| {
+                save.rename_resource("points", "score");
+                Ok(())
+            });
+
+        registry.migrate(&mut save).unwrap();
+
+        assert_eq!(save.get_version(), 2);
+        assert!(save.get_resource("score").is_some());
+    }
+}
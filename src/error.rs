@@ -3,8 +3,8 @@ use thiserror::Error;
 /// WordCountError enumerates all possible errors returned by this library.
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("Failed to compile WGSL shader")]
-    WgslCompilationFailure,
+    #[error("Failed to compile WGSL shader: {0}")]
+    WgslCompilationFailure(String),
 
     #[error("Unsupported shader type")]
     UnsupportedShaderType,
@@ -15,6 +15,44 @@ pub enum Error {
     #[error("No device supplied")]
     NoDeviceSupplied,
 
+    #[error("No shader module supplied")]
+    NoShaderSupplied,
+
+    #[error("No target texture format supplied")]
+    NoTargetFormat,
+
+    #[error("Invalid or unsupported font data")]
+    InvalidFont,
+
+    #[error("Invalid config file: {0}")]
+    InvalidConfig(String),
+
+    #[error("Invalid input recording: {0}")]
+    InvalidRecording(String),
+
+    #[error("Invalid component field: {0}")]
+    InvalidField(String),
+
+    #[error("Invalid prefab: {0}")]
+    InvalidPrefab(String),
+
+    #[error("Invalid asset: {0}")]
+    InvalidAsset(String),
+
+    #[error("Invalid save game: {0}")]
+    InvalidSave(String),
+
+    #[error("Invalid asset pack: {0}")]
+    InvalidPack(String),
+
+    #[error("Invalid scene file {file}:{line}:{column}: {message}")]
+    InvalidScene {
+        file: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
     /// Represents all other cases of `std::io::Error`.
     #[error(transparent)]
     IOError(#[from] std::io::Error),
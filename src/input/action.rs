@@ -0,0 +1,612 @@
+//! Input actions: named inputs that players can rebind.
+//!
+//! Instead of asking "is W held?", games ask "is `paddle_up` held?", and an `ActionMap` decides
+//! which inputs that means. Actions are on or off, and are bound to one or more `Binding`s (any of
+//! which turns the action on). A binding can be a chord of several inputs that all have to be held,
+//! and can require modifier keys. Axes (`move`) go from -1 to 1, and are bound to pairs of digital
+//! inputs or to analogue gamepad axes.
+//!
+//! Action maps are saved to and loaded from TOML files, so bindings can live in a config file:
+//!
+//! ```toml
+//! [[actions.paddle_up]]
+//! inputs = [{ type = "Key", value = "W" }]
+//!
+//! [[actions.paddle_up]]
+//! inputs = [{ type = "GamepadButton", value = "DPadUp" }]
+//!
+//! [[actions.save]]
+//! inputs = [{ type = "Key", value = "S" }]
+//! modifiers = { control = true }
+//!
+//! [[axes.paddle]]
+//! type = "Gamepad"
+//! axis = "LeftStickY"
+//! ```
+//!
+//! The `Actions` resource works out the state of every action each frame (after input is in, but
+//! before anything else runs). `Actions::start_rebind` captures the next input pressed and binds
+//! it to an action, for controls menus.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! actions.get_mut_map()
+//!     .bind_action("paddle_up", Binding::new(InputSource::Key(VirtualKeyCode::W)))
+//!     .bind_axis("paddle", AxisBinding::buttons(
+//!         Binding::new(InputSource::Key(VirtualKeyCode::S)),
+//!         Binding::new(InputSource::Key(VirtualKeyCode::W)),
+//!     ));
+//!
+//! if actions.pressed("paddle_up") {
+//!     paddle.y += speed * time.delta_seconds();
+//! }
+//! ```
+
+use super::Input;
+use super::gamepad::{GamepadButton, GamepadAxis, apply_dead_zone};
+use crate::error::Error as GompError;
+
+use winit::event::{ModifiersState, MouseButton, ScanCode, VirtualKeyCode};
+use serde::{Serialize, Deserialize};
+use log::{debug, info};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// How far a gamepad axis has to be pushed to count as pressed, when bound to an action.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Something that can be pressed. Tagged with a `type`, as TOML has nothing like Rust's enums.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum InputSource {
+    /// A key, by what it's labelled (so it moves with the keyboard layout).
+    Key(VirtualKeyCode),
+
+    /// A key, by where it is on the keyboard.
+    ScanCode(ScanCode),
+
+    /// A mouse button.
+    Mouse(#[serde(with = "mouse_button")] MouseButton),
+
+    /// A button on any gamepad.
+    GamepadButton(GamepadButton),
+
+    /// A gamepad axis pushed (most of the way) in one direction, on any gamepad.
+    GamepadAxis { axis: GamepadAxis, positive: bool },
+}
+
+impl InputSource {
+    /// Is the input held, or was it tapped this frame.
+    fn is_held(&self, input: &Input) -> bool {
+        match *self {
+            Self::Key(key) => input.key_pressed(key) || input.key_just_pressed(key),
+            Self::ScanCode(scancode) => input.scancode_pressed(scancode) || input.scancode_just_pressed(scancode),
+            Self::Mouse(button) => input.mouse_pressed(button) || input.mouse_just_pressed(button),
+            Self::GamepadButton(button) => {
+                let buttons = input.get_gamepad_buttons();
+
+                buttons.get_pressed().chain(buttons.get_just_pressed()).any(|(_, b)| *b == button)
+            },
+            Self::GamepadAxis { axis, positive } => {
+                let value = input.get_any_gamepad_axis(axis);
+
+                if positive { value >= AXIS_PRESS_THRESHOLD } else { value <= -AXIS_PRESS_THRESHOLD }
+            },
+        }
+    }
+}
+
+/// Mouse buttons as `"Left"`, `"Right"` or `"Middle"`, or a number for the others (winit's own
+/// encoding of those doesn't fit in TOML).
+mod mouse_button {
+    use winit::event::MouseButton;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::de::Error;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Encoded {
+        Named(String),
+        Other(u16),
+    }
+
+    pub fn serialize<S: Serializer>(button: &MouseButton, serializer: S) -> Result<S::Ok, S::Error> {
+        match *button {
+            MouseButton::Left => Encoded::Named("Left".to_owned()),
+            MouseButton::Right => Encoded::Named("Right".to_owned()),
+            MouseButton::Middle => Encoded::Named("Middle".to_owned()),
+            MouseButton::Other(button) => Encoded::Other(button),
+        }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MouseButton, D::Error> {
+        match Encoded::deserialize(deserializer)? {
+            Encoded::Named(name) => match name.as_str() {
+                "Left" => Ok(MouseButton::Left),
+                "Right" => Ok(MouseButton::Right),
+                "Middle" => Ok(MouseButton::Middle),
+                _ => Err(D::Error::unknown_variant(&name, &["Left", "Right", "Middle"])),
+            },
+            Encoded::Other(button) => Ok(MouseButton::Other(button)),
+        }
+    }
+}
+
+/// Modifier keys a binding needs held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl Modifiers {
+    /// No modifiers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets if shift is needed.
+    pub fn with_shift(self, shift: bool) -> Self { Self { shift, .. self } }
+
+    /// Sets if control is needed.
+    pub fn with_control(self, control: bool) -> Self { Self { control, .. self } }
+
+    /// Sets if alt is needed.
+    pub fn with_alt(self, alt: bool) -> Self { Self { alt, .. self } }
+
+    /// Sets if the logo (windows, command) key is needed.
+    pub fn with_logo(self, logo: bool) -> Self { Self { logo, .. self } }
+
+    /// Are no modifiers needed.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Are all the needed modifiers held. Extra ones don't matter.
+    fn are_held(&self, held: ModifiersState) -> bool {
+        (!self.shift || held.shift())
+            && (!self.control || held.ctrl())
+            && (!self.alt || held.alt())
+            && (!self.logo || held.logo())
+    }
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Self {
+        Self {
+            shift: state.shift(),
+            control: state.ctrl(),
+            alt: state.alt(),
+            logo: state.logo(),
+        }
+    }
+}
+
+/// A combination of inputs that turns an action on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    /// Every one of these has to be held (more than one makes a chord).
+    pub inputs: Vec<InputSource>,
+
+    /// Modifier keys that have to be held too.
+    #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    /// A binding to a single input.
+    pub fn new(input: InputSource) -> Self {
+        Self::chord(vec![input])
+    }
+
+    /// A binding to several inputs held at once.
+    pub fn chord(inputs: Vec<InputSource>) -> Self {
+        Self {
+            inputs,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// Sets the modifier keys that have to be held.
+    pub fn with_modifiers(self, modifiers: Modifiers) -> Self { Self { modifiers, .. self } }
+
+    /// Is every input in the binding held.
+    fn is_held(&self, input: &Input) -> bool {
+        !self.inputs.is_empty()
+            && self.inputs.iter().all(|source| source.is_held(input))
+            && self.modifiers.are_held(input.get_modifiers())
+    }
+}
+
+/// Gamepads already have dead zones (see `Gamepads::set_dead_zone`), so bindings don't need their
+/// own unless they want a bigger one.
+fn default_dead_zone() -> f32 {
+    0.0
+}
+
+/// Something that moves an axis. Tagged with a `type`, like `InputSource`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AxisBinding {
+    /// Two bindings, one pushing the axis to -1 and one to 1.
+    Buttons { negative: Binding, positive: Binding },
+
+    /// An analogue gamepad axis (on any gamepad). Anything within the dead zone counts as 0, on
+    /// top of the gamepad's own dead zone.
+    Gamepad {
+        axis: GamepadAxis,
+        #[serde(default)]
+        invert: bool,
+        #[serde(default = "default_dead_zone")]
+        dead_zone: f32,
+    },
+}
+
+impl AxisBinding {
+    /// An axis moved by two bindings.
+    pub fn buttons(negative: Binding, positive: Binding) -> Self {
+        Self::Buttons { negative, positive }
+    }
+
+    /// An axis moved by a gamepad axis, with no extra dead zone.
+    pub fn gamepad(axis: GamepadAxis) -> Self {
+        Self::Gamepad {
+            axis,
+            invert: false,
+            dead_zone: default_dead_zone(),
+        }
+    }
+
+    /// Gets the value of the axis, from -1 to 1.
+    fn value(&self, input: &Input) -> f32 {
+        match self {
+            Self::Buttons { negative, positive } => positive.is_held(input) as u8 as f32 - negative.is_held(input) as u8 as f32,
+            Self::Gamepad { axis, invert, dead_zone } => {
+                let value = apply_dead_zone(input.get_any_gamepad_axis(*axis), *dead_zone);
+
+                if *invert { -value } else { value }
+            },
+        }
+    }
+}
+
+/// Which inputs every action and axis is bound to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap {
+    #[serde(default)]
+    actions: BTreeMap<String, Vec<Binding>>,
+
+    #[serde(default)]
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    /// Creates an empty action map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to an action.
+    pub fn with_action(mut self, action: &str, binding: Binding) -> Self {
+        self.bind_action(action, binding);
+        self
+    }
+
+    /// Adds a binding to an axis.
+    pub fn with_axis(mut self, axis: &str, binding: AxisBinding) -> Self {
+        self.bind_axis(axis, binding);
+        self
+    }
+
+    /// Adds a binding to an action.
+    pub fn bind_action(&mut self, action: &str, binding: Binding) -> &mut Self {
+        self.actions.entry(action.to_owned()).or_default().push(binding);
+        self
+    }
+
+    /// Adds a binding to an axis.
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) -> &mut Self {
+        self.axes.entry(axis.to_owned()).or_default().push(binding);
+        self
+    }
+
+    /// Removes every binding from an action.
+    pub fn unbind_action(&mut self, action: &str) -> &mut Self {
+        self.actions.remove(action);
+        self
+    }
+
+    /// Removes every binding from an axis.
+    pub fn unbind_axis(&mut self, axis: &str) -> &mut Self {
+        self.axes.remove(axis);
+        self
+    }
+
+    /// Gets the bindings of an action.
+    pub fn get_action_bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Gets the bindings of an axis.
+    pub fn get_axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Gets the names of every action.
+    pub fn get_actions(&self) -> impl Iterator<Item = &String> {
+        self.actions.keys()
+    }
+
+    /// Gets the names of every axis.
+    pub fn get_axes(&self) -> impl Iterator<Item = &String> {
+        self.axes.keys()
+    }
+
+    /// Parses an action map from TOML.
+    pub fn from_toml(source: &str) -> Result<Self, GompError> {
+        toml::from_str(source).map_err(|e| GompError::InvalidConfig(e.to_string()))
+    }
+
+    /// Writes the action map as TOML.
+    pub fn to_toml(&self) -> Result<String, GompError> {
+        toml::to_string_pretty(self).map_err(|e| GompError::InvalidConfig(e.to_string()))
+    }
+
+    /// Loads an action map from a TOML file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GompError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Saves the action map to a TOML file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GompError> {
+        std::fs::write(path, self.to_toml()?)?;
+
+        Ok(())
+    }
+}
+
+/// An action waiting for the next input to be bound to it.
+#[derive(Debug, Clone)]
+struct Rebind {
+    action: String,
+    replace: bool,
+}
+
+/// The state of every action and axis, worked out from `Input` every frame.
+#[derive(Debug, Clone)]
+pub struct Actions {
+    map: ActionMap,
+
+    /// Actions that are on this frame, and were last frame.
+    pressed: HashSet<String>,
+    previous: HashSet<String>,
+
+    axes: HashMap<String, f32>,
+
+    rebind: Option<Rebind>,
+    /// The last binding captured by a rebind, and which action it went to.
+    rebound: Option<(String, Binding)>,
+}
+
+impl Actions {
+    /// Creates the state for an action map, with nothing pressed.
+    pub fn new(map: ActionMap) -> Self {
+        Self {
+            map,
+            pressed: HashSet::new(),
+            previous: HashSet::new(),
+            axes: HashMap::new(),
+            rebind: None,
+            rebound: None,
+        }
+    }
+
+    /// Gets the action map.
+    pub fn get_map(&self) -> &ActionMap {
+        &self.map
+    }
+
+    /// Gets a mutable reference to the action map, to change bindings.
+    pub fn get_mut_map(&mut self) -> &mut ActionMap {
+        &mut self.map
+    }
+
+    /// Swaps the action map for another.
+    pub fn set_map(&mut self, map: ActionMap) {
+        self.map = map;
+    }
+
+    /// Replaces the action map with one loaded from a TOML file.
+    pub fn load_bindings<P: AsRef<Path>>(&mut self, path: P) -> Result<(), GompError> {
+        self.map = ActionMap::load(&path)?;
+        info!("Loaded input bindings from {}", path.as_ref().display());
+
+        Ok(())
+    }
+
+    /// Saves the action map to a TOML file.
+    pub fn save_bindings<P: AsRef<Path>>(&self, path: P) -> Result<(), GompError> {
+        self.map.save(&path)?;
+        info!("Saved input bindings to {}", path.as_ref().display());
+
+        Ok(())
+    }
+
+    /// Is the action on.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    /// Did the action turn on this frame.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action) && !self.previous.contains(action)
+    }
+
+    /// Did the action turn off this frame.
+    pub fn just_released(&self, action: &str) -> bool {
+        !self.pressed.contains(action) && self.previous.contains(action)
+    }
+
+    /// Gets the value of an axis, from -1 to 1.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+
+    /// Binds the next input pressed to an action, either replacing its bindings or adding to them.
+    /// Modifier keys held at the time are included. Every action is off until the input is
+    /// captured, so it doesn't trigger anything.
+    pub fn start_rebind(&mut self, action: &str, replace: bool) {
+        debug!("Waiting for an input to bind to {}", action);
+
+        self.rebind = Some(Rebind {
+            action: action.to_owned(),
+            replace,
+        });
+    }
+
+    /// Stops waiting for an input to bind.
+    pub fn cancel_rebind(&mut self) {
+        self.rebind = None;
+    }
+
+    /// Are we waiting for an input to bind.
+    pub fn is_rebinding(&self) -> bool {
+        self.rebind.is_some()
+    }
+
+    /// Takes the binding captured by the last rebind (and the action it was bound to), if there
+    /// is one that hasn't been taken yet.
+    pub fn take_rebound(&mut self) -> Option<(String, Binding)> {
+        self.rebound.take()
+    }
+
+    /// Works out the state of every action and axis. Called once a frame.
+    pub fn update(&mut self, input: &Input) {
+        self.previous = std::mem::take(&mut self.pressed);
+        self.axes.clear();
+
+        if self.rebind.is_some() {
+            self.capture_rebind(input);
+
+            return;
+        }
+
+        for (action, bindings) in &self.map.actions {
+            if bindings.iter().any(|binding| binding.is_held(input)) {
+                self.pressed.insert(action.clone());
+            }
+        }
+
+        for (axis, bindings) in &self.map.axes {
+            let value: f32 = bindings.iter().map(|binding| binding.value(input)).sum();
+
+            self.axes.insert(axis.clone(), value.clamp(-1.0, 1.0));
+        }
+    }
+
+    /// Binds the first input pressed this frame (that isn't a modifier key) to the action being
+    /// rebound.
+    fn capture_rebind(&mut self, input: &Input) {
+        let source = input.get_keys().get_just_pressed()
+            .find(|key| !is_modifier(**key))
+            .map(|key| InputSource::Key(*key))
+            .or_else(|| input.get_mouse_buttons().get_just_pressed().next().map(|button| InputSource::Mouse(*button)))
+            .or_else(|| input.get_gamepad_buttons().get_just_pressed().next().map(|(_, button)| InputSource::GamepadButton(*button)));
+
+        let (source, rebind) = match (source, self.rebind.take()) {
+            (Some(source), Some(rebind)) => (source, rebind),
+            (None, rebind) => {
+                self.rebind = rebind;
+
+                return;
+            },
+            _ => return,
+        };
+
+        let binding = Binding::new(source).with_modifiers(input.get_modifiers().into());
+        info!("Bound {:?} to {}", binding, rebind.action);
+
+        if rebind.replace {
+            self.map.unbind_action(&rebind.action);
+        }
+
+        self.map.bind_action(&rebind.action, binding.clone());
+        self.rebound = Some((rebind.action, binding));
+    }
+}
+
+impl Default for Actions {
+    fn default() -> Self {
+        Self::new(ActionMap::new())
+    }
+}
+
+/// Is the key a modifier key. These are bound as modifiers, rather than on their own.
+fn is_modifier(key: VirtualKeyCode) -> bool {
+    matches!(key,
+        VirtualKeyCode::LShift | VirtualKeyCode::RShift
+        | VirtualKeyCode::LControl | VirtualKeyCode::RControl
+        | VirtualKeyCode::LAlt | VirtualKeyCode::RAlt
+        | VirtualKeyCode::LWin | VirtualKeyCode::RWin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_maps_round_trip_through_toml() {
+        let map = ActionMap::new()
+            .with_action("jump", Binding::new(InputSource::Key(VirtualKeyCode::Space)))
+            .with_action("jump", Binding::new(InputSource::ScanCode(57)))
+            .with_action("jump", Binding::new(InputSource::GamepadButton(GamepadButton::South)))
+            .with_action("fire", Binding::new(InputSource::Mouse(MouseButton::Left)))
+            .with_action("fire", Binding::new(InputSource::Mouse(MouseButton::Other(4))))
+            .with_action("lean", Binding::new(InputSource::GamepadAxis { axis: GamepadAxis::RightStickX, positive: false }))
+            .with_action("save", Binding::new(InputSource::Key(VirtualKeyCode::S)).with_modifiers(Modifiers::new().with_control(true)))
+            .with_action("sprint_jump", Binding::chord(vec![
+                InputSource::Key(VirtualKeyCode::LShift),
+                InputSource::Key(VirtualKeyCode::Space),
+            ]))
+            .with_axis("move", AxisBinding::buttons(
+                Binding::new(InputSource::Key(VirtualKeyCode::A)),
+                Binding::new(InputSource::Key(VirtualKeyCode::D)),
+            ))
+            .with_axis("move", AxisBinding::gamepad(GamepadAxis::LeftStickX))
+            .with_axis("look", AxisBinding::Gamepad { axis: GamepadAxis::RightStickY, invert: true, dead_zone: 0.25 });
+
+        let toml = map.to_toml().unwrap();
+
+        assert_eq!(ActionMap::from_toml(&toml).unwrap(), map);
+    }
+
+    #[test]
+    fn action_maps_read_the_documented_format() {
+        let map = ActionMap::from_toml(r#"
+            [[actions.paddle_up]]
+            inputs = [{ type = "Key", value = "W" }]
+
+            [[actions.save]]
+            inputs = [{ type = "Key", value = "S" }]
+            modifiers = { control = true }
+
+            [[axes.paddle]]
+            type = "Gamepad"
+            axis = "LeftStickY"
+        "#).unwrap();
+
+        assert_eq!(map.get_action_bindings("paddle_up"), [Binding::new(InputSource::Key(VirtualKeyCode::W))]);
+        assert_eq!(map.get_action_bindings("save")[0].modifiers, Modifiers::new().with_control(true));
+        assert_eq!(map.get_axis_bindings("paddle"), [AxisBinding::gamepad(GamepadAxis::LeftStickY)]);
+    }
+
+    #[test]
+    fn bad_toml_is_an_error() {
+        assert!(matches!(ActionMap::from_toml("[[actions.jump]]\ninputs = [{ type = \"Nose\" }]"), Err(GompError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// The state of a set of buttons (keys, mouse buttons, ...).
+///
+/// A button is "just pressed" or "just released" for the frame it changed in, and "pressed" for as
+/// long as it is held.
+#[derive(Debug, Clone)]
+pub struct ButtonInput<T: Copy + Eq + Hash> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> ButtonInput<T> {
+    /// Creates a set of buttons, none of which are pressed.
+    pub fn new() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+
+    /// Presses a button. Key repeats don't count as being pressed again.
+    pub fn press(&mut self, button: T) {
+        if self.pressed.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    /// Releases a button.
+    pub fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    /// Releases every button.
+    pub fn release_all(&mut self) {
+        self.just_released.extend(self.pressed.drain());
+    }
+
+    /// Is the button held down.
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Was the button pressed this frame.
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    /// Was the button released this frame.
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    /// Gets every button that is held down.
+    pub fn get_pressed(&self) -> impl Iterator<Item = &T> {
+        self.pressed.iter()
+    }
+
+    /// Gets every button that was pressed this frame.
+    pub fn get_just_pressed(&self) -> impl Iterator<Item = &T> {
+        self.just_pressed.iter()
+    }
+
+    /// Gets every button that was released this frame.
+    pub fn get_just_released(&self) -> impl Iterator<Item = &T> {
+        self.just_released.iter()
+    }
+
+    /// Forgets what was pressed and released this frame, ready for the next.
+    pub fn clear_just(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+impl<T: Copy + Eq + Hash> Default for ButtonInput<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_are_just_pressed_for_one_frame() {
+        let mut buttons = ButtonInput::new();
+        buttons.press('a');
+
+        assert!(buttons.pressed('a'));
+        assert!(buttons.just_pressed('a'));
+
+        buttons.clear_just();
+
+        assert!(buttons.pressed('a'));
+        assert!(!buttons.just_pressed('a'));
+    }
+
+    #[test]
+    fn repeats_are_not_pressed_again() {
+        let mut buttons = ButtonInput::new();
+        buttons.press('a');
+        buttons.clear_just();
+        buttons.press('a');
+
+        assert!(buttons.pressed('a'));
+        assert!(!buttons.just_pressed('a'));
+    }
+
+    #[test]
+    fn releases_are_just_released_for_one_frame() {
+        let mut buttons = ButtonInput::new();
+        buttons.press('a');
+        buttons.clear_just();
+        buttons.release('a');
+
+        assert!(!buttons.pressed('a'));
+        assert!(buttons.just_released('a'));
+
+        buttons.clear_just();
+        assert!(!buttons.just_released('a'));
+    }
+
+    #[test]
+    fn releasing_an_unpressed_button_does_nothing() {
+        let mut buttons = ButtonInput::<char>::new();
+        buttons.release('a');
+
+        assert!(!buttons.just_released('a'));
+    }
+
+    #[test]
+    fn pressed_and_released_in_one_frame() {
+        let mut buttons = ButtonInput::new();
+        buttons.press('a');
+        buttons.release('a');
+
+        assert!(buttons.just_pressed('a'));
+        assert!(buttons.just_released('a'));
+        assert!(!buttons.pressed('a'));
+    }
+
+    #[test]
+    fn releasing_everything() {
+        let mut buttons = ButtonInput::new();
+        buttons.press('a');
+        buttons.press('b');
+        buttons.release_all();
+
+        assert_eq!(buttons.get_pressed().count(), 0);
+
+        let mut released: Vec<char> = buttons.get_just_released().copied().collect();
+        released.sort_unstable();
+
+        assert_eq!(released, ['a', 'b']);
+    }
+}
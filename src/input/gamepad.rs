@@ -0,0 +1,522 @@
+//! Gamepads.
+//!
+//! Gamepad events (connections, buttons and axes) come from a `GamepadBackend`. The `Gamepads`
+//! resource polls it every frame, applies dead zones, and the `GamepadPlugin` then feeds the
+//! events into `Input` and an `Events<GamepadEvent>` resource. There's no OS backend built in yet,
+//! so by default gamepads come from a `VirtualGamepadBackend`, which is driven from code.
+//!
+//! Gamepads are numbered (`GamepadId`) in the order they were connected. Buttons and axes use the
+//! usual "standard layout" names, so `South` is A on an Xbox pad and cross on a PlayStation one.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let gamepads = resources.get_mut::<Gamepads>().unwrap();
+//! gamepads.set_dead_zone(GamepadAxis::LeftStickY, 0.2);
+//!
+//! for event in resources.get::<Events<GamepadEvent>>().unwrap().iter() {
+//!     if let GamepadEvent::Connected(gamepad, info) = event {
+//!         players.join(*gamepad, &info.name);
+//!     }
+//! }
+//!
+//! // When the ball hits a paddle
+//! gamepads.rumble(RumbleRequest::new(player.gamepad, 0.5, Duration::from_millis(100)));
+//! ```
+
+use serde::{Serialize, Deserialize};
+use log::{info, warn};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Identifies a connected gamepad.
+pub type GamepadId = usize;
+
+/// A gamepad button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// A gamepad axis. Sticks go from -1 to 1 (up and right are positive), triggers from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// The default dead zone for every axis.
+pub const DEFAULT_DEAD_ZONE: f32 = 0.1;
+
+/// Information about a connected gamepad.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamepadInfo {
+    /// What the gamepad calls itself.
+    pub name: String,
+}
+
+/// Something that happened to a gamepad.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GamepadEvent {
+    Connected(GamepadId, GamepadInfo),
+    Disconnected(GamepadId),
+    ButtonPressed(GamepadId, GamepadButton),
+    ButtonReleased(GamepadId, GamepadButton),
+    AxisChanged(GamepadId, GamepadAxis, f32),
+}
+
+impl GamepadEvent {
+    /// Gets which gamepad the event is about.
+    pub fn get_gamepad(&self) -> GamepadId {
+        match *self {
+            Self::Connected(gamepad, _)
+            | Self::Disconnected(gamepad)
+            | Self::ButtonPressed(gamepad, _)
+            | Self::ButtonReleased(gamepad, _)
+            | Self::AxisChanged(gamepad, _, _) => gamepad,
+        }
+    }
+}
+
+/// A request to make a gamepad rumble.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RumbleRequest {
+    pub gamepad: GamepadId,
+
+    /// How hard the strong (low frequency) motor shakes, from 0 to 1.
+    pub strong: f32,
+
+    /// How hard the weak (high frequency) motor shakes, from 0 to 1.
+    pub weak: f32,
+
+    /// How long to rumble for. A new request replaces the old one, so a zero length one stops it.
+    pub duration: Duration,
+}
+
+impl RumbleRequest {
+    /// Rumbles both motors equally.
+    pub fn new(gamepad: GamepadId, intensity: f32, duration: Duration) -> Self {
+        Self {
+            gamepad,
+            strong: intensity,
+            weak: intensity,
+            duration,
+        }
+    }
+
+    /// Stops a gamepad rumbling.
+    pub fn stop(gamepad: GamepadId) -> Self {
+        Self::new(gamepad, 0.0, Duration::ZERO)
+    }
+}
+
+/// Where gamepad events come from. Implement this to plug in an OS (or any other) gamepad library.
+pub trait GamepadBackend {
+    /// Adds every event since the last poll to `events`, oldest first. Axis values are raw; dead
+    /// zones are applied afterwards.
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>);
+
+    /// Makes a gamepad rumble. Backends for gamepads that can't rumble can ignore this.
+    fn rumble(&mut self, request: &RumbleRequest);
+}
+
+/// Turns an axis value within the dead zone into 0, and rescales the rest so it still reaches 1.
+pub fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value.abs() < dead_zone {
+        0.0
+    } else {
+        value.signum() * ((value.abs() - dead_zone) / (1.0 - dead_zone).max(f32::EPSILON)).min(1.0)
+    }
+}
+
+/// The gamepads, as the `Gamepads` resource. Polls the backend every frame, keeps track of which
+/// gamepads are connected and applies dead zones.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Gamepads {
+    #[derivative(Debug = "ignore")]
+    backend: Box<dyn GamepadBackend>,
+
+    connected: BTreeMap<GamepadId, GamepadInfo>,
+
+    default_dead_zone: f32,
+    dead_zones: HashMap<GamepadAxis, f32>,
+}
+
+impl Gamepads {
+    /// Creates the gamepads, with events coming from a backend.
+    pub fn new<B: GamepadBackend + 'static>(backend: B) -> Self {
+        Self {
+            backend: Box::new(backend),
+            connected: BTreeMap::new(),
+            default_dead_zone: DEFAULT_DEAD_ZONE,
+            dead_zones: HashMap::new(),
+        }
+    }
+
+    /// Swaps the backend for another. Every gamepad from the old one is forgotten, so games will
+    /// want to do this before the first frame.
+    pub fn set_backend<B: GamepadBackend + 'static>(&mut self, backend: B) {
+        self.backend = Box::new(backend);
+        self.connected.clear();
+    }
+
+    /// Gets every connected gamepad, in the order they were connected.
+    pub fn get_connected(&self) -> impl Iterator<Item = (GamepadId, &GamepadInfo)> {
+        self.connected.iter().map(|(gamepad, info)| (*gamepad, info))
+    }
+
+    /// Gets information about a gamepad, if it is connected.
+    pub fn get_info(&self, gamepad: GamepadId) -> Option<&GamepadInfo> {
+        self.connected.get(&gamepad)
+    }
+
+    /// Is the gamepad connected.
+    pub fn is_connected(&self, gamepad: GamepadId) -> bool {
+        self.connected.contains_key(&gamepad)
+    }
+
+    /// Sets the dead zone used by axes without one of their own.
+    pub fn set_default_dead_zone(&mut self, dead_zone: f32) {
+        self.default_dead_zone = dead_zone.clamp(0.0, 1.0);
+    }
+
+    /// Sets the dead zone of an axis (on every gamepad).
+    pub fn set_dead_zone(&mut self, axis: GamepadAxis, dead_zone: f32) {
+        self.dead_zones.insert(axis, dead_zone.clamp(0.0, 1.0));
+    }
+
+    /// Gets the dead zone of an axis.
+    pub fn get_dead_zone(&self, axis: GamepadAxis) -> f32 {
+        self.dead_zones.get(&axis).copied().unwrap_or(self.default_dead_zone)
+    }
+
+    /// Makes a gamepad rumble. Requests for gamepads that aren't connected are dropped.
+    pub fn rumble(&mut self, request: RumbleRequest) {
+        if !self.is_connected(request.gamepad) {
+            warn!("Tried to rumble gamepad {}, which isn't connected", request.gamepad);
+
+            return;
+        }
+
+        self.backend.rumble(&request);
+    }
+
+    /// Polls the backend, returning every event since the last update with dead zones applied.
+    /// Called once a frame.
+    pub fn update(&mut self) -> Vec<GamepadEvent> {
+        let mut events = vec![];
+        self.backend.poll(&mut events);
+
+        for event in &mut events {
+            if let GamepadEvent::AxisChanged(_, axis, value) = event {
+                *value = apply_dead_zone(*value, self.get_dead_zone(*axis));
+            }
+        }
+
+        self.track_connections(&events);
+
+        events
+    }
+
+    /// Takes events from somewhere other than the backend (like an input replay), which already
+    /// have dead zones applied. The backend is still polled, but its events are thrown away.
+    pub fn update_from(&mut self, events: Vec<GamepadEvent>) -> Vec<GamepadEvent> {
+        self.backend.poll(&mut vec![]);
+        self.track_connections(&events);
+
+        events
+    }
+
+    /// Keeps track of which gamepads are connected.
+    fn track_connections(&mut self, events: &[GamepadEvent]) {
+        for event in events {
+            match event {
+                GamepadEvent::Connected(gamepad, info) => {
+                    info!("Gamepad {} ({}) connected", gamepad, info.name);
+                    self.connected.insert(*gamepad, info.clone());
+                },
+                GamepadEvent::Disconnected(gamepad) => {
+                    info!("Gamepad {} disconnected", gamepad);
+                    self.connected.remove(gamepad);
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new(VirtualGamepadBackend::new())
+    }
+}
+
+/// The state shared between clones of a `VirtualGamepadBackend`.
+#[derive(Debug, Default)]
+struct VirtualGamepads {
+    next_id: GamepadId,
+    connected: HashMap<GamepadId, GamepadInfo>,
+    events: Vec<GamepadEvent>,
+    rumbles: HashMap<GamepadId, RumbleRequest>,
+}
+
+/// An in-memory gamepad backend, driven from code. Useful for tests, and for feeding in input from
+/// anywhere else (like the network).
+///
+/// Clones share the same gamepads, so keep a clone around to drive the one given to `Gamepads`:
+///
+/// ```ignore
+/// let pads = VirtualGamepadBackend::new();
+/// app.insert_resource(Gamepads::new(pads.clone()));
+///
+/// let player = pads.connect("Player 1");
+/// pads.press(player, GamepadButton::South);
+/// pads.set_axis(player, GamepadAxis::LeftStickY, 1.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VirtualGamepadBackend {
+    state: Rc<RefCell<VirtualGamepads>>,
+}
+
+impl VirtualGamepadBackend {
+    /// Creates a backend with no gamepads.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plugs in a gamepad, returning its id.
+    pub fn connect(&self, name: &str) -> GamepadId {
+        let mut state = self.state.borrow_mut();
+        let gamepad = state.next_id;
+        let info = GamepadInfo { name: name.to_owned() };
+
+        state.next_id += 1;
+        state.connected.insert(gamepad, info.clone());
+        state.events.push(GamepadEvent::Connected(gamepad, info));
+
+        gamepad
+    }
+
+    /// Unplugs a gamepad.
+    pub fn disconnect(&self, gamepad: GamepadId) {
+        let mut state = self.state.borrow_mut();
+
+        if state.connected.remove(&gamepad).is_some() {
+            state.rumbles.remove(&gamepad);
+            state.events.push(GamepadEvent::Disconnected(gamepad));
+        }
+    }
+
+    /// Presses a button on a gamepad.
+    pub fn press(&self, gamepad: GamepadId, button: GamepadButton) {
+        self.push(GamepadEvent::ButtonPressed(gamepad, button));
+    }
+
+    /// Releases a button on a gamepad.
+    pub fn release(&self, gamepad: GamepadId, button: GamepadButton) {
+        self.push(GamepadEvent::ButtonReleased(gamepad, button));
+    }
+
+    /// Moves an axis on a gamepad.
+    pub fn set_axis(&self, gamepad: GamepadId, axis: GamepadAxis, value: f32) {
+        self.push(GamepadEvent::AxisChanged(gamepad, axis, value.clamp(-1.0, 1.0)));
+    }
+
+    /// Gets the last rumble requested for a gamepad, if there was one.
+    pub fn get_rumble(&self, gamepad: GamepadId) -> Option<RumbleRequest> {
+        self.state.borrow().rumbles.get(&gamepad).copied()
+    }
+
+    /// Queues an event, if the gamepad is connected.
+    fn push(&self, event: GamepadEvent) {
+        let mut state = self.state.borrow_mut();
+
+        if state.connected.contains_key(&event.get_gamepad()) {
+            state.events.push(event);
+        }
+    }
+}
+
+impl GamepadBackend for VirtualGamepadBackend {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+        events.append(&mut self.state.borrow_mut().events);
+    }
+
+    fn rumble(&mut self, request: &RumbleRequest) {
+        self.state.borrow_mut().rumbles.insert(request.gamepad, *request);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Input;
+
+    /// Runs a frame the way `GamepadPlugin` does, handing back the events.
+    fn frame(gamepads: &mut Gamepads, input: &mut Input) -> Vec<GamepadEvent> {
+        input.end_frame();
+
+        let events = gamepads.update();
+
+        for event in &events {
+            input.process_gamepad_event(event);
+        }
+
+        events
+    }
+
+    fn setup() -> (VirtualGamepadBackend, Gamepads, Input) {
+        let pads = VirtualGamepadBackend::new();
+
+        (pads.clone(), Gamepads::new(pads), Input::new())
+    }
+
+    #[test]
+    fn dead_zones_zero_small_values_and_rescale_the_rest() {
+        assert_eq!(apply_dead_zone(0.05, 0.1), 0.0);
+        assert_eq!(apply_dead_zone(-0.05, 0.1), 0.0);
+        assert_eq!(apply_dead_zone(1.0, 0.1), 1.0);
+        assert_eq!(apply_dead_zone(-1.0, 0.1), -1.0);
+        assert!((apply_dead_zone(0.55, 0.1) - 0.5).abs() < 1e-6);
+        // A dead zone of 1 turns the axis off altogether
+        assert_eq!(apply_dead_zone(1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn axes_reach_input_with_dead_zones_applied() {
+        let (pads, mut gamepads, mut input) = setup();
+        gamepads.set_dead_zone(GamepadAxis::LeftStickX, 0.5);
+        gamepads.set_default_dead_zone(2.0);
+
+        let player = pads.connect("Player 1");
+        pads.set_axis(player, GamepadAxis::LeftStickX, 0.4);
+        pads.set_axis(player, GamepadAxis::LeftStickY, 5.0);
+        pads.set_axis(player, GamepadAxis::RightStickX, 0.9);
+        frame(&mut gamepads, &mut input);
+
+        assert_eq!(gamepads.get_dead_zone(GamepadAxis::LeftStickX), 0.5);
+        assert_eq!(gamepads.get_dead_zone(GamepadAxis::RightStickX), 1.0);
+
+        assert_eq!(input.get_gamepad_axis(player, GamepadAxis::LeftStickX), 0.0);
+        assert_eq!(input.get_gamepad_axis(player, GamepadAxis::LeftStickY), 0.0);
+        assert_eq!(input.get_gamepad_axis(player, GamepadAxis::RightStickX), 0.0);
+
+        pads.set_axis(player, GamepadAxis::LeftStickX, -0.75);
+        frame(&mut gamepads, &mut input);
+
+        assert_eq!(input.get_gamepad_axis(player, GamepadAxis::LeftStickX), -0.5);
+    }
+
+    #[test]
+    fn gamepads_are_tracked_as_they_come_and_go() {
+        let (pads, mut gamepads, mut input) = setup();
+
+        let first = pads.connect("First");
+        let second = pads.connect("Second");
+        let events = frame(&mut gamepads, &mut input);
+
+        assert_eq!(events, vec![
+            GamepadEvent::Connected(first, GamepadInfo { name: "First".to_owned() }),
+            GamepadEvent::Connected(second, GamepadInfo { name: "Second".to_owned() }),
+        ]);
+        assert_eq!(gamepads.get_connected().map(|(gamepad, _)| gamepad).collect::<Vec<_>>(), vec![first, second]);
+        assert_eq!(gamepads.get_info(second).unwrap().name, "Second");
+
+        pads.press(first, GamepadButton::South);
+        pads.set_axis(first, GamepadAxis::LeftTrigger, 1.0);
+        pads.press(second, GamepadButton::South);
+        frame(&mut gamepads, &mut input);
+
+        pads.disconnect(first);
+        frame(&mut gamepads, &mut input);
+
+        assert!(!gamepads.is_connected(first));
+        assert!(gamepads.is_connected(second));
+
+        // Everything the unplugged gamepad held is let go of
+        assert!(!input.gamepad_button_pressed(first, GamepadButton::South));
+        assert!(input.gamepad_button_just_released(first, GamepadButton::South));
+        assert_eq!(input.get_gamepad_axis(first, GamepadAxis::LeftTrigger), 0.0);
+        assert!(input.gamepad_button_pressed(second, GamepadButton::South));
+
+        // Unplugged gamepads don't send anything
+        pads.press(first, GamepadButton::East);
+        assert!(frame(&mut gamepads, &mut input).is_empty());
+    }
+
+    #[test]
+    fn buttons_are_just_pressed_and_released_for_a_frame() {
+        let (pads, mut gamepads, mut input) = setup();
+        let player = pads.connect("Player 1");
+
+        pads.press(player, GamepadButton::Start);
+        frame(&mut gamepads, &mut input);
+
+        assert!(input.gamepad_button_pressed(player, GamepadButton::Start));
+        assert!(input.gamepad_button_just_pressed(player, GamepadButton::Start));
+
+        frame(&mut gamepads, &mut input);
+
+        assert!(input.gamepad_button_pressed(player, GamepadButton::Start));
+        assert!(!input.gamepad_button_just_pressed(player, GamepadButton::Start));
+
+        pads.release(player, GamepadButton::Start);
+        frame(&mut gamepads, &mut input);
+
+        assert!(!input.gamepad_button_pressed(player, GamepadButton::Start));
+        assert!(input.gamepad_button_just_released(player, GamepadButton::Start));
+
+        frame(&mut gamepads, &mut input);
+
+        assert!(!input.gamepad_button_just_released(player, GamepadButton::Start));
+    }
+
+    #[test]
+    fn rumble_requests_reach_connected_gamepads() {
+        let (pads, mut gamepads, mut input) = setup();
+        let player = pads.connect("Player 1");
+
+        // Not connected until the backend has been polled
+        gamepads.rumble(RumbleRequest::new(player, 0.5, Duration::from_millis(100)));
+        assert_eq!(pads.get_rumble(player), None);
+
+        frame(&mut gamepads, &mut input);
+
+        let request = RumbleRequest::new(player, 0.5, Duration::from_millis(100));
+        gamepads.rumble(request);
+        assert_eq!(pads.get_rumble(player), Some(request));
+
+        gamepads.rumble(RumbleRequest::stop(player));
+        assert_eq!(pads.get_rumble(player), Some(RumbleRequest::stop(player)));
+
+        pads.disconnect(player);
+        frame(&mut gamepads, &mut input);
+
+        gamepads.rumble(request);
+        assert_eq!(pads.get_rumble(player), None);
+    }
+}
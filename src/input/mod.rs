@@ -0,0 +1,367 @@
+//! Input handling.
+//!
+//! The `InputPlugin` adds an `Input` resource, kept up to date from window events. It knows which
+//! keys (by virtual key code or scancode) and mouse buttons are held down, and which were pressed
+//! or released this frame. It also tracks the cursor, the scroll wheel, typed text and gamepads.
+//!
+//! Rather than checking keys directly, games should usually go through actions (see the `action`
+//! module), so players can rebind them. Input can also be recorded and played back (see the
+//! `replay` module).
+//!
+//! "This frame" means everything since the end of the last frame, so a key that is tapped between
+//! two frames is still just pressed (and just released) in the next one.
+//!
+//! # World space
+//!
+//! There's no camera yet, so world space is clip space: (-1, -1) is the bottom left of the window,
+//! and (1, 1) is the top right. This is the same space transforms are in.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let input = resources.get::<Input>().unwrap();
+//!
+//! if input.key_just_pressed(VirtualKeyCode::Space) {
+//!     serve();
+//! }
+//!
+//! // Name entry
+//! name.push_str(input.get_text());
+//! if input.key_just_pressed(VirtualKeyCode::Back) {
+//!     name.pop();
+//! }
+//! ```
+
+pub mod button;
+pub mod gamepad;
+pub mod action;
+pub mod replay;
+pub mod plugin;
+
+use button::ButtonInput;
+use gamepad::{GamepadId, GamepadButton, GamepadAxis, GamepadEvent};
+
+use std::collections::HashMap;
+
+use winit::dpi::PhysicalSize;
+use winit::event::{
+    ElementState,
+    KeyboardInput,
+    ModifiersState,
+    MouseButton,
+    MouseScrollDelta,
+    ScanCode,
+    VirtualKeyCode,
+    WindowEvent,
+};
+
+/// How many pixels of scrolling (from a touchpad, say) count as one line.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// The state of the keyboard, mouse and gamepads.
+#[derive(Debug, Clone)]
+pub struct Input {
+    keys: ButtonInput<VirtualKeyCode>,
+    scancodes: ButtonInput<ScanCode>,
+    mouse_buttons: ButtonInput<MouseButton>,
+    modifiers: ModifiersState,
+
+    /// Where the cursor is in the window, in pixels from the top left. `None` if it's outside.
+    cursor_position: Option<[f64; 2]>,
+
+    /// How far the cursor moved this frame, in pixels.
+    cursor_delta: [f64; 2],
+
+    /// How far the scroll wheel moved this frame, in lines.
+    scroll_delta: [f32; 2],
+
+    /// Characters typed this frame.
+    text: String,
+
+    /// Needed to turn window positions into world positions.
+    window_size: PhysicalSize<u32>,
+
+    gamepad_buttons: ButtonInput<(GamepadId, GamepadButton)>,
+    gamepad_axes: HashMap<(GamepadId, GamepadAxis), f32>,
+}
+
+impl Input {
+    /// Creates a new input state, with nothing pressed.
+    pub fn new() -> Self {
+        Self {
+            keys: ButtonInput::new(),
+            scancodes: ButtonInput::new(),
+            mouse_buttons: ButtonInput::new(),
+            modifiers: ModifiersState::empty(),
+            cursor_position: None,
+            cursor_delta: [0.0, 0.0],
+            scroll_delta: [0.0, 0.0],
+            text: String::new(),
+            window_size: PhysicalSize::new(1, 1),
+            gamepad_buttons: ButtonInput::new(),
+            gamepad_axes: HashMap::new(),
+        }
+    }
+
+    /// Updates the state from a window event.
+    pub fn process_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state, scancode, virtual_keycode, .. },
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    self.scancodes.press(*scancode);
+
+                    if let Some(key) = virtual_keycode {
+                        self.keys.press(*key);
+                    }
+                },
+                ElementState::Released => {
+                    self.scancodes.release(*scancode);
+
+                    if let Some(key) = virtual_keycode {
+                        self.keys.release(*key);
+                    }
+                },
+            },
+
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => self.mouse_buttons.press(*button),
+                ElementState::Released => self.mouse_buttons.release(*button),
+            },
+
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some([x, y]) = self.cursor_position {
+                    self.cursor_delta[0] += position.x - x;
+                    self.cursor_delta[1] += position.y - y;
+                }
+
+                self.cursor_position = Some([position.x, position.y]);
+            },
+
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+
+            WindowEvent::MouseWheel { delta, .. } => {
+                let [x, y] = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => [*x, *y],
+                    MouseScrollDelta::PixelDelta(position) => [position.x as f32 / PIXELS_PER_LINE, position.y as f32 / PIXELS_PER_LINE],
+                };
+
+                self.scroll_delta[0] += x;
+                self.scroll_delta[1] += y;
+            },
+
+            // Backspace, enter and the like come through as key presses instead
+            WindowEvent::ReceivedCharacter(c) if !c.is_control() => self.text.push(*c),
+
+            // We won't hear about anything being released while unfocused, so let it all go now
+            WindowEvent::Focused(false) => {
+                self.keys.release_all();
+                self.scancodes.release_all();
+                self.mouse_buttons.release_all();
+            },
+
+            WindowEvent::Resized(size) => self.set_window_size(*size),
+
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => self.set_window_size(**new_inner_size),
+
+            _ => {},
+        }
+    }
+
+    /// Updates the state from a gamepad event.
+    pub fn process_gamepad_event(&mut self, event: &GamepadEvent) {
+        match *event {
+            GamepadEvent::ButtonPressed(gamepad, button) => self.press_gamepad_button(gamepad, button),
+            GamepadEvent::ButtonReleased(gamepad, button) => self.release_gamepad_button(gamepad, button),
+            GamepadEvent::AxisChanged(gamepad, axis, value) => self.set_gamepad_axis(gamepad, axis, value),
+
+            // Let go of everything the gamepad was holding
+            GamepadEvent::Disconnected(gamepad) => {
+                let held: Vec<_> = self.gamepad_buttons.get_pressed()
+                    .filter(|(g, _)| *g == gamepad)
+                    .copied()
+                    .collect();
+
+                for button in held {
+                    self.gamepad_buttons.release(button);
+                }
+
+                self.gamepad_axes.retain(|(g, _), _| *g != gamepad);
+            },
+
+            GamepadEvent::Connected(..) => {},
+        }
+    }
+
+    /// Forgets everything that only lasts a frame. Called at the end of every frame.
+    pub fn end_frame(&mut self) {
+        self.keys.clear_just();
+        self.scancodes.clear_just();
+        self.mouse_buttons.clear_just();
+        self.gamepad_buttons.clear_just();
+        self.cursor_delta = [0.0, 0.0];
+        self.scroll_delta = [0.0, 0.0];
+        self.text.clear();
+    }
+
+    /// Sets the size of the window, used to work out world positions.
+    pub fn set_window_size(&mut self, size: PhysicalSize<u32>) {
+        if size.width > 0 && size.height > 0 {
+            self.window_size = size;
+        }
+    }
+
+    /// Is the key held down.
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys.pressed(key)
+    }
+
+    /// Was the key pressed this frame.
+    pub fn key_just_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys.just_pressed(key)
+    }
+
+    /// Was the key released this frame.
+    pub fn key_just_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys.just_released(key)
+    }
+
+    /// Is the key with this scancode held down. Scancodes are the physical key, so they don't
+    /// change with the keyboard layout.
+    pub fn scancode_pressed(&self, scancode: ScanCode) -> bool {
+        self.scancodes.pressed(scancode)
+    }
+
+    /// Was the key with this scancode pressed this frame.
+    pub fn scancode_just_pressed(&self, scancode: ScanCode) -> bool {
+        self.scancodes.just_pressed(scancode)
+    }
+
+    /// Was the key with this scancode released this frame.
+    pub fn scancode_just_released(&self, scancode: ScanCode) -> bool {
+        self.scancodes.just_released(scancode)
+    }
+
+    /// Is the mouse button held down.
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed(button)
+    }
+
+    /// Was the mouse button pressed this frame.
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_pressed(button)
+    }
+
+    /// Was the mouse button released this frame.
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_released(button)
+    }
+
+    /// Gets the keys, by virtual key code.
+    pub fn get_keys(&self) -> &ButtonInput<VirtualKeyCode> {
+        &self.keys
+    }
+
+    /// Gets the keys, by scancode.
+    pub fn get_scancodes(&self) -> &ButtonInput<ScanCode> {
+        &self.scancodes
+    }
+
+    /// Gets the mouse buttons.
+    pub fn get_mouse_buttons(&self) -> &ButtonInput<MouseButton> {
+        &self.mouse_buttons
+    }
+
+    /// Gets the modifier keys (shift, control, ...) that are held down.
+    pub fn get_modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Gets where the cursor is in the window, in pixels from the top left. `None` if the cursor
+    /// isn't over the window.
+    pub fn get_cursor_position(&self) -> Option<[f64; 2]> {
+        self.cursor_position
+    }
+
+    /// Gets where the cursor is in world space. `None` if the cursor isn't over the window.
+    pub fn get_cursor_world_position(&self) -> Option<[f32; 2]> {
+        self.cursor_position.map(|[x, y]| [
+            (x / self.window_size.width as f64 * 2.0 - 1.0) as f32,
+            (1.0 - y / self.window_size.height as f64 * 2.0) as f32,
+        ])
+    }
+
+    /// Gets how far the cursor moved this frame, in pixels.
+    pub fn get_cursor_delta(&self) -> [f64; 2] {
+        self.cursor_delta
+    }
+
+    /// Gets how far the scroll wheel moved this frame, in lines. Positive y is scrolling up.
+    pub fn get_scroll_delta(&self) -> [f32; 2] {
+        self.scroll_delta
+    }
+
+    /// Presses a button on a gamepad.
+    pub fn press_gamepad_button(&mut self, gamepad: GamepadId, button: GamepadButton) {
+        self.gamepad_buttons.press((gamepad, button));
+    }
+
+    /// Releases a button on a gamepad.
+    pub fn release_gamepad_button(&mut self, gamepad: GamepadId, button: GamepadButton) {
+        self.gamepad_buttons.release((gamepad, button));
+    }
+
+    /// Sets the value of an axis on a gamepad.
+    pub fn set_gamepad_axis(&mut self, gamepad: GamepadId, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes.insert((gamepad, axis), value);
+    }
+
+    /// Is the button held down on the gamepad.
+    pub fn gamepad_button_pressed(&self, gamepad: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad_buttons.pressed((gamepad, button))
+    }
+
+    /// Was the button pressed on the gamepad this frame.
+    pub fn gamepad_button_just_pressed(&self, gamepad: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad_buttons.just_pressed((gamepad, button))
+    }
+
+    /// Was the button released on the gamepad this frame.
+    pub fn gamepad_button_just_released(&self, gamepad: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad_buttons.just_released((gamepad, button))
+    }
+
+    /// Gets the value of an axis on a gamepad (0 if we've never heard from it).
+    pub fn get_gamepad_axis(&self, gamepad: GamepadId, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes.get(&(gamepad, axis)).copied().unwrap_or(0.0)
+    }
+
+    /// Gets the buttons of every gamepad.
+    pub fn get_gamepad_buttons(&self) -> &ButtonInput<(GamepadId, GamepadButton)> {
+        &self.gamepad_buttons
+    }
+
+    /// Gets the value of an axis on whichever gamepad is pushing it furthest.
+    pub fn get_any_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes.iter()
+            .filter(|((_, a), _)| *a == axis)
+            .map(|(_, value)| *value)
+            .fold(0.0, |furthest: f32, value| if value.abs() > furthest.abs() { value } else { furthest })
+    }
+
+    /// Gets the characters typed this frame, in order. Control characters (backspace, enter, ...)
+    /// are left out, check for their keys instead.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,212 @@
+//! The input plugins.
+
+use super::Input;
+use super::action::{Actions, ActionMap, Binding, InputSource};
+use super::gamepad::{GamepadEvent, Gamepads};
+use super::replay::{InputRecorder, InputRecording, InputReplay};
+use crate::application::{AppExit, Seed};
+use crate::application::builder::{AppBuilder, Stage};
+use crate::application::events::Events;
+use crate::application::plugin::Plugin;
+
+use winit::event::{VirtualKeyCode, WindowEvent};
+use log::warn;
+
+/// Adds the `Input` resource and keeps it up to date. Also forwards the input related window events
+/// (keyboard, mouse, focus) into an `Events<WindowEvent<'static>>` resource, for anything that
+/// wants the raw events.
+#[derive(Debug, Default)]
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Input::new())
+            .add_event::<WindowEvent<'static>>()
+            .add_setup(|resources, window| {
+                if let (Some(input), Some(window)) = (resources.get_mut::<Input>(), window) {
+                    input.set_window_size(window.inner_size());
+                }
+
+                Ok(())
+            })
+            .add_window_event_handler(|resources, event| {
+                if let Some(input) = resources.get_mut::<Input>() {
+                    input.process_event(event);
+                }
+
+                if let (Some(events), Some(event)) = (resources.get_mut::<Events<WindowEvent<'static>>>(), to_input_event(event)) {
+                    events.send(event);
+                }
+
+                false
+            })
+            .add_system(Stage::Last, |resources, _| {
+                if let Some(input) = resources.get_mut::<Input>() {
+                    input.end_frame();
+                }
+            });
+    }
+}
+
+/// Adds the `Gamepads` resource and an `Events<GamepadEvent>` resource, and feeds gamepad events
+/// into `Input` at the start of every frame. Needs the `InputPlugin`.
+///
+/// Unless a `Gamepads` resource was inserted before this plugin was added, gamepads come from a
+/// `VirtualGamepadBackend`. Use `Gamepads::set_backend` to plug in another.
+#[derive(Debug, Default)]
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        if !app.get_resources().contains::<Gamepads>() {
+            app.insert_resource(Gamepads::default());
+        }
+
+        app.add_event::<GamepadEvent>()
+            .add_system(Stage::First, |resources, _| {
+                let replayed = resources.get_mut::<InputReplay>()
+                    .filter(|replay| replay.is_playing())
+                    .map(InputReplay::take_gamepad_events);
+
+                let events = match (resources.get_mut::<Gamepads>(), replayed) {
+                    (Some(gamepads), Some(replayed)) => gamepads.update_from(replayed),
+                    (Some(gamepads), None) => gamepads.update(),
+                    (None, _) => return,
+                };
+
+                if let Some(recorder) = resources.get_mut::<InputRecorder>() {
+                    recorder.record_gamepad_events(&events);
+                }
+
+                if let Some(input) = resources.get_mut::<Input>() {
+                    for event in &events {
+                        input.process_gamepad_event(event);
+                    }
+                }
+
+                if let Some(queue) = resources.get_mut::<Events<GamepadEvent>>() {
+                    for event in events {
+                        queue.send(event);
+                    }
+                }
+            });
+    }
+}
+
+/// Adds the `Actions` resource, and works out the state of every action at the start of every
+/// frame. Needs the `InputPlugin`, and should be added after the `GamepadPlugin` so actions see
+/// this frame's gamepad input.
+///
+/// Unless the bindings were already set up (by inserting an `Actions` resource before this plugin
+/// was added), there is a single action, the exit action from the config, bound to escape.
+/// Bindings are then loaded from `AppConfig::input_bindings`, if it is set and the file exists.
+/// Whenever the exit action is pressed, the application is asked to exit.
+#[derive(Debug, Default)]
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let exit_action = app.get_config().exit_action.clone();
+        let bindings = app.get_config().input_bindings.clone();
+
+        if !app.get_resources().contains::<Actions>() {
+            let mut map = ActionMap::new();
+
+            if let Some(exit_action) = &exit_action {
+                map.bind_action(exit_action, Binding::new(InputSource::Key(VirtualKeyCode::Escape)));
+            }
+
+            app.insert_resource(Actions::new(map));
+        }
+
+        app.add_setup(move |resources, _| {
+            if let (Some(actions), Some(path)) = (resources.get_mut::<Actions>(), bindings) {
+                if path.exists() {
+                    if let Err(e) = actions.load_bindings(&path) {
+                        warn!("Failed to load input bindings from {}, using the defaults: {}", path.display(), e);
+                    }
+                }
+            }
+
+            Ok(())
+        })
+        .add_system(Stage::First, move |resources, _| {
+            // Take the actions out while they're updated, as they need to look at the input
+            let mut actions = match resources.remove::<Actions>() {
+                Some(actions) => actions,
+                None => return,
+            };
+
+            if let Some(input) = resources.get::<Input>() {
+                actions.update(input);
+            }
+
+            let exit = exit_action.as_ref().map(|action| actions.just_pressed(action)).unwrap_or(false);
+            resources.insert(actions);
+
+            if let (true, Some(events)) = (exit, resources.get_mut::<Events<AppExit>>()) {
+                events.send(AppExit(0));
+            }
+        });
+    }
+}
+
+/// Records input to `AppConfig::record_input` (saving it on shutdown), or plays it back from
+/// `AppConfig::replay_input`. Does nothing if neither is set. See the `replay` module.
+#[derive(Debug, Default)]
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let tick_rate = app.get_config().tick_rate;
+        let record = app.get_config().record_input.clone();
+        let replay = app.get_config().replay_input.clone();
+
+        app.add_setup(move |resources, _| {
+            if let Some(path) = replay {
+                let recording = InputRecording::load(&path)?;
+
+                if recording.tick_rate != tick_rate {
+                    warn!("{} was recorded at {} ticks per second, but we run at {}", path.display(), recording.tick_rate, tick_rate);
+                }
+
+                resources.insert(Seed(recording.seed));
+                resources.insert(InputReplay::new(recording));
+            } else if record.is_some() {
+                let seed = resources.get::<Seed>().map(|seed| seed.0).unwrap_or_default();
+
+                resources.insert(InputRecorder::new(seed, tick_rate));
+            }
+
+            Ok(())
+        });
+
+        if let Some(path) = app.get_config().record_input.clone() {
+            app.add_system(Stage::Shutdown, move |resources, _| {
+                if let Some(recorder) = resources.remove::<InputRecorder>() {
+                    if let Err(e) = recorder.finish().save(&path) {
+                        warn!("Failed to save the input recording to {}: {}", path.display(), e);
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Copies an input event so it can be kept around. Window events can't be cloned (some borrow the
+/// window), but all of the input ones are plain data.
+#[allow(deprecated)]
+fn to_input_event(event: &WindowEvent) -> Option<WindowEvent<'static>> {
+    Some(match *event {
+        WindowEvent::KeyboardInput { device_id, input, is_synthetic } => WindowEvent::KeyboardInput { device_id, input, is_synthetic },
+        WindowEvent::ReceivedCharacter(c) => WindowEvent::ReceivedCharacter(c),
+        WindowEvent::ModifiersChanged(modifiers) => WindowEvent::ModifiersChanged(modifiers),
+        WindowEvent::CursorMoved { device_id, position, modifiers } => WindowEvent::CursorMoved { device_id, position, modifiers },
+        WindowEvent::CursorEntered { device_id } => WindowEvent::CursorEntered { device_id },
+        WindowEvent::CursorLeft { device_id } => WindowEvent::CursorLeft { device_id },
+        WindowEvent::MouseWheel { device_id, delta, phase, modifiers } => WindowEvent::MouseWheel { device_id, delta, phase, modifiers },
+        WindowEvent::MouseInput { device_id, state, button, modifiers } => WindowEvent::MouseInput { device_id, state, button, modifiers },
+        WindowEvent::Focused(focused) => WindowEvent::Focused(focused),
+        _ => return None,
+    })
+}
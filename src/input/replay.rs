@@ -0,0 +1,441 @@
+//! Input recording and replay.
+//!
+//! An `InputRecorder` resource records every input event (from the window and from gamepads) as it
+//! comes in, along with how long each frame was and how many simulation steps it ran. Together
+//! with the `Seed` the application started with, that's enough to play a session back exactly, as
+//! long as the game only uses input, time and the seed to decide what happens.
+//!
+//! An `InputReplay` resource plays a recording back. While it plays, real input is ignored (apart
+//! from closing the window), and every frame runs with the recorded input, frame time and number
+//! of simulation steps. Once it runs out, real input takes over again.
+//!
+//! Window resizes aren't recorded, so world positions of the cursor can drift if the window is a
+//! different size when playing back.
+//!
+//! Recordings are saved as compact binary files. Usually the `ReplayPlugin` takes care of
+//! everything, from `AppConfig::record_input` and `AppConfig::replay_input`:
+//!
+//! ```ignore
+//! // Record a session for a bug report
+//! let config = AppConfig::new().with_input_recording("session.gompreplay");
+//!
+//! // ... and play it back
+//! let config = AppConfig::new().with_input_replay("session.gompreplay");
+//!
+//! // Seed the game's random number generator from the `Seed` resource, so replays match
+//! app.add_system(Stage::Startup, |resources, _| {
+//!     let seed = resources.get::<Seed>().unwrap().0;
+//!     resources.insert(Rng::seed_from_u64(seed));
+//! });
+//! ```
+
+use super::gamepad::GamepadEvent;
+use crate::error::Error as GompError;
+use crate::versioned;
+
+use winit::dpi::PhysicalPosition;
+use winit::event::{
+    DeviceId,
+    ElementState,
+    KeyboardInput,
+    ModifiersState,
+    MouseButton,
+    MouseScrollDelta,
+    ScanCode,
+    TouchPhase,
+    VirtualKeyCode,
+    WindowEvent,
+};
+use bincode::Options;
+use serde::{Serialize, Deserialize};
+use log::info;
+
+use std::path::Path;
+use std::time::Duration;
+
+/// The first bytes of every recording file.
+const MAGIC: &[u8; 8] = b"GOMPREC\0";
+
+/// Bumped whenever the file format changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// An input event from the window, as it's stored in a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedEvent {
+    Key {
+        scancode: ScanCode,
+        key: Option<VirtualKeyCode>,
+        pressed: bool,
+    },
+    Mouse {
+        button: MouseButton,
+        pressed: bool,
+    },
+    Modifiers(ModifiersState),
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorEntered,
+    CursorLeft,
+    Scroll {
+        x: f32,
+        y: f32,
+        /// Is the scroll in pixels, rather than lines.
+        pixels: bool,
+    },
+    Character(char),
+    Focused(bool),
+}
+
+impl RecordedEvent {
+    /// Gets the recordable part of a window event. Anything that isn't input gives `None`.
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match *event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { scancode, virtual_keycode, state, .. },
+                ..
+            } => Self::Key {
+                scancode,
+                key: virtual_keycode,
+                pressed: state == ElementState::Pressed,
+            },
+            WindowEvent::MouseInput { state, button, .. } => Self::Mouse {
+                button,
+                pressed: state == ElementState::Pressed,
+            },
+            WindowEvent::ModifiersChanged(modifiers) => Self::Modifiers(modifiers),
+            WindowEvent::CursorMoved { position, .. } => Self::CursorMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::CursorEntered { .. } => Self::CursorEntered,
+            WindowEvent::CursorLeft { .. } => Self::CursorLeft,
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => Self::Scroll { x, y, pixels: false },
+                MouseScrollDelta::PixelDelta(position) => Self::Scroll {
+                    x: position.x as f32,
+                    y: position.y as f32,
+                    pixels: true,
+                },
+            },
+            WindowEvent::ReceivedCharacter(c) => Self::Character(c),
+            WindowEvent::Focused(focused) => Self::Focused(focused),
+            _ => return None,
+        })
+    }
+
+    /// Turns the event back into a window event, as if it came from the window.
+    #[allow(deprecated)]
+    pub fn to_window_event(&self) -> WindowEvent<'static> {
+        // Nothing looks at which device input came from
+        let device_id = unsafe { DeviceId::dummy() };
+        let state = |pressed| if pressed { ElementState::Pressed } else { ElementState::Released };
+
+        match *self {
+            Self::Key { scancode, key, pressed } => WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode,
+                    state: state(pressed),
+                    virtual_keycode: key,
+                    modifiers: ModifiersState::empty(),
+                },
+                is_synthetic: false,
+            },
+            Self::Mouse { button, pressed } => WindowEvent::MouseInput {
+                device_id,
+                state: state(pressed),
+                button,
+                modifiers: ModifiersState::empty(),
+            },
+            Self::Modifiers(modifiers) => WindowEvent::ModifiersChanged(modifiers),
+            Self::CursorMoved { x, y } => WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(x, y),
+                modifiers: ModifiersState::empty(),
+            },
+            Self::CursorEntered => WindowEvent::CursorEntered { device_id },
+            Self::CursorLeft => WindowEvent::CursorLeft { device_id },
+            Self::Scroll { x, y, pixels } => WindowEvent::MouseWheel {
+                device_id,
+                delta: if pixels {
+                    MouseScrollDelta::PixelDelta(PhysicalPosition::new(x as f64, y as f64))
+                } else {
+                    MouseScrollDelta::LineDelta(x, y)
+                },
+                phase: TouchPhase::Moved,
+                modifiers: ModifiersState::empty(),
+            },
+            Self::Character(c) => WindowEvent::ReceivedCharacter(c),
+            Self::Focused(focused) => WindowEvent::Focused(focused),
+        }
+    }
+}
+
+/// Everything that went into a single frame.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// How long the frame was (before time scaling).
+    pub delta: Duration,
+
+    /// How many simulation steps the frame ran.
+    pub ticks: u32,
+
+    /// Input events from the window, oldest first.
+    pub events: Vec<RecordedEvent>,
+
+    /// Gamepad events, with dead zones already applied.
+    pub gamepad_events: Vec<GamepadEvent>,
+}
+
+/// A recorded session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    /// The `Seed` the session ran with.
+    pub seed: u64,
+
+    /// The tick rate the session ran at.
+    pub tick_rate: u32,
+
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    /// Creates an empty recording.
+    pub fn new(seed: u64, tick_rate: u32) -> Self {
+        Self {
+            seed,
+            tick_rate,
+            frames: vec![],
+        }
+    }
+
+    /// How many simulation steps the recording covers.
+    pub fn get_tick_count(&self) -> u64 {
+        self.frames.iter().map(|frame| frame.ticks as u64).sum()
+    }
+
+    /// Encodes the recording.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GompError> {
+        let mut bytes = versioned::write_header(MAGIC, FORMAT_VERSION);
+
+        bincode::DefaultOptions::new()
+            .serialize_into(&mut bytes, self)
+            .map_err(|e| GompError::InvalidRecording(e.to_string()))?;
+
+        Ok(bytes)
+    }
+
+    /// Decodes a recording.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GompError> {
+        let (_, body) = versioned::read_header(bytes, MAGIC, "recording", FORMAT_VERSION)
+            .map_err(GompError::InvalidRecording)?;
+
+        bincode::DefaultOptions::new()
+            .deserialize(body)
+            .map_err(|e| GompError::InvalidRecording(e.to_string()))
+    }
+
+    /// Loads a recording from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GompError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Saves the recording to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GompError> {
+        std::fs::write(path, self.to_bytes()?)?;
+
+        Ok(())
+    }
+}
+
+/// Records input while it's a resource. Remove it (or call `Application::stop_input_recording`) to
+/// get the recording.
+#[derive(Debug, Clone)]
+pub struct InputRecorder {
+    recording: InputRecording,
+
+    /// The frame being recorded.
+    current: RecordedFrame,
+}
+
+impl InputRecorder {
+    /// Starts a recording, for an application running with a seed and tick rate.
+    pub fn new(seed: u64, tick_rate: u32) -> Self {
+        info!("Recording input");
+
+        Self {
+            recording: InputRecording::new(seed, tick_rate),
+            current: RecordedFrame::default(),
+        }
+    }
+
+    /// Gets everything recorded so far (not counting the frame being recorded).
+    pub fn get_recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    /// Stops recording, and gets the recording.
+    pub fn finish(self) -> InputRecording {
+        info!("Recorded {} frames of input", self.recording.frames.len());
+
+        self.recording
+    }
+
+    /// Records a window event, if it's input.
+    pub(crate) fn record_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = RecordedEvent::from_window_event(event) {
+            self.current.events.push(event);
+        }
+    }
+
+    /// Records gamepad events.
+    pub(crate) fn record_gamepad_events(&mut self, events: &[GamepadEvent]) {
+        self.current.gamepad_events.extend_from_slice(events);
+    }
+
+    /// Finishes the frame being recorded, and starts the next.
+    pub(crate) fn end_frame(&mut self, delta: Duration, ticks: u32) {
+        let mut frame = std::mem::take(&mut self.current);
+        frame.delta = delta;
+        frame.ticks = ticks;
+
+        self.recording.frames.push(frame);
+    }
+}
+
+/// Plays back a recording while it's a resource. The application's `Seed` should match the
+/// recording's (`Application::replay_input` takes care of this), and playback should start from
+/// the first frame for it to come out the same.
+#[derive(Debug, Clone)]
+pub struct InputReplay {
+    recording: InputRecording,
+
+    /// The next frame to play.
+    next: usize,
+    playing: bool,
+
+    /// Gamepad events for the frame being played, until the gamepad plugin takes them.
+    gamepad_events: Vec<GamepadEvent>,
+}
+
+impl InputReplay {
+    /// Starts playing back a recording.
+    pub fn new(recording: InputRecording) -> Self {
+        info!("Replaying {} frames of input", recording.frames.len());
+
+        Self {
+            recording,
+            next: 0,
+            playing: true,
+            gamepad_events: vec![],
+        }
+    }
+
+    /// Gets the recording being played.
+    pub fn get_recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    /// Is input still coming from the recording.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// How many frames have been played.
+    pub fn get_frames_played(&self) -> usize {
+        self.next
+    }
+
+    /// Moves onto the next recorded frame. Returns `None` (and stops playing) once every frame has
+    /// been played.
+    pub(crate) fn next_frame(&mut self) -> Option<RecordedFrame> {
+        if !self.playing {
+            return None;
+        }
+
+        match self.recording.frames.get(self.next) {
+            Some(frame) => {
+                self.next += 1;
+                self.gamepad_events = frame.gamepad_events.clone();
+
+                Some(frame.clone())
+            },
+            None => {
+                info!("Finished replaying input, after {} frames", self.next);
+                self.playing = false;
+                self.gamepad_events.clear();
+
+                None
+            },
+        }
+    }
+
+    /// Takes the gamepad events for the frame being played.
+    pub(crate) fn take_gamepad_events(&mut self) -> Vec<GamepadEvent> {
+        std::mem::take(&mut self.gamepad_events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::gamepad::{GamepadAxis, GamepadButton, GamepadInfo};
+
+    fn recording() -> InputRecording {
+        let mut recording = InputRecording::new(1234, 60);
+
+        recording.frames.push(RecordedFrame {
+            delta: Duration::from_micros(16_667),
+            ticks: 1,
+            events: vec![
+                RecordedEvent::Key { scancode: 17, key: Some(VirtualKeyCode::W), pressed: true },
+                RecordedEvent::Mouse { button: MouseButton::Other(4), pressed: false },
+                RecordedEvent::Modifiers(ModifiersState::SHIFT),
+                RecordedEvent::CursorMoved { x: 10.5, y: -3.0 },
+                RecordedEvent::Scroll { x: 0.0, y: 1.0, pixels: false },
+                RecordedEvent::Character('é'),
+            ],
+            gamepad_events: vec![
+                GamepadEvent::Connected(0, GamepadInfo { name: "Pad".to_owned() }),
+                GamepadEvent::ButtonPressed(0, GamepadButton::South),
+                GamepadEvent::AxisChanged(0, GamepadAxis::LeftStickX, -0.75),
+            ],
+        });
+        recording.frames.push(RecordedFrame::default());
+
+        recording
+    }
+
+    #[test]
+    fn recordings_round_trip() {
+        let recording = recording();
+        let bytes = recording.to_bytes().unwrap();
+
+        assert_eq!(&bytes[..MAGIC.len()], MAGIC);
+        assert_eq!(InputRecording::from_bytes(&bytes).unwrap(), recording);
+        assert_eq!(recording.get_tick_count(), 1);
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        assert!(matches!(InputRecording::from_bytes(b"GOMPREC"), Err(GompError::InvalidRecording(_))));
+        assert!(matches!(InputRecording::from_bytes(b"PNG\0\0\0\0\0\x01\0\0\0"), Err(GompError::InvalidRecording(_))));
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut bytes = recording().to_bytes().unwrap();
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+
+        assert!(matches!(InputRecording::from_bytes(&bytes), Err(GompError::InvalidRecording(_))));
+    }
+
+    #[test]
+    fn truncated_recordings_are_refused() {
+        let bytes = recording().to_bytes().unwrap();
+
+        assert!(matches!(InputRecording::from_bytes(&bytes[..bytes.len() - 3]), Err(GompError::InvalidRecording(_))));
+    }
+}
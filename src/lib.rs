@@ -8,9 +8,18 @@
 #[macro_use]
 extern crate derivative;
 
+// So code generated by gomp_derive (which uses `::gomp::`) works in here too
+extern crate self as gomp;
+
 pub mod error;
 pub mod state;
 pub mod application;
 pub mod ecs;
 pub mod renderer;
+pub mod asset;
+pub mod watch;
+pub mod time;
+pub mod input;
+
+pub(crate) mod versioned;
 
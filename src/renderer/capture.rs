@@ -0,0 +1,285 @@
+//! Screenshots and frame recording.
+//!
+//! `capture_frame` saves the next presented frame to a PNG. `start_recording` saves *every*
+//! presented frame to numbered PNGs (`frame_000000.png`, `frame_000001.png`, ...) in a directory,
+//! which is handy for making trailers. While recording, the application steps time by a fixed
+//! timestep (`recording_timestep`) rather than the real frame time, so the recording plays back
+//! smoothly at the requested frame rate no matter how long each frame took to capture.
+//!
+//! # How
+//!
+//! Frames are copied from a texture into a buffer on the GPU, which is then mapped and read back
+//! once the GPU has got round to it, a frame or so later (the renderer checks every frame, rather
+//! than waiting). Surfaces usually can't be copied from (they aren't `COPY_SRC`), so in that case
+//! the frame is drawn a second time into an intermediate texture that can be. Encoding the PNG
+//! happens on a separate thread, so the game doesn't stall. When the renderer is dropped, it waits
+//! for frames still being read back or saved, so the end of a recording isn't lost.
+
+use super::Renderer;
+use super::render::Frame;
+use crate::error::Error as GompError;
+
+use futures::FutureExt;
+
+use std::future::Future;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::Duration;
+
+use log::{debug, info, error};
+
+/// An ongoing recording.
+#[derive(Debug)]
+pub(crate) struct Recording {
+    directory: PathBuf,
+    next_frame: u32,
+    timestep: Duration,
+}
+
+/// A frame that has been copied into a buffer, but not read back yet.
+pub(crate) struct PendingCapture {
+    buffer: wgpu::Buffer,
+    padded_bytes_per_row: u32,
+    width: u32,
+    height: u32,
+    paths: Vec<PathBuf>,
+}
+
+/// A captured frame, being read back from the GPU.
+pub(crate) struct ReadingCapture {
+    capture: PendingCapture,
+
+    /// Done once the buffer has been mapped.
+    mapped: Pin<Box<dyn Future<Output=Result<(), wgpu::BufferAsyncError>> + Send>>,
+}
+
+impl Renderer {
+    /// Saves the next presented frame to a PNG file.
+    pub fn capture_frame<P: AsRef<Path>>(&mut self, path: P) {
+        self.pending_captures.push(path.as_ref().to_path_buf());
+    }
+
+    /// Starts saving every presented frame to numbered PNGs in a directory (which is created if it
+    /// doesn't exist). Time is stepped by `1 / fps` seconds per frame while recording.
+    pub fn start_recording<P: AsRef<Path>>(&mut self, directory: P, fps: u32) -> Result<(), GompError> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory)?;
+
+        info!("Recording frames to {} at {} fps", directory.display(), fps);
+
+        self.recording = Some(Recording {
+            directory,
+            next_frame: 0,
+            timestep: Duration::from_secs_f64(1.0 / fps.max(1) as f64),
+        });
+
+        Ok(())
+    }
+
+    /// Stops recording. Does nothing if we weren't.
+    pub fn stop_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            info!("Stopped recording after {} frames", recording.next_frame);
+        }
+    }
+
+    /// Are we recording.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// The fixed amount time should advance by per frame while recording.
+    pub fn recording_timestep(&self) -> Option<Duration> {
+        self.recording.as_ref().map(|recording| recording.timestep)
+    }
+
+    /// Copies the frame into a buffer if there's a screenshot to take or we're recording.
+    pub(crate) fn encode_capture(&mut self, encoder: &mut wgpu::CommandEncoder, surface_texture: &wgpu::Texture, frame: &Frame) -> Option<PendingCapture> {
+        let mut paths = std::mem::take(&mut self.pending_captures);
+
+        if let Some(recording) = &mut self.recording {
+            paths.push(recording.directory.join(format!("frame_{:06}.png", recording.next_frame)));
+            recording.next_frame += 1;
+        }
+
+        if paths.is_empty() {
+            return None;
+        }
+
+        let (width, height) = (self.config.width, self.config.height);
+
+        // Surfaces usually aren't COPY_SRC, so draw the frame again somewhere that is.
+        let source = if self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+            surface_texture
+        } else {
+            self.ensure_capture_target();
+
+            let target = &self.capture_target.as_ref().expect("capture target was just created").0;
+            let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            self.encode_frame(encoder, &view, frame);
+
+            target
+        };
+
+        // Rows in a copy have to be aligned.
+        let unpadded_bytes_per_row = 4 * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame capture buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: source,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Some(PendingCapture {
+            buffer,
+            padded_bytes_per_row,
+            width,
+            height,
+            paths,
+        })
+    }
+
+    /// Starts reading a captured frame back, once it has been submitted.
+    pub(crate) fn read_capture(&mut self, capture: PendingCapture) {
+        let mapped = Box::pin(capture.buffer.slice(..).map_async(wgpu::MapMode::Read));
+
+        self.reading_captures.push(ReadingCapture {
+            capture,
+            mapped,
+        });
+    }
+
+    /// Saves every captured frame that has been read back. If `wait`, waits for the GPU to finish
+    /// with the rest first.
+    pub(crate) fn poll_captures(&mut self, wait: bool) {
+        self.saving_captures.retain(|saving| !saving.is_finished());
+
+        if self.reading_captures.is_empty() {
+            return;
+        }
+
+        self.device.poll(if wait { wgpu::Maintain::Wait } else { wgpu::Maintain::Poll });
+
+        for mut reading in std::mem::take(&mut self.reading_captures) {
+            let mapped = if wait {
+                Some(pollster::block_on(&mut reading.mapped))
+            } else {
+                (&mut reading.mapped).now_or_never()
+            };
+
+            match mapped {
+                Some(Ok(())) => self.save_capture(reading.capture),
+                Some(Err(e)) => error!("Failed to read back captured frame: {}", e),
+                None => self.reading_captures.push(reading),
+            }
+        }
+    }
+
+    /// Copies a mapped frame out, and saves it on another thread.
+    fn save_capture(&mut self, capture: PendingCapture) {
+        let slice = capture.buffer.slice(..);
+        let unpadded_bytes_per_row = (4 * capture.width) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * capture.height as usize);
+
+        for row in slice.get_mapped_range().chunks(capture.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+        }
+
+        capture.buffer.unmap();
+
+        // Most surfaces are BGRA, but PNGs are RGBA
+        if let wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb = self.config.format {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        let (width, height, paths) = (capture.width, capture.height, capture.paths);
+
+        let saving = std::thread::spawn(move || {
+            let image = match image::RgbaImage::from_raw(width, height, pixels) {
+                Some(image) => image,
+                None => {
+                    error!("Captured frame was the wrong size");
+
+                    return;
+                }
+            };
+
+            for path in paths {
+                match image.save(&path) {
+                    Ok(()) => debug!("Saved frame to {}", path.display()),
+                    Err(e) => error!("Failed to save frame to {}: {}", path.display(), e),
+                }
+            }
+        });
+
+        self.saving_captures.push(saving);
+    }
+
+    /// Makes sure there is an intermediate texture (the size of the surface) to capture from.
+    fn ensure_capture_target(&mut self) {
+        let size = (self.config.width, self.config.height);
+
+        if let Some((_, target_size)) = &self.capture_target {
+            if *target_size == size {
+                return;
+            }
+        }
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Frame capture target"),
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        self.capture_target = Some((texture, size));
+    }
+}
+
+impl Drop for Renderer {
+    /// Saves any frames still being read back, and waits for every frame to be saved.
+    fn drop(&mut self) {
+        self.poll_captures(true);
+
+        for saving in self.saving_captures.drain(..) {
+            if saving.join().is_err() {
+                error!("Thread saving a captured frame panicked");
+            }
+        }
+    }
+}
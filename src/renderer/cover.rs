@@ -0,0 +1,66 @@
+//! Screen covers.
+//!
+//! A cover is a flat coloured rectangle drawn over every scene (but under debug shapes), blended by
+//! its opacity. Scene transitions use it for fades and wipes, but anything can set one.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! // Fade the screen halfway to black
+//! renderer.set_cover(Some(Cover::full_screen([0.0, 0.0, 0.0], 0.5)));
+//! ```
+
+use super::Renderer;
+use super::vertex::Vertex;
+
+/// A coloured rectangle drawn over the frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cover {
+    /// The bottom left corner, in clip space.
+    pub min: [f32; 2],
+
+    /// The top right corner, in clip space.
+    pub max: [f32; 2],
+
+    pub colour: [f32; 3],
+
+    /// How opaque the cover is, from 0 (not drawn at all) to 1.
+    pub opacity: f32,
+}
+
+impl Cover {
+    /// A cover over the whole screen.
+    pub fn full_screen(colour: [f32; 3], opacity: f32) -> Self {
+        Self {
+            min: [-1.0, -1.0],
+            max: [1.0, 1.0],
+            colour,
+            opacity,
+        }
+    }
+}
+
+impl Renderer {
+    /// Sets the cover drawn over every frame, until it's changed (`None` for no cover).
+    pub fn set_cover(&mut self, cover: Option<Cover>) {
+        self.cover = cover;
+    }
+
+    /// Gets the cover drawn over every frame.
+    pub fn get_cover(&self) -> Option<&Cover> {
+        self.cover.as_ref()
+    }
+}
+
+/// Two counter-clockwise triangles covering the cover's rectangle.
+pub(crate) fn cover_vertices(cover: &Cover) -> [Vertex; 6] {
+    let [left, bottom] = cover.min;
+    let [right, top] = cover.max;
+
+    let bottom_left = Vertex::new([left, bottom, 0.0], cover.colour);
+    let bottom_right = Vertex::new([right, bottom, 0.0], cover.colour);
+    let top_right = Vertex::new([right, top, 0.0], cover.colour);
+    let top_left = Vertex::new([left, top, 0.0], cover.colour);
+
+    [bottom_left, bottom_right, top_right, bottom_left, top_right, top_left]
+}
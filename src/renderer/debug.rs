@@ -0,0 +1,251 @@
+//! Immediate mode debug drawing.
+//!
+//! Handy for gameplay debugging (where is that hitbox? which way is the ball going?). Shapes are
+//! collected throughout the frame, drawn over everything else in a final overlay pass with a
+//! line-list pipeline, and then thrown away once the frame has been presented. Nothing is
+//! retained, so draw them every frame you want to see them.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let debug = renderer.debug();
+//!
+//! debug.line([0.0, 0.0], [0.5, 0.5], [1.0, 0.0, 0.0]);
+//! debug.rect([-0.1, -0.1], [0.1, 0.1], [0.0, 1.0, 0.0]);
+//! debug.circle([0.0, 0.0], 0.25, [0.0, 0.0, 1.0]);
+//! debug.arrow([0.0, 0.0], [0.3, 0.0], [1.0, 1.0, 0.0]);
+//! debug.text_marker([0.2, 0.2], "spawn", [1.0, 1.0, 1.0]);
+//! ```
+//!
+//! # Feature flag
+//!
+//! All of this lives behind the `debug-draw` cargo feature (on by default). Without it the API is
+//! still there so your code keeps compiling, but every call does nothing and the overlay pipeline
+//! is never built. It can also be toggled at runtime with `set_enabled`.
+
+use super::vertex::Vertex;
+
+use std::f32::consts::PI;
+
+/// How many segments make up a circle.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// How big text markers (and arrow heads) are.
+const MARKER_SIZE: f32 = 0.02;
+
+/// A labelled point.
+#[derive(Debug, Clone)]
+pub struct TextMarker {
+    pub position: [f32; 2],
+    pub text: String,
+    pub colour: [f32; 3],
+}
+
+/// Collects debug shapes for the current frame.
+#[derive(Debug)]
+pub struct DebugDraw {
+    /// Is debug drawing enabled at runtime.
+    enabled: bool,
+
+    /// Every line, as pairs of vertices.
+    vertices: Vec<Vertex>,
+
+    /// Every text marker.
+    markers: Vec<TextMarker>,
+}
+
+impl DebugDraw {
+    /// Creates a new (enabled) debug drawer.
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            vertices: vec![],
+            markers: vec![],
+        }
+    }
+
+    /// Enables or disables debug drawing at runtime. Shapes drawn while disabled are dropped.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    /// Is debug drawing enabled. Always false without the `debug-draw` feature.
+    pub fn is_enabled(&self) -> bool {
+        cfg!(feature = "debug-draw") && self.enabled
+    }
+
+    /// Draws a line from `a` to `b`.
+    pub fn line(&mut self, a: [f32; 2], b: [f32; 2], colour: [f32; 3]) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.vertices.push(Vertex::new([a[0], a[1], 0.0], colour));
+        self.vertices.push(Vertex::new([b[0], b[1], 0.0], colour));
+    }
+
+    /// Draws the outline of a rectangle, from its bottom left to its top right corner.
+    pub fn rect(&mut self, min: [f32; 2], max: [f32; 2], colour: [f32; 3]) {
+        self.line([min[0], min[1]], [max[0], min[1]], colour);
+        self.line([max[0], min[1]], [max[0], max[1]], colour);
+        self.line([max[0], max[1]], [min[0], max[1]], colour);
+        self.line([min[0], max[1]], [min[0], min[1]], colour);
+    }
+
+    /// Draws the outline of a circle.
+    pub fn circle(&mut self, centre: [f32; 2], radius: f32, colour: [f32; 3]) {
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * 2.0 * PI;
+
+            [centre[0] + radius * angle.cos(), centre[1] + radius * angle.sin()]
+        };
+
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), colour);
+        }
+    }
+
+    /// Draws an arrow pointing from `from` to `to`.
+    pub fn arrow(&mut self, from: [f32; 2], to: [f32; 2], colour: [f32; 3]) {
+        self.line(from, to, colour);
+
+        let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+        let length = (dx * dx + dy * dy).sqrt();
+
+        if length == 0.0 {
+            return;
+        }
+
+        // Unit vector pointing back along the arrow, and one perpendicular to it.
+        let (bx, by) = (-dx / length, -dy / length);
+        let (px, py) = (-by, bx);
+
+        let head = MARKER_SIZE.min(length / 2.0);
+        self.line(to, [to[0] + (bx + px) * head, to[1] + (by + py) * head], colour);
+        self.line(to, [to[0] + (bx - px) * head, to[1] + (by - py) * head], colour);
+    }
+
+    /// Marks a point with a small diamond and a label.
+    pub fn text_marker(&mut self, position: [f32; 2], text: &str, colour: [f32; 3]) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let [x, y] = position;
+        self.line([x - MARKER_SIZE, y], [x, y + MARKER_SIZE], colour);
+        self.line([x, y + MARKER_SIZE], [x + MARKER_SIZE, y], colour);
+        self.line([x + MARKER_SIZE, y], [x, y - MARKER_SIZE], colour);
+        self.line([x, y - MARKER_SIZE], [x - MARKER_SIZE, y], colour);
+
+        self.markers.push(TextMarker {
+            position,
+            text: text.to_owned(),
+            colour,
+        });
+    }
+
+    /// Gets every line vertex drawn this frame (in pairs).
+    pub fn get_vertices(&self) -> &Vec<Vertex> {
+        &self.vertices
+    }
+
+    /// Gets every text marker drawn this frame.
+    pub fn get_text_markers(&self) -> &Vec<TextMarker> {
+        &self.markers
+    }
+
+    /// Throws away everything drawn so far. Called by the renderer after presenting.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.markers.clear();
+    }
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "debug-draw")]
+    fn positions(debug: &DebugDraw) -> Vec<[f32; 3]> {
+        debug.get_vertices().iter().map(Vertex::get_position).collect()
+    }
+
+    #[cfg(feature = "debug-draw")]
+    #[test]
+    fn shapes_are_queued_as_lines() {
+        let mut debug = DebugDraw::new();
+
+        debug.line([0.0, 0.0], [0.5, 0.5], [1.0, 0.0, 0.0]);
+        assert_eq!(positions(&debug), [[0.0, 0.0, 0.0], [0.5, 0.5, 0.0]]);
+        assert_eq!(debug.get_vertices()[0].get_colour(), [1.0, 0.0, 0.0]);
+
+        debug.rect([-0.1, -0.1], [0.1, 0.1], [0.0, 1.0, 0.0]);
+        assert_eq!(debug.get_vertices().len(), 2 + 4 * 2);
+
+        debug.circle([0.0, 0.0], 0.25, [0.0, 0.0, 1.0]);
+        assert_eq!(debug.get_vertices().len(), 2 + 4 * 2 + CIRCLE_SEGMENTS * 2);
+
+        // Every point of the circle is on it, and it's closed
+        let circle = &positions(&debug)[10..];
+        assert!(circle.iter().all(|[x, y, _]| ((x * x + y * y).sqrt() - 0.25).abs() < 1e-5));
+        assert!((circle[0][0] - circle[circle.len() - 1][0]).abs() < 1e-5);
+
+        debug.text_marker([0.2, 0.2], "spawn", [1.0, 1.0, 1.0]);
+        assert_eq!(debug.get_text_markers()[0].text, "spawn");
+    }
+
+    #[cfg(feature = "debug-draw")]
+    #[test]
+    fn shapes_only_last_until_the_frame_is_presented() {
+        let mut debug = DebugDraw::new();
+
+        debug.arrow([0.0, 0.0], [0.3, 0.0], [1.0, 1.0, 0.0]);
+        debug.text_marker([0.2, 0.2], "spawn", [1.0, 1.0, 1.0]);
+        assert_eq!(debug.get_vertices().len(), 3 * 2 + 4 * 2);
+
+        debug.clear();
+        assert!(debug.get_vertices().is_empty());
+        assert!(debug.get_text_markers().is_empty());
+    }
+
+    #[test]
+    fn nothing_is_queued_while_disabled() {
+        let mut debug = DebugDraw::new();
+        debug.line([0.0, 0.0], [0.5, 0.5], [1.0, 0.0, 0.0]);
+
+        // Disabling throws away what's already there too
+        debug.set_enabled(false);
+        debug.rect([-0.1, -0.1], [0.1, 0.1], [0.0, 1.0, 0.0]);
+        debug.text_marker([0.2, 0.2], "spawn", [1.0, 1.0, 1.0]);
+
+        assert!(!debug.is_enabled());
+        assert!(debug.get_vertices().is_empty());
+        assert!(debug.get_text_markers().is_empty());
+    }
+
+    #[cfg(not(feature = "debug-draw"))]
+    #[test]
+    fn every_call_does_nothing_without_the_feature() {
+        let mut debug = DebugDraw::new();
+        debug.set_enabled(true);
+
+        debug.line([0.0, 0.0], [0.5, 0.5], [1.0, 0.0, 0.0]);
+        debug.circle([0.0, 0.0], 0.25, [0.0, 0.0, 1.0]);
+        debug.arrow([0.0, 0.0], [0.3, 0.0], [1.0, 1.0, 0.0]);
+        debug.text_marker([0.2, 0.2], "spawn", [1.0, 1.0, 1.0]);
+
+        assert!(!debug.is_enabled());
+        assert!(debug.get_vertices().is_empty());
+        assert!(debug.get_text_markers().is_empty());
+    }
+}
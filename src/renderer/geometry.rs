@@ -0,0 +1,134 @@
+//! Mesh geometry.
+//!
+//! Geometry lives on the CPU inside an `Rc`, so many `Mesh` components can share it. The renderer
+//! uploads it to the GPU the first time it is drawn, and keeps the buffers around (keyed by the
+//! geometry's ID) so it only happens once. Meshes that share geometry and a material are drawn
+//! together with instancing.
+
+use super::Renderer;
+use super::vertex::Vertex;
+
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Used to hand out unique geometry IDs.
+static NEXT_GEOMETRY_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The unit quad every mesh starts out with. Shared, so they all batch together.
+    static UNIT_QUAD: Rc<Geometry> = Rc::new(Geometry::new(
+        vec![
+            Vertex::new([-0.5, -0.5, 0.0], [1.0, 1.0, 1.0]),
+            Vertex::new([0.5, -0.5, 0.0], [1.0, 1.0, 1.0]),
+            Vertex::new([0.5, 0.5, 0.0], [1.0, 1.0, 1.0]),
+            Vertex::new([-0.5, 0.5, 0.0], [1.0, 1.0, 1.0]),
+        ],
+        vec![0, 1, 2, 0, 2, 3],
+    ));
+
+    /// Geometry handed out by `Geometry::shared`, while it's still in use.
+    static SHARED: RefCell<Vec<Weak<Geometry>>> = const { RefCell::new(vec![]) };
+}
+
+/// A bunch of vertices, and the order to draw them in.
+#[derive(Debug)]
+pub struct Geometry {
+    id: u64,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+}
+
+impl Geometry {
+    /// Creates new geometry. Indices are counter-clockwise triangles.
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u16>) -> Self {
+        Self {
+            id: NEXT_GEOMETRY_ID.fetch_add(1, Ordering::Relaxed),
+            vertices,
+            indices,
+        }
+    }
+
+    /// Gets geometry with these vertices and indices, shared with any identical geometry from
+    /// earlier calls that is still in use. Used when geometry is read from data (a scene file, say),
+    /// so identical meshes still batch together.
+    pub fn shared(vertices: Vec<Vertex>, indices: Vec<u16>) -> Rc<Self> {
+        SHARED.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            shared.retain(|geometry| geometry.strong_count() > 0);
+
+            let existing = shared.iter()
+                .filter_map(Weak::upgrade)
+                .find(|geometry| geometry.vertices == vertices && geometry.indices == indices);
+
+            existing.unwrap_or_else(|| {
+                let geometry = Rc::new(Self::new(vertices, indices));
+                shared.push(Rc::downgrade(&geometry));
+
+                geometry
+            })
+        })
+    }
+
+    /// Gets the shared unit quad (centered on the origin, with sides of length 1).
+    pub fn unit_quad() -> Rc<Self> {
+        UNIT_QUAD.with(Rc::clone)
+    }
+
+    /// Gets the unique ID of the geometry.
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    /// Gets the vertices.
+    pub fn get_vertices(&self) -> &Vec<Vertex> {
+        &self.vertices
+    }
+
+    /// Gets the indices.
+    pub fn get_indices(&self) -> &Vec<u16> {
+        &self.indices
+    }
+}
+
+/// Geometry that has been uploaded to the GPU.
+#[derive(Debug)]
+pub struct GpuGeometry {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+}
+
+impl Renderer {
+    /// Uploads geometry to the GPU, unless it has already been uploaded.
+    pub(crate) fn upload_geometry(&mut self, geometry: &Geometry) {
+        if self.geometry.contains_key(&geometry.get_id()) {
+            return;
+        }
+
+        let vertex_buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Geometry vertex buffer"),
+            contents: bytemuck::cast_slice(geometry.get_vertices()),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Geometry index buffer"),
+            contents: bytemuck::cast_slice(geometry.get_indices()),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        self.geometry.insert(geometry.get_id(), GpuGeometry {
+            vertex_buffer,
+            index_buffer,
+            index_count: geometry.get_indices().len() as u32,
+        });
+    }
+
+    /// Frees the GPU buffers of some geometry. It will be uploaded again if it is drawn again.
+    pub fn forget_geometry(&mut self, id: u64) {
+        self.geometry.remove(&id);
+    }
+}
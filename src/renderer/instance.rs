@@ -0,0 +1,77 @@
+/// Per-instance data for instanced rendering. Every entity that shares a mesh and a material is
+/// drawn in a single draw call, with one of these per entity.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Instance {
+    /// The model matrix, column major.
+    model: [[f32; 4]; 4],
+
+    /// The colour the mesh is tinted with.
+    colour: [f32; 4],
+}
+
+/// Non-static implementations
+impl Instance {
+    /// Construct a new instance.
+    pub fn new(model: cgmath::Matrix4<f32>, colour: [f32; 4]) -> Self {
+        Self {
+            model: model.into(),
+            colour,
+        }
+    }
+}
+
+/// Static implementations
+impl Instance {
+    /// Describes how an instance buffer is layed out in memory. Unlike `Vertex::desc`, this steps
+    /// once per instance rather than once per vertex.
+    ///
+    /// A `mat4x4` can't be a single vertex attribute, so the model matrix is passed in as four
+    /// column vectors, in locations 2 to 5. The colour is in location 6.
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    /// The attributes of an instance. This has to be a constant (rather than built inline like in
+    /// `Vertex::desc`), as the offsets are computed and the slice needs to live long enough.
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = [
+        wgpu::VertexAttribute {
+            offset: 0,
+            shader_location: 2,
+            format: wgpu::VertexFormat::Float32x4,
+        },
+        wgpu::VertexAttribute {
+            offset: COLUMN,
+            shader_location: 3,
+            format: wgpu::VertexFormat::Float32x4,
+        },
+        wgpu::VertexAttribute {
+            offset: COLUMN * 2,
+            shader_location: 4,
+            format: wgpu::VertexFormat::Float32x4,
+        },
+        wgpu::VertexAttribute {
+            offset: COLUMN * 3,
+            shader_location: 5,
+            format: wgpu::VertexFormat::Float32x4,
+        },
+        wgpu::VertexAttribute {
+            offset: COLUMN * 4,
+            shader_location: 6,
+            format: wgpu::VertexFormat::Float32x4,
+        },
+    ];
+}
+
+/// The size of one column of the model matrix.
+const COLUMN: wgpu::BufferAddress = std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress;
+
+/// Implementation to enable bytemuck.
+unsafe impl bytemuck::Zeroable for Instance {}
+
+/// Implementation to enable bytemuck.
+unsafe impl bytemuck::Pod for Instance {}
@@ -0,0 +1,8 @@
+/// How a mesh is shaded. Meshes are only batched together (drawn with instancing) if they share
+/// both geometry and a material.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Material {
+    /// Vertex colours, tinted by the colour of the mesh. Drawn with the default pipeline.
+    #[default]
+    Flat,
+}
@@ -13,19 +13,55 @@
 //! | `new` | Creates a new renderer. |
 //! | `resize` | Handles window resize events. |
 //! | `render` | Renders a frame. |
+//! | `load_shader_file` | Swaps in a shader from disk, and watches it for changes. |
+//! | `debug` | Immediate mode debug shapes, drawn over everything else. |
+//! | `create_texture` | Uploads an image for the textured pipeline. |
+//! | `capture_frame` | Saves the next frame to a PNG. |
+//! | `start_recording` | Saves every frame to numbered PNGs. |
+//! | `add_pass` | Draws a plugin's own render passes every frame. |
+//! | `set_cover` | Covers the frame with a colour (for scene transitions). |
 
 pub mod buffer;
 pub mod vertex;
 pub mod shaders;
 pub mod render;
+pub mod pipeline;
+pub mod reload;
+pub mod instance;
+pub mod geometry;
+pub mod material;
+pub mod debug;
+pub mod texture;
+pub mod text;
+pub mod capture;
+pub mod pass;
+pub mod cover;
+pub mod plugin;
 
-use shaders::ShaderBuilder;
-use vertex::Vertex;
+use shaders::{ShaderBuilder, ShaderStage};
+use pipeline::PipelineBuilder;
+use vertex::{Vertex, TexturedVertex};
+use instance::Instance;
+use geometry::GpuGeometry;
+use debug::DebugDraw;
+use texture::Texture;
+use text::{atlas::GlyphAtlas, font::Font};
+use capture::{ReadingCapture, Recording};
+use pass::{CustomPass, PassContext};
+use cover::Cover;
+
+use crate::error::Error as GompError;
+use crate::watch::FileWatcher;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
 
 use winit::window::Window;
 use log::debug;
 
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct Renderer {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -33,6 +69,55 @@ pub struct Renderer {
     config: wgpu::SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,
+
+    vertex_shader: wgpu::ShaderModule,
+    fragment_shader: wgpu::ShaderModule,
+
+    /// Watches the shaders that were loaded from files, so we can hot-reload them.
+    shader_watcher: FileWatcher,
+    /// Which stage each watched shader file is for.
+    shader_files: HashMap<PathBuf, ShaderStage>,
+    /// Are watched shaders reloaded.
+    hot_reload: bool,
+
+    /// Geometry that has been uploaded to the GPU, by geometry ID.
+    geometry: HashMap<u64, GpuGeometry>,
+
+    /// Every texture is bound with this layout.
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    /// Draws textured (and alpha blended) triangles. Used for text.
+    textured_pipeline: wgpu::RenderPipeline,
+
+    /// Rasterised glyphs, and the texture they get uploaded to.
+    glyph_atlas: GlyphAtlas,
+    glyph_texture: Option<Texture>,
+
+    /// Debug shapes drawn this frame.
+    debug: DebugDraw,
+    #[cfg(feature = "debug-draw")]
+    debug_pipeline: wgpu::RenderPipeline,
+    /// The font debug text markers are labelled with.
+    debug_font: Option<Font>,
+
+    /// Where to save the next frame to.
+    pending_captures: Vec<PathBuf>,
+    /// Set while recording every frame.
+    recording: Option<Recording>,
+    /// Frames are drawn into this when capturing, if the surface can't be copied from.
+    capture_target: Option<(wgpu::Texture, (u32, u32))>,
+    /// Captured frames that haven't been read back from the GPU yet.
+    #[derivative(Debug="ignore")]
+    reading_captures: Vec<ReadingCapture>,
+    /// The threads saving captured frames.
+    saving_captures: Vec<JoinHandle<()>>,
+
+    /// Drawn over the frame, and the pipeline that draws it.
+    cover: Option<Cover>,
+    cover_pipeline: wgpu::RenderPipeline,
+
+    /// Render passes added by plugins, drawn after the scene.
+    #[derivative(Debug = "ignore")]
+    passes: Vec<Box<dyn CustomPass>>,
 }
 
 impl Renderer {
@@ -94,55 +179,72 @@ impl Renderer {
             .with_device(&device)
             .compile()?;
 
-        debug!("Creating render pipeline layout");
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Default render pipeline layout"),
-            bind_group_layouts: &[],
-            push_constant_ranges: &[],
-        });
-
-        debug!("Creating render pipeline with the just-now-created layout");
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            // Specify vertex instructions.
-            vertex: wgpu::VertexState {
-                module: &vertex_shader,
-                entry_point: "main",
-                buffers: &[
-                    Vertex::desc(),
-                ],
-            },
-            // Specify fragment instructions.
-            fragment: Some(wgpu::FragmentState {
-                module: &fragment_shader,
-                entry_point: "main",
-                targets: &[wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLAMPING
-                clamp_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false, // 4.
-            },
-        });
+        debug!("Creating render pipeline");
+        let render_pipeline = build_render_pipeline(&device, &vertex_shader, &fragment_shader, config.format)?;
+
+        debug!("Creating textured pipeline");
+        let texture_bind_group_layout = Texture::create_bind_group_layout(&device);
+        let textured_shader = ShaderBuilder::new()
+            .with_label("Textured shader")
+            .with_source(shaders::ShaderSourceType::Wgsl, shaders::textured::TEXTURED_SOURCE)
+            .with_device(&device)
+            .compile()?;
+
+        let textured_pipeline = PipelineBuilder::new()
+            .with_label("Textured pipeline")
+            .with_vertex_shader(&textured_shader, "vs_main")
+            .with_fragment_shader(&textured_shader, "fs_main")
+            .with_vertex_buffer(TexturedVertex::desc())
+            .with_bind_group_layout(&texture_bind_group_layout)
+            .with_blend(wgpu::BlendState::ALPHA_BLENDING)
+            .with_format(config.format)
+            .with_device(&device)
+            .build()?;
+
+        debug!("Creating cover pipeline");
+        let cover_shader = ShaderBuilder::new()
+            .with_label("Cover shader")
+            .with_source(shaders::ShaderSourceType::Wgsl, shaders::debug::DEBUG_SOURCE)
+            .with_device(&device)
+            .compile()?;
+
+        // The shader is opaque, so the opacity comes in as the blend constant
+        let cover_pipeline = PipelineBuilder::new()
+            .with_label("Cover pipeline")
+            .with_vertex_shader(&cover_shader, "vs_main")
+            .with_fragment_shader(&cover_shader, "fs_main")
+            .with_vertex_buffer(Vertex::desc())
+            .with_blend(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Constant,
+                    dst_factor: wgpu::BlendFactor::OneMinusConstant,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            })
+            .with_format(config.format)
+            .with_device(&device)
+            .build()?;
+
+        #[cfg(feature = "debug-draw")]
+        let debug_pipeline = {
+            debug!("Creating debug drawing pipeline");
+            let debug_shader = ShaderBuilder::new()
+                .with_label("Debug drawing shader")
+                .with_source(shaders::ShaderSourceType::Wgsl, shaders::debug::DEBUG_SOURCE)
+                .with_device(&device)
+                .compile()?;
+
+            PipelineBuilder::new()
+                .with_label("Debug drawing pipeline")
+                .with_vertex_shader(&debug_shader, "vs_main")
+                .with_fragment_shader(&debug_shader, "fs_main")
+                .with_vertex_buffer(Vertex::desc())
+                .with_topology(wgpu::PrimitiveTopology::LineList)
+                .with_format(config.format)
+                .with_device(&device)
+                .build()?
+        };
 
         Ok(Self {
             surface,
@@ -151,6 +253,28 @@ impl Renderer {
             config,
             size,
             render_pipeline,
+            vertex_shader,
+            fragment_shader,
+            shader_watcher: FileWatcher::new(),
+            shader_files: HashMap::new(),
+            hot_reload: true,
+            geometry: HashMap::new(),
+            texture_bind_group_layout,
+            textured_pipeline,
+            glyph_atlas: GlyphAtlas::new(),
+            glyph_texture: None,
+            debug: DebugDraw::new(),
+            #[cfg(feature = "debug-draw")]
+            debug_pipeline,
+            debug_font: None,
+            pending_captures: vec![],
+            recording: None,
+            capture_target: None,
+            reading_captures: vec![],
+            saving_captures: vec![],
+            cover: None,
+            cover_pipeline,
+            passes: vec![],
         })
     }
 
@@ -164,9 +288,51 @@ impl Renderer {
         }
     }
 
+    /// Gets the debug drawer. Anything drawn with it is shown over the next frame only.
+    pub fn debug(&mut self) -> &mut DebugDraw {
+        &mut self.debug
+    }
+
     /// Returns the device the renderer is rendering to.
     pub fn get_device(&self) -> &wgpu::Device {
         &self.device
     }
+
+    /// Returns the queue commands are submitted to.
+    pub fn get_queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Returns the format of the surface.
+    pub fn get_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Adds a render pass, drawn after the scene (and text) every frame. See the `pass` module.
+    pub fn add_pass(&mut self, pass: Box<dyn CustomPass>) {
+        self.passes.push(pass);
+    }
+
+    /// Gets what custom passes work with.
+    fn pass_context(&self) -> PassContext<'_> {
+        PassContext {
+            device: &self.device,
+            queue: &self.queue,
+            format: self.config.format,
+            size: self.size,
+        }
+    }
 }
 
+/// Builds the default render pipeline. Also used to rebuild it when a shader is reloaded.
+fn build_render_pipeline(device: &wgpu::Device, vertex_shader: &wgpu::ShaderModule, fragment_shader: &wgpu::ShaderModule, format: wgpu::TextureFormat) -> Result<wgpu::RenderPipeline, GompError> {
+    PipelineBuilder::new()
+        .with_label("Default render pipeline")
+        .with_vertex_shader(vertex_shader, "main")
+        .with_fragment_shader(fragment_shader, "main")
+        .with_vertex_buffer(Vertex::desc())
+        .with_instance_buffer(Instance::desc())
+        .with_format(format)
+        .with_device(device)
+        .build()
+}
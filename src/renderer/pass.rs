@@ -0,0 +1,34 @@
+//! Custom render passes.
+//!
+//! Plugins can draw things the renderer doesn't know about by adding a `CustomPass`. Every frame,
+//! each pass is given the chance to upload what it needs (`prepare`), then records its own render
+//! passes onto the frame (`encode`). Custom passes are drawn after the scene and text, but under
+//! debug shapes, in the order they were added.
+//!
+//! Remember to load (rather than clear) the view in your render pass, or you'll wipe out the
+//! scene.
+
+use crate::ecs::scene::Scene;
+
+/// What a custom pass gets to work with.
+#[derive(Debug)]
+pub struct PassContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+
+    /// The format of the view being drawn to, for building pipelines.
+    pub format: wgpu::TextureFormat,
+
+    /// The size of the view being drawn to, in pixels.
+    pub size: winit::dpi::PhysicalSize<u32>,
+}
+
+/// Something that draws its own render passes every frame.
+pub trait CustomPass {
+    /// Called every frame before any render pass starts, so buffers can be uploaded. `alpha` is
+    /// the same as the one given to `Renderer::render`.
+    fn prepare(&mut self, _context: &PassContext, _scene: &Scene, _alpha: f64) {}
+
+    /// Records render passes drawing onto `view`.
+    fn encode(&self, context: &PassContext, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView);
+}
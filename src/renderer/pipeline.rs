@@ -0,0 +1,232 @@
+//! Render pipeline abstraction.
+//!
+//! Building a `wgpu::RenderPipeline` by hand takes a good 50 lines of descriptors, most of which
+//! are the same for every pipeline gomp uses. This builder fills in the boring parts, in the same
+//! fashion as the `ShaderBuilder`.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let pipeline = PipelineBuilder::new()
+//!     .with_label("Default render pipeline")
+//!     .with_vertex_shader(&vertex_shader, "main")
+//!     .with_fragment_shader(&fragment_shader, "main")
+//!     .with_vertex_buffer(Vertex::desc())
+//!     .with_instance_buffer(Instance::desc())
+//!     .with_format(config.format)
+//!     .with_device(&device)
+//!     .build()?;
+//! ```
+
+use wgpu::{
+    BindGroupLayout,
+    BlendState,
+    Device,
+    PrimitiveTopology,
+    RenderPipeline,
+    ShaderModule,
+    TextureFormat,
+    VertexBufferLayout,
+};
+
+use log::warn;
+
+use crate::error::Error as GompError;
+
+/// Helps to build a render pipeline.
+#[derive(Debug)]
+pub struct PipelineBuilder<'a> {
+    /// The label of the pipeline.
+    label: String,
+
+    /// The vertex shader, and its entry point.
+    vertex: Option<(&'a ShaderModule, String)>,
+
+    /// The fragment shader, and its entry point.
+    fragment: Option<(&'a ShaderModule, String)>,
+
+    /// The layouts of the vertex buffers, in slot order.
+    buffers: Vec<VertexBufferLayout<'a>>,
+
+    /// The bind group layouts, in group order.
+    bind_group_layouts: Vec<&'a BindGroupLayout>,
+
+    /// How the vertices are assembled into primitives.
+    topology: PrimitiveTopology,
+
+    /// How fragments are blended onto the target.
+    blend: BlendState,
+
+    /// The format of the texture we are rendering to.
+    format: Option<TextureFormat>,
+
+    /// The device to build the pipeline on.
+    device: Option<&'a Device>,
+}
+
+impl<'a> PipelineBuilder<'a> {
+    /// Creates a new pipeline builder.
+    pub fn new() -> Self {
+        Self {
+            label: "".to_owned(),
+            vertex: None,
+            fragment: None,
+            buffers: vec![],
+            bind_group_layouts: vec![],
+            topology: PrimitiveTopology::TriangleList,
+            blend: BlendState::REPLACE,
+            format: None,
+            device: None,
+        }
+    }
+
+    /// Gives the pipeline a label. Not required, but highly recommended.
+    pub fn with_label(self, label: &str) -> Self {
+        Self {
+            label: label.to_owned(),
+            .. self
+        }
+    }
+
+    /// Sets the vertex shader. Required.
+    pub fn with_vertex_shader(self, module: &'a ShaderModule, entry: &str) -> Self {
+        Self {
+            vertex: Some((module, entry.to_owned())),
+            .. self
+        }
+    }
+
+    /// Sets the fragment shader. Required.
+    pub fn with_fragment_shader(self, module: &'a ShaderModule, entry: &str) -> Self {
+        Self {
+            fragment: Some((module, entry.to_owned())),
+            .. self
+        }
+    }
+
+    /// Adds a vertex buffer layout. Buffers are bound to slots in the order they are added.
+    pub fn with_vertex_buffer(mut self, layout: VertexBufferLayout<'a>) -> Self {
+        self.buffers.push(layout);
+        self
+    }
+
+    /// Adds an instance buffer layout. This is just a vertex buffer that steps once per instance
+    /// rather than once per vertex, so the step mode is forced to `Instance`. Add these after the
+    /// per-vertex buffers.
+    pub fn with_instance_buffer(mut self, layout: VertexBufferLayout<'a>) -> Self {
+        self.buffers.push(VertexBufferLayout {
+            step_mode: wgpu::VertexStepMode::Instance,
+            .. layout
+        });
+        self
+    }
+
+    /// Adds a bind group layout. Groups are numbered in the order they are added.
+    pub fn with_bind_group_layout(mut self, layout: &'a BindGroupLayout) -> Self {
+        self.bind_group_layouts.push(layout);
+        self
+    }
+
+    /// Sets the primitive topology. Defaults to a triangle list.
+    pub fn with_topology(self, topology: PrimitiveTopology) -> Self {
+        Self {
+            topology,
+            .. self
+        }
+    }
+
+    /// Sets the blend state. Defaults to replacing whatever is already there.
+    pub fn with_blend(self, blend: BlendState) -> Self {
+        Self {
+            blend,
+            .. self
+        }
+    }
+
+    /// Sets the format of the texture being rendered to. Required.
+    pub fn with_format(self, format: TextureFormat) -> Self {
+        Self {
+            format: Some(format),
+            .. self
+        }
+    }
+
+    /// The device to build the pipeline on. Required.
+    pub fn with_device(self, device: &'a Device) -> Self {
+        Self {
+            device: Some(device),
+            .. self
+        }
+    }
+}
+
+impl<'a> PipelineBuilder<'a> {
+    /// Actually builds the pipeline.
+    pub fn build(self) -> Result<RenderPipeline, GompError> {
+        let device = self.device.ok_or(GompError::NoDeviceSupplied)?;
+        let (vertex_module, vertex_entry) = self.vertex.ok_or(GompError::NoShaderSupplied)?;
+        let (fragment_module, fragment_entry) = self.fragment.ok_or(GompError::NoShaderSupplied)?;
+        let format = self.format.ok_or(GompError::NoTargetFormat)?;
+
+        if self.label.is_empty() {
+            warn!("No label given for new render pipeline. Consider giving it one to ease in debugging");
+        }
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&self.label),
+            bind_group_layouts: &self.bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        // Line and point lists can't be culled, as they have no face.
+        let cull_mode = match self.topology {
+            PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip => Some(wgpu::Face::Back),
+            _ => None,
+        };
+
+        Ok(device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&self.label),
+            layout: Some(&layout),
+            // Specify vertex instructions.
+            vertex: wgpu::VertexState {
+                module: vertex_module,
+                entry_point: &vertex_entry,
+                buffers: &self.buffers,
+            },
+            // Specify fragment instructions.
+            fragment: Some(wgpu::FragmentState {
+                module: fragment_module,
+                entry_point: &fragment_entry,
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(self.blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: self.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                // Requires Features::DEPTH_CLAMPING
+                clamp_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        }))
+    }
+}
+
+impl<'a> Default for PipelineBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
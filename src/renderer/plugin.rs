@@ -0,0 +1,130 @@
+//! The rendering plugin.
+//!
+//! Creates the renderer once the window is open, draws the active scene every frame, keeps the
+//! surface the size of the window and takes screenshots when the screenshot key is pressed.
+//! Headless applications have nothing to render to, so they don't get a renderer.
+
+use super::Renderer;
+use crate::application::AppExit;
+use crate::application::builder::AppBuilder;
+use crate::application::events::Events;
+use crate::application::plugin::Plugin;
+use crate::application::resources::Resources;
+use crate::application::timestep::FixedTimestep;
+use crate::ecs::scene::Scene;
+
+use futures::executor;
+use winit::event::{ElementState, KeyboardInput, WindowEvent};
+
+use log::{trace, info, warn, error};
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Adds a `Renderer` resource, and draws the active scene with it.
+#[derive(Debug, Default)]
+pub struct RenderPlugin;
+
+impl Plugin for RenderPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let screenshot_key = app.get_config().screenshot_key;
+        let screenshot_directory = app.get_config().screenshot_directory.clone();
+        let hot_reload = app.get_config().hot_reload;
+
+        app.add_setup(move |resources, window| {
+            match window {
+                Some(window) => {
+                    let mut renderer = executor::block_on(Renderer::new(window))?;
+                    renderer.set_hot_reload(hot_reload);
+                    resources.insert(renderer);
+                },
+                None => warn!("There is no window to render to, so there won't be a renderer"),
+            }
+
+            Ok(())
+        })
+        .add_render_system(render)
+        .add_window_event_handler(move |resources, event| {
+            let renderer = match resources.get_mut::<Renderer>() {
+                Some(renderer) => renderer,
+                None => return false,
+            };
+
+            match event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } if screenshot_key == Some(*key) => {
+                    take_screenshot(renderer, &screenshot_directory);
+
+                    true
+                },
+
+                WindowEvent::Resized(physical_size) => {
+                    trace!("Window resized");
+                    renderer.resize(*physical_size);
+
+                    false
+                },
+
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    trace!("Window scale factor changed");
+                    renderer.resize(**new_inner_size);
+
+                    false
+                },
+
+                _ => false,
+            }
+        });
+    }
+}
+
+/// Draws the active scene, over any paused scenes that show under it.
+fn render(resources: &mut Resources, layers: &[Scene], scene: &mut Scene) {
+    let alpha = resources.get::<FixedTimestep>().map(FixedTimestep::alpha).unwrap_or(1.0);
+    let scenes: Vec<&Scene> = layers.iter().chain(std::iter::once(&*scene)).collect();
+
+    let renderer = match resources.get_mut::<Renderer>() {
+        Some(renderer) => renderer,
+        None => return,
+    };
+
+    let result = renderer.render_layers(&scenes, alpha);
+
+    match result {
+        Ok(_) => {}
+        // Reconfigure the surface if lost
+        Err(wgpu::SurfaceError::Lost) => renderer.resize(renderer.size),
+        // The system is out of memory, we should probably quit
+        Err(wgpu::SurfaceError::OutOfMemory) => {
+            error!("Out of memory while rendering, exiting");
+
+            if let Some(exit) = resources.get_mut::<Events<AppExit>>() {
+                exit.send(AppExit(1));
+            }
+        },
+        // All other errors (Outdated, Timeout) should be resolved by the next frame
+        Err(e) => error!("Failed to render frame: {:?}", e),
+    }
+}
+
+/// Saves the next frame into the screenshot directory, named after the current time.
+fn take_screenshot(renderer: &mut Renderer, directory: &Path) {
+    if let Err(e) = std::fs::create_dir_all(directory) {
+        error!("Failed to create screenshot directory {}: {}", directory.display(), e);
+
+        return;
+    }
+
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let path = directory.join(format!("screenshot_{}.png", millis));
+
+    info!("Saving screenshot to {}", path.display());
+    renderer.capture_frame(path);
+}
@@ -0,0 +1,80 @@
+//! Shader hot-reloading.
+//!
+//! Shaders loaded with `Renderer::load_shader_file` are watched for changes. At the start of every
+//! frame the renderer checks the watcher, recompiles whatever changed through the `ShaderBuilder`,
+//! and rebuilds the pipelines that use it. If the new source doesn't compile, the error is logged
+//! and the last good version is kept, so a typo won't bring down your application.
+
+use super::{Renderer, build_render_pipeline};
+use super::shaders::{ShaderBuilder, ShaderSourceType, ShaderStage};
+use crate::error::Error as GompError;
+
+use std::path::Path;
+
+use log::{debug, info, error};
+
+impl Renderer {
+    /// Compiles a WGSL shader from a file, swaps it in for the default shader of the given stage,
+    /// and watches the file for changes.
+    pub fn load_shader_file<P: AsRef<Path>>(&mut self, stage: ShaderStage, path: P) -> Result<(), GompError> {
+        let path = path.as_ref();
+
+        debug!("Loading {:?} shader from {}", stage, path.display());
+        self.compile_shader_file(stage, path)?;
+
+        self.shader_watcher.watch(path);
+        self.shader_files.insert(path.to_path_buf(), stage);
+
+        Ok(())
+    }
+
+    /// Sets if shaders are reloaded when their files change. They are unless it's turned off (the
+    /// render plugin follows `AppConfig::hot_reload`).
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+    }
+
+    /// Recompiles any watched shaders that changed on disk. Called at the start of every frame.
+    pub(crate) fn reload_shaders(&mut self) {
+        if !self.hot_reload {
+            return;
+        }
+
+        for path in self.shader_watcher.poll() {
+            let stage = match self.shader_files.get(&path) {
+                Some(stage) => *stage,
+                None => continue,
+            };
+
+            match self.compile_shader_file(stage, &path) {
+                Ok(()) => info!("Reloaded {:?} shader {}", stage, path.display()),
+                Err(e) => error!("Failed to reload shader, keeping the last good version: {}", e),
+            }
+        }
+    }
+
+    /// Compiles a shader file and, if that worked, rebuilds the pipelines with it. Nothing is
+    /// touched if anything fails.
+    fn compile_shader_file(&mut self, stage: ShaderStage, path: &Path) -> Result<(), GompError> {
+        let label = format!("{:?} shader ({})", stage, path.display());
+        let module = ShaderBuilder::new()
+            .with_label(&label)
+            .with_source_file(ShaderSourceType::Wgsl, path)?
+            .with_device(&self.device)
+            .compile()?;
+
+        let pipeline = match stage {
+            ShaderStage::Vertex => build_render_pipeline(&self.device, &module, &self.fragment_shader, self.config.format)?,
+            ShaderStage::Fragment => build_render_pipeline(&self.device, &self.vertex_shader, &module, self.config.format)?,
+        };
+
+        match stage {
+            ShaderStage::Vertex => self.vertex_shader = module,
+            ShaderStage::Fragment => self.fragment_shader = module,
+        }
+
+        self.render_pipeline = pipeline;
+
+        Ok(())
+    }
+}
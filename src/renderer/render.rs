@@ -3,22 +3,78 @@
 //! Actually does the rendering work. These functions are well documented, so you should be able to
 //! build an interface to this module with little effort. Plus, you can see the way the Application
 //! calls it for in-source examples.
+//!
+//! # Instancing
+//!
+//! Meshes are never drawn one at a time. Before the render pass starts, every mesh in the scene is
+//! grouped into a batch by its geometry and material, and each batch gets an instance buffer with
+//! one `Instance` (model matrix and colour) per mesh. Each batch is then a single `draw_indexed`.
+//!
+//! # Layers
+//!
+//! Several scenes can be drawn in one frame with `render_layers` (a paused scene under an overlay,
+//! say). Each scene is a layer, drawn bottom first: its meshes, then its text. Custom passes, the
+//! transition cover and debug shapes then go over every layer.
 
 use super::Renderer;
+use super::instance::Instance;
+use super::material::Material;
+use super::pass::PassContext;
+use super::cover::cover_vertices;
 use crate::ecs::{
     scene::Scene,
     component::{
         Component,
-        ComponentType
+        ComponentType,
+        mesh::Mesh,
+        transform::Transform,
     },
 };
 
-use wgpu::RenderPass;
-use log::debug;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A group of meshes that share geometry and a material, ready to be drawn in one call.
+struct Batch {
+    geometry: u64,
+    material: Material,
+    instances: wgpu::Buffer,
+    count: u32,
+}
+
+/// The meshes and text of a single scene.
+struct Layer {
+    batches: Vec<Batch>,
+
+    /// Which vertices in the text buffer belong to the scene.
+    text: Range<u32>,
+}
+
+/// Everything that gets drawn in a frame, uploaded and ready to go. Built before any render pass
+/// starts, as the buffers need to outlive the passes.
+pub(crate) struct Frame {
+    layers: Vec<Layer>,
+    text: Option<wgpu::Buffer>,
+    cover: Option<(wgpu::Buffer, f32)>,
+    #[cfg(feature = "debug-draw")]
+    debug: Option<(wgpu::Buffer, u32)>,
+}
 
 impl Renderer {
-    /// Actually render to a frame.
-    pub fn render(&mut self, scene: &Scene) -> Result<(), wgpu::SurfaceError> {
+    /// Actually render to a frame. `alpha` is how far we are between the last simulation step and
+    /// the next (0 to 1), and is used to blend transforms between the last two steps.
+    pub fn render(&mut self, scene: &Scene, alpha: f64) -> Result<(), wgpu::SurfaceError> {
+        self.render_layers(&[scene], alpha)
+    }
+
+    /// Renders several scenes on top of each other, bottom first. Custom passes are given the top
+    /// scene.
+    pub fn render_layers(&mut self, scenes: &[&Scene], alpha: f64) -> Result<(), wgpu::SurfaceError> {
+        // Pick up any shaders that changed on disk since the last frame
+        self.reload_shaders();
+
         // Get somewhere to render to
         let output = self.surface.get_current_texture()?;
 
@@ -30,12 +86,94 @@ impl Renderer {
             label: Some("Default render encoder"),
         });
 
+        let frame = self.prepare_frame(scenes, alpha);
+        self.encode_frame(&mut encoder, &view, &frame);
+
+        // Copy the frame out if we're taking a screenshot (or recording)
+        let capture = self.encode_capture(&mut encoder, &output.texture, &frame);
+
+        // Submit will accept anything that implements IntoIter
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        if let Some(capture) = capture {
+            self.read_capture(capture);
+        }
+
+        self.poll_captures(false);
+
+        // Debug shapes only last a single frame
+        self.debug.clear();
+
+        Ok(())
+    }
+
+    /// Uploads everything that needs drawing this frame.
+    pub(crate) fn prepare_frame(&mut self, scenes: &[&Scene], alpha: f64) -> Frame {
+        let (text_vertices, text_ends) = self.prepare_text(scenes, alpha);
+        let text = (!text_vertices.is_empty()).then(|| self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Text vertex buffer"),
+            contents: bytemuck::cast_slice(&text_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+
+        let mut layers = vec![];
+        let mut text_start = 0;
+
+        for (scene, text_end) in scenes.iter().zip(text_ends) {
+            layers.push(Layer {
+                batches: self.batch_scene_meshes(scene, alpha),
+                text: text_start..text_end,
+            });
+
+            text_start = text_end;
+        }
+
+        let cover = self.cover.as_ref().filter(|cover| cover.opacity > 0.0).map(|cover| (self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Cover vertex buffer"),
+            contents: bytemuck::cast_slice(&cover_vertices(cover)),
+            usage: wgpu::BufferUsages::VERTEX,
+        }), cover.opacity.min(1.0)));
+
+        let context = PassContext {
+            device: &self.device,
+            queue: &self.queue,
+            format: self.config.format,
+            size: self.size,
+        };
+
+        if let Some(scene) = scenes.last() {
+            for pass in &mut self.passes {
+                pass.prepare(&context, scene, alpha);
+            }
+        }
+
+        #[cfg(feature = "debug-draw")]
+        let debug_vertices = self.debug.get_vertices();
+        #[cfg(feature = "debug-draw")]
+        let debug = (self.debug.is_enabled() && !debug_vertices.is_empty()).then(|| (self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Debug drawing vertex buffer"),
+            contents: bytemuck::cast_slice(debug_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }), debug_vertices.len() as u32));
+
+        Frame {
+            layers,
+            text,
+            cover,
+            #[cfg(feature = "debug-draw")]
+            debug,
+        }
+    }
+
+    /// Records the render passes that draw a frame onto a view.
+    pub(crate) fn encode_frame(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, frame: &Frame) {
         {
             // Create a render pass
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Default render pass"),
                 color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -50,46 +188,154 @@ impl Renderer {
                 depth_stencil_attachment: None,
             });
 
-            /* let verticies: &[Vertex] = &[
-                Vertex::new([0.0, 0.5, 0.0], [1.0, 0.0, 0.0]),
-                Vertex::new([-0.5, -0.5, 0.0], [0.0, 1.0, 0.0]),
-                Vertex::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0]),
-            ];
-
-            let vertex_buffer = Box::leak(Box::new(self.device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor {
-                    label: Some("Vertex Buffer"),
-                    contents: bytemuck::cast_slice(verticies),
-                    usage: wgpu::BufferUsages::VERTEX,
+            for layer in &frame.layers {
+                for batch in &layer.batches {
+                    let geometry = &self.geometry[&batch.geometry];
+
+                    match batch.material {
+                        Material::Flat => render_pass.set_pipeline(&self.render_pipeline),
+                    }
+
+                    render_pass.set_vertex_buffer(0, geometry.vertex_buffer.slice(..));
+                    render_pass.set_vertex_buffer(1, batch.instances.slice(..));
+                    render_pass.set_index_buffer(geometry.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                    render_pass.draw_indexed(0..geometry.index_count, 0, 0..batch.count);
                 }
-            )));
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.draw(0..(verticies.len() as u32), 0..1); */
-        
-            self.render_scene_meshes(&mut render_pass, scene);
+                // Text goes over the layer's meshes
+                if let (Some(text_buffer), Some(glyph_texture)) = (&frame.text, &self.glyph_texture) {
+                    if !layer.text.is_empty() {
+                        render_pass.set_pipeline(&self.textured_pipeline);
+                        render_pass.set_bind_group(0, glyph_texture.get_bind_group(), &[]);
+                        render_pass.set_vertex_buffer(0, text_buffer.slice(..));
+                        render_pass.draw(layer.text.clone(), 0..1);
+                    }
+                }
+            }
         }
 
-        // Submit will accept anything that implements IntoIter
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        // Then anything plugins draw
+        let context = self.pass_context();
 
-        Ok(())
+        for pass in &self.passes {
+            pass.encode(&context, encoder, view);
+        }
+
+        // The cover hides everything but debug shapes
+        self.render_cover(encoder, view, frame);
+
+        // Debug shapes go over the top of everything else
+        #[cfg(feature = "debug-draw")]
+        self.render_debug_overlay(encoder, view, frame);
+    }
+
+    /// Draws the cover (if there is one) over the frame, blended by its opacity.
+    fn render_cover(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, frame: &Frame) {
+        let (vertex_buffer, opacity) = match &frame.cover {
+            Some(cover) => cover,
+            None => return,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Cover render pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        let opacity = *opacity as f64;
+
+        render_pass.set_pipeline(&self.cover_pipeline);
+        render_pass.set_blend_constant(wgpu::Color { r: opacity, g: opacity, b: opacity, a: opacity });
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..6, 0..1);
+    }
+
+    /// Draws the debug shapes collected this frame in an overlay pass.
+    #[cfg(feature = "debug-draw")]
+    fn render_debug_overlay(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, frame: &Frame) {
+        let (vertex_buffer, count) = match &frame.debug {
+            Some(debug) => debug,
+            None => return,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug overlay render pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // Keep what the main pass drew
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.debug_pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..*count, 0..1);
     }
 
-    fn render_scene_meshes(&mut self, pass: &mut RenderPass, scene: &Scene) {
+    /// Groups every mesh in the scene by geometry and material, uploading geometry and building
+    /// instance buffers along the way. Batches are in the order their first mesh is in the scene,
+    /// so (with no depth buffer) overlapping meshes are drawn the same way every frame.
+    fn batch_scene_meshes(&mut self, scene: &Scene, alpha: f64) -> Vec<Batch> {
+        let mut groups: Vec<((u64, Material), Vec<Instance>)> = vec![];
+        let mut indices: HashMap<(u64, Material), usize> = HashMap::new();
+
         // Get all components and find the meshes.
         for component in scene.get_components() {
-            if let ComponentType::Mesh = component.type_of() {
-                self.render_mesh(pass, component);
+            let component = component.borrow();
+
+            if component.type_of() != ComponentType::Mesh {
+                continue;
             }
+
+            // We know for sure that this is a Mesh here, so no need to worry.
+            let mesh = match component.as_any().downcast_ref::<Mesh>() {
+                Some(mesh) => mesh,
+                None => continue,
+            };
+
+            // Meshes without a transform sit at the origin.
+            let model = mesh.get_weak_parent().upgrade()
+                .and_then(|entity| entity.find_component(ComponentType::Transform))
+                .and_then(|transform| {
+                    let transform = transform.borrow();
+
+                    transform.as_any().downcast_ref::<Transform>().map(|t| t.interpolated_model_matrix(alpha))
+                })
+                .unwrap_or_else(cgmath::SquareMatrix::identity);
+
+            let geometry = mesh.get_geometry();
+            self.upload_geometry(geometry);
+
+            let key = (geometry.get_id(), mesh.get_material());
+            let index = *indices.entry(key).or_insert_with(|| {
+                groups.push((key, vec![]));
+                groups.len() - 1
+            });
+
+            groups[index].1.push(Instance::new(model, mesh.get_colour()));
         }
-    }
 
-    fn render_mesh(&mut self, pass: &mut RenderPass, mesh: &Box<dyn Component>) {
-        // We know for sure that mesh is a Mesh here, so no need to worry.
-        debug!("Rendering {}", mesh.get_parent().get_name());
+        groups.into_iter().map(|((geometry, material), instances)| Batch {
+            geometry,
+            material,
+            instances: self.device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Instance buffer"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            count: instances.len() as u32,
+        }).collect()
     }
 }
-
@@ -0,0 +1,4 @@
+//! # Internal shader!
+//! Used for the debug drawing overlay (and screen covers). Holds both the vertex (`vs_main`) and
+//! fragment (`fs_main`) stages. Clients should use this for reference only.
+pub const DEBUG_SOURCE: &str = include_str!("debug.wgsl");
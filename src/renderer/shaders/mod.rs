@@ -9,10 +9,16 @@
 //!
 //! # Panics
 //!
-//! This builder will only panic when compiling shaders. If there is an error during the
-//! compilation process, gomp *will not* catch it, but rather it will panic. It appears that
-//! wgpu does't support trying to compile a shader (e.g. catching the error in a more idiomatic
-//! way), but if you know of such a function, please submit a issue/PR.
+//! This builder shouldn't panic. wgpu panics when handed a shader that doesn't compile, so gomp
+//! parses and validates WGSL with naga (the same thing wgpu uses under the hood) before handing it
+//! over. Errors come back as `Error::WgslCompilationFailure`, with the pretty naga diagnostic
+//! inside.
+//!
+//! # Hot reloading
+//!
+//! Shaders can be loaded from a file with `with_source_file`. The renderer will watch shaders it
+//! loaded this way (see `Renderer::load_shader_file`), and recompile them when they change on
+//! disk. If the new version fails to compile, the last good version is kept.
 //!
 //! # Note
 //! The only shader types supported current are: WGSL. Everything else isn't supported by gomp. The
@@ -20,7 +26,7 @@
 //!
 //! # Examples
 //!
-//! ```
+//! ```ignore
 //! // Make sure the query your application's renderer for the device; this will be used later in
 //! // the example.
 //!
@@ -41,12 +47,16 @@
 
 pub mod vertex;
 pub mod fragment;
+pub mod debug;
+pub mod textured;
 
 use wgpu::ShaderModule;
 use wgpu::Device;
 
 use log::{info, warn};
 
+use std::path::{Path, PathBuf};
+
 use super::super::error::Error as GompError;
 
 /// Defines the language of a shader.
@@ -62,6 +72,16 @@ pub enum ShaderSourceType {
     Hlsl,
 }
 
+/// The stage of the pipeline a shader belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    /// A vertex shader.
+    Vertex,
+
+    /// A fragment shader.
+    Fragment,
+}
+
 /// Helps to build a shader.
 #[derive(Debug)]
 pub struct ShaderBuilder<'a> {
@@ -82,6 +102,9 @@ pub struct ShaderBuilder<'a> {
 
     /// The device to compile the shader under.
     device: Option<&'a Device>,
+
+    /// The file the source was read from, if any.
+    path: Option<PathBuf>,
 }
 
 impl<'a> ShaderBuilder<'a> {
@@ -93,6 +116,7 @@ impl<'a> ShaderBuilder<'a> {
             entry: "main".to_owned(),
             label: "".to_owned(),
             device: None,
+            path: None,
         }
     }
 
@@ -105,6 +129,18 @@ impl<'a> ShaderBuilder<'a> {
         }
     }
 
+    /// Reads the source of the shader from a file. Either this or `with_source` is required.
+    pub fn with_source_file<P: AsRef<Path>>(self, source_type: ShaderSourceType, path: P) -> Result<Self, GompError> {
+        let source = std::fs::read_to_string(path.as_ref())?;
+
+        Ok(Self {
+            source,
+            source_type,
+            path: Some(path.as_ref().to_path_buf()),
+            .. self
+        })
+    }
+
     /// Gives the shader an entry point. Not used in
     /// compilation, but rather to be put into the
     /// builder to be used later in client code.
@@ -124,7 +160,7 @@ impl<'a> ShaderBuilder<'a> {
         }
     }
 
-    /// 
+    /// The device to compile the shader on. Required.
     pub fn with_device(self, device: &'a Device) -> Self {
         Self {
             device: Some(device),
@@ -133,6 +169,12 @@ impl<'a> ShaderBuilder<'a> {
     }
 }
 
+impl<'a> Default for ShaderBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> ShaderBuilder<'a> {
     pub fn compile(self) -> Result<ShaderModule, GompError> {
         match self.source_type {
@@ -155,6 +197,8 @@ impl<'a> ShaderBuilder<'a> {
             warn!("No label given for new shader (before compilation). Consider giving it one to ease in debugging");
         }
 
+        self.validate()?;
+
         let shader = self.device.expect("unreachable panic on unwrapping of device reference in ShaderBuilder")
             .create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: Some(&self.label),
@@ -165,3 +209,26 @@ impl<'a> ShaderBuilder<'a> {
     }
 }
 
+impl<'a> ShaderBuilder<'a> {
+    /// Gets the file the source was read from, if it was read from one.
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Parses and validates the source with naga, so we get an error rather than a wgpu panic.
+    fn validate(&self) -> Result<(), GompError> {
+        let name = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => self.label.clone(),
+        };
+
+        let module = naga::front::wgsl::parse_str(&self.source)
+            .map_err(|e| GompError::WgslCompilationFailure(format!("{}\n{}", name, e.emit_to_string(&self.source))))?;
+
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+            .validate(&module)
+            .map_err(|e| GompError::WgslCompilationFailure(format!("{}: {}", name, e)))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,4 @@
+//! # Internal shader!
+//! Used for the textured pipeline (text, mostly). Holds both the vertex (`vs_main`) and fragment
+//! (`fs_main`) stages. Clients should use this for reference only.
+pub const TEXTURED_SOURCE: &str = include_str!("textured.wgsl");
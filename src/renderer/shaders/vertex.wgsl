@@ -3,6 +3,14 @@ struct VertexInput {
     [[location(1)]] color: vec3<f32>;
 };
 
+struct InstanceInput {
+    [[location(2)]] model_0: vec4<f32>;
+    [[location(3)]] model_1: vec4<f32>;
+    [[location(4)]] model_2: vec4<f32>;
+    [[location(5)]] model_3: vec4<f32>;
+    [[location(6)]] color: vec4<f32>;
+};
+
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec3<f32>;
@@ -11,10 +19,17 @@ struct VertexOutput {
 [[stage(vertex)]]
 fn main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+
     var out: VertexOutput;
-    out.color = model.color;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    out.color = model.color * instance.color.rgb;
+    out.clip_position = model_matrix * vec4<f32>(model.position, 1.0);
     return out;
 }
-
@@ -0,0 +1,241 @@
+use super::font::Font;
+
+use ab_glyph::{Font as _, GlyphId, PxScale, point};
+use image::{Rgba, RgbaImage};
+use log::warn;
+
+use std::collections::HashMap;
+
+/// The width and height of the atlas, in pixels.
+pub const ATLAS_SIZE: u32 = 1024;
+
+/// Empty space left around every glyph, so linear filtering doesn't bleed neighbours in.
+const PADDING: u32 = 1;
+
+/// Identifies a rasterised glyph. The same glyph at a different size is a different entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: u64,
+    glyph: u16,
+    size: u32,
+}
+
+/// Where a glyph lives in the atlas, and how to place it.
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasGlyph {
+    /// The top left of the glyph in the atlas, in UV coordinates.
+    pub uv_min: [f32; 2],
+
+    /// The bottom right of the glyph in the atlas, in UV coordinates.
+    pub uv_max: [f32; 2],
+
+    /// The offset of the top left of the glyph from its origin (on the baseline), in pixels.
+    pub offset: [f32; 2],
+
+    /// The size of the glyph, in pixels.
+    pub size: [f32; 2],
+}
+
+/// Returned when the atlas has no room left for a glyph.
+#[derive(Debug)]
+pub struct AtlasFull;
+
+/// Glyphs rasterised on the CPU, packed into a single image that is uploaded as a texture.
+///
+/// Glyphs are packed into rows (shelves), left to right, top to bottom. Coverage is stored in the
+/// alpha channel of white pixels, so the textured pipeline can tint glyphs with a colour.
+#[derive(Debug)]
+pub struct GlyphAtlas {
+    image: RgbaImage,
+
+    /// Every glyph in the atlas. `None` for glyphs with nothing to draw (like spaces), or that are
+    /// too big to ever fit.
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+
+    /// Where the next glyph goes.
+    cursor: (u32, u32),
+
+    /// The height of the tallest glyph in the current row.
+    row_height: u32,
+
+    /// Has the image changed since it was last uploaded.
+    dirty: bool,
+}
+
+impl GlyphAtlas {
+    /// Creates a new, empty atlas.
+    pub fn new() -> Self {
+        Self {
+            image: RgbaImage::from_pixel(ATLAS_SIZE, ATLAS_SIZE, Rgba([255, 255, 255, 0])),
+            glyphs: HashMap::new(),
+            cursor: (PADDING, PADDING),
+            row_height: 0,
+            dirty: true,
+        }
+    }
+
+    /// Gets a glyph, rasterising it into the atlas if it isn't already there. Returns `Ok(None)`
+    /// for glyphs with nothing to draw, or that are too big for the atlas.
+    pub fn get_or_insert(&mut self, font: &Font, glyph: GlyphId, size: f32) -> Result<Option<AtlasGlyph>, AtlasFull> {
+        let key = GlyphKey {
+            font: font.get_id(),
+            glyph: glyph.0,
+            size: size.round() as u32,
+        };
+
+        if let Some(entry) = self.glyphs.get(&key) {
+            return Ok(*entry);
+        }
+
+        let outlined = match font.inner().outline_glyph(glyph.with_scale_and_position(PxScale::from(key.size as f32), point(0.0, 0.0))) {
+            Some(outlined) => outlined,
+            None => {
+                self.glyphs.insert(key, None);
+
+                return Ok(None);
+            }
+        };
+
+        let bounds = outlined.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+
+        let (x, y) = match self.reserve(width, height)? {
+            Some(position) => position,
+            None => {
+                warn!("Glyph {} at {}px is too big for the glyph atlas, so won't be drawn", glyph.0, key.size);
+                self.glyphs.insert(key, None);
+
+                return Ok(None);
+            }
+        };
+
+        let image = &mut self.image;
+        outlined.draw(|gx, gy, coverage| {
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+
+            image.put_pixel(x + gx, y + gy, Rgba([255, 255, 255, alpha]));
+        });
+
+        self.dirty = true;
+
+        let entry = AtlasGlyph {
+            uv_min: [x as f32 / ATLAS_SIZE as f32, y as f32 / ATLAS_SIZE as f32],
+            uv_max: [(x + width) as f32 / ATLAS_SIZE as f32, (y + height) as f32 / ATLAS_SIZE as f32],
+            offset: [bounds.min.x, bounds.min.y],
+            size: [width as f32, height as f32],
+        };
+
+        self.glyphs.insert(key, Some(entry));
+
+        Ok(Some(entry))
+    }
+
+    /// Throws away every glyph, making room for new ones. Glyphs that weren't drawn (so don't take
+    /// up any room) are remembered.
+    pub fn clear(&mut self) {
+        let mut glyphs = std::mem::take(&mut self.glyphs);
+        glyphs.retain(|_, entry| entry.is_none());
+
+        *self = Self {
+            glyphs,
+            .. Self::new()
+        };
+    }
+
+    /// Finds room for a glyph, and moves the cursor past it. Returns `Ok(None)` if it's too big to
+    /// fit even in an empty atlas.
+    fn reserve(&mut self, width: u32, height: u32) -> Result<Option<(u32, u32)>, AtlasFull> {
+        if width + PADDING * 2 > ATLAS_SIZE || height + PADDING * 2 > ATLAS_SIZE {
+            return Ok(None);
+        }
+
+        // Move onto a new row if this one is full
+        if self.cursor.0 + width + PADDING > ATLAS_SIZE {
+            self.cursor = (PADDING, self.cursor.1 + self.row_height + PADDING);
+            self.row_height = 0;
+        }
+
+        if self.cursor.0 + width + PADDING > ATLAS_SIZE || self.cursor.1 + height + PADDING > ATLAS_SIZE {
+            return Err(AtlasFull);
+        }
+
+        let position = self.cursor;
+        self.cursor.0 += width + PADDING;
+        self.row_height = self.row_height.max(height);
+
+        Ok(Some(position))
+    }
+
+    /// Gets the atlas image.
+    pub fn get_image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    /// Has the image changed since it was last uploaded.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Marks the image as uploaded.
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs_are_placed_in_rows() {
+        let mut atlas = GlyphAtlas::new();
+
+        assert_eq!(atlas.reserve(10, 20).unwrap(), Some((PADDING, PADDING)));
+        assert_eq!(atlas.reserve(10, 5).unwrap(), Some((PADDING * 2 + 10, PADDING)));
+
+        // Too wide for what's left of the row, so it goes under the tallest glyph so far
+        assert_eq!(atlas.reserve(ATLAS_SIZE - PADDING * 2, 5).unwrap(), Some((PADDING, PADDING * 2 + 20)));
+    }
+
+    #[test]
+    fn glyphs_too_big_for_the_atlas_are_refused() {
+        let mut atlas = GlyphAtlas::new();
+
+        assert_eq!(atlas.reserve(ATLAS_SIZE, 10).unwrap(), None);
+        assert_eq!(atlas.reserve(10, ATLAS_SIZE - PADDING).unwrap(), None);
+
+        // The biggest glyph that fits does
+        assert_eq!(atlas.reserve(ATLAS_SIZE - PADDING * 2, ATLAS_SIZE - PADDING * 2).unwrap(), Some((PADDING, PADDING)));
+    }
+
+    #[test]
+    fn a_full_atlas_says_so() {
+        let mut atlas = GlyphAtlas::new();
+        let half = ATLAS_SIZE / 2;
+
+        assert!(atlas.reserve(10, half).unwrap().is_some());
+        assert!(atlas.reserve(ATLAS_SIZE - PADDING * 2, half).is_err());
+    }
+
+    #[test]
+    fn clearing_remembers_glyphs_that_were_not_drawn() {
+        let mut atlas = GlyphAtlas::new();
+        let key = |glyph| GlyphKey { font: 0, glyph, size: 12 };
+        let drawn = AtlasGlyph { uv_min: [0.0; 2], uv_max: [0.0; 2], offset: [0.0; 2], size: [0.0; 2] };
+
+        atlas.glyphs.insert(key(0), None);
+        atlas.glyphs.insert(key(1), Some(drawn));
+        atlas.reserve(10, 10).unwrap();
+        atlas.clear();
+
+        assert_eq!(atlas.glyphs.len(), 1);
+        assert!(atlas.glyphs.contains_key(&key(0)));
+        assert_eq!(atlas.cursor, (PADDING, PADDING));
+    }
+}
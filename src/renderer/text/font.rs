@@ -0,0 +1,44 @@
+use crate::error::Error as GompError;
+
+use ab_glyph::FontArc;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Used to hand out unique font IDs.
+static NEXT_FONT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A TrueType (TTF) or OpenType (OTF) font. Cheap to clone, as the font data is shared.
+#[derive(Debug, Clone)]
+pub struct Font {
+    /// Used to tell fonts apart in the glyph atlas.
+    id: u64,
+    inner: FontArc,
+}
+
+impl Font {
+    /// Loads a font from the raw bytes of a TTF or OTF file.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, GompError> {
+        let inner = FontArc::try_from_vec(bytes).map_err(|_| GompError::InvalidFont)?;
+
+        Ok(Self {
+            id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed),
+            inner,
+        })
+    }
+
+    /// Loads a font from a TTF or OTF file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, GompError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    /// Gets the unique ID of the font.
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    /// Gets the underlying ab_glyph font.
+    pub(crate) fn inner(&self) -> &FontArc {
+        &self.inner
+    }
+}
@@ -0,0 +1,277 @@
+//! Text rendering.
+//!
+//! Fonts are loaded from TTF/OTF bytes, glyphs are rasterised on the CPU into a `GlyphAtlas`
+//! (which is uploaded as a texture whenever it changes), and every `Text` component in the scene
+//! becomes a bunch of glyph quads drawn with the textured pipeline.
+//!
+//! Text sizes, and wrapping widths, are in pixels so text stays crisp no matter the window size.
+//! The text is positioned by the `Transform` of its entity: the top of the first line sits at the
+//! transform, and the alignment decides whether the lines start, are centred on, or end at it.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let font = Font::from_file("assets/fonts/score.ttf")?;
+//!
+//! text.set_font(Some(font));
+//! text.set_text("1 - 0");
+//! text.set_size(48.0);
+//! text.set_alignment(Alignment::Centre);
+//! ```
+
+pub mod font;
+pub mod atlas;
+
+use super::Renderer;
+use super::vertex::TexturedVertex;
+use atlas::AtlasFull;
+use font::Font;
+use crate::ecs::{
+    scene::Scene,
+    component::{
+        Component,
+        ComponentType,
+        text::Text,
+        transform::Transform,
+    },
+};
+
+use ab_glyph::{Font as _, GlyphId, ScaleFont};
+use log::warn;
+
+/// How the lines of a block of text are aligned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    /// Lines start at the position of the text.
+    #[default]
+    Left,
+
+    /// Lines are centred on the position of the text.
+    Centre,
+
+    /// Lines end at the position of the text.
+    Right,
+}
+
+/// A glyph that has been laid out, relative to the top of the text block (in pixels, y down).
+#[derive(Debug, Clone, Copy)]
+pub struct LaidOutGlyph {
+    pub id: GlyphId,
+    pub origin: [f32; 2],
+}
+
+/// Lays out a block of text. Lines are broken at newlines, and (if there is a wrapping width)
+/// between words that would otherwise overflow it.
+pub fn layout(font: &Font, text: &str, size: f32, alignment: Alignment, wrap_width: Option<f32>) -> Vec<LaidOutGlyph> {
+    let font = font.inner().as_scaled(size);
+    let line_height = font.height() + font.line_gap();
+
+    // Split into lines of words first, so we know the width of each line before placing it.
+    let mut lines: Vec<(Vec<(GlyphId, f32)>, f32)> = vec![];
+
+    for paragraph in text.split('\n') {
+        let mut line: Vec<(GlyphId, f32)> = vec![];
+        let mut width = 0.0;
+
+        for (i, word) in paragraph.split(' ').enumerate() {
+            let mut glyphs = vec![];
+            let mut word_width = 0.0;
+            let mut previous: Option<GlyphId> = None;
+
+            // Words after the first are preceded by the space they were split on.
+            let characters = if i > 0 { Some(' ') } else { None }.into_iter().chain(word.chars());
+
+            for c in characters {
+                let id = font.glyph_id(c);
+
+                if let Some(previous) = previous.or_else(|| line.last().map(|(id, _)| *id)) {
+                    word_width += font.kern(previous, id);
+                }
+
+                glyphs.push((id, width + word_width));
+                word_width += font.h_advance(id);
+                previous = Some(id);
+            }
+
+            let overflows = wrap_width.map(|wrap| width + word_width > wrap).unwrap_or(false);
+
+            if overflows && !line.is_empty() {
+                lines.push((std::mem::take(&mut line), width));
+
+                // Lay the word out again at the start of a fresh line, minus its leading space.
+                width = 0.0;
+                let mut previous: Option<GlyphId> = None;
+
+                for c in word.chars() {
+                    let id = font.glyph_id(c);
+
+                    if let Some(previous) = previous {
+                        width += font.kern(previous, id);
+                    }
+
+                    line.push((id, width));
+                    width += font.h_advance(id);
+                    previous = Some(id);
+                }
+            } else {
+                line.extend(glyphs);
+                width += word_width;
+            }
+        }
+
+        lines.push((line, width));
+    }
+
+    let mut laid_out = vec![];
+
+    for (i, (line, width)) in lines.into_iter().enumerate() {
+        let start = match alignment {
+            Alignment::Left => 0.0,
+            Alignment::Centre => -width / 2.0,
+            Alignment::Right => -width,
+        };
+
+        let baseline = font.ascent() + line_height * i as f32;
+
+        laid_out.extend(line.into_iter().map(|(id, x)| LaidOutGlyph {
+            id,
+            origin: [start + x, baseline],
+        }));
+    }
+
+    laid_out
+}
+
+impl Renderer {
+    /// Sets the font used to label debug text markers. Without one, markers are drawn unlabelled.
+    pub fn set_debug_font(&mut self, font: Option<Font>) {
+        self.debug_font = font;
+    }
+
+    /// Builds the glyph quads for every `Text` in each scene (and every debug text marker, with
+    /// the last scene), and uploads the glyph atlas if it changed. Returns the vertices, six per
+    /// glyph, and where each scene's vertices end.
+    pub(crate) fn prepare_text(&mut self, scenes: &[&Scene], alpha: f64) -> (Vec<TexturedVertex>, Vec<u32>) {
+        let mut vertices = vec![];
+        let mut ends = vec![];
+
+        // If the atlas fills up part way through, empty it and start over. Everything we need
+        // this frame should fit in an empty atlas, so only try that once.
+        for attempt in 0..2 {
+            vertices.clear();
+            ends.clear();
+
+            match self.build_text_vertices(scenes, alpha, &mut vertices, &mut ends) {
+                Ok(()) => break,
+                Err(AtlasFull) if attempt == 0 => {
+                    warn!("Glyph atlas is full, clearing it");
+                    self.glyph_atlas.clear();
+                },
+                Err(AtlasFull) => warn!("Too much text to fit in the glyph atlas, some won't be drawn"),
+            }
+        }
+
+        if self.glyph_atlas.is_dirty() {
+            match &self.glyph_texture {
+                Some(texture) => texture.write(&self.queue, self.glyph_atlas.get_image()),
+                None => self.glyph_texture = Some(self.create_texture("Glyph atlas", self.glyph_atlas.get_image())),
+            }
+
+            self.glyph_atlas.mark_clean();
+        }
+
+        // Make sure every scene has an end, even if we gave up part way through
+        ends.resize(scenes.len(), vertices.len() as u32);
+
+        (vertices, ends)
+    }
+
+    fn build_text_vertices(&mut self, scenes: &[&Scene], alpha: f64, vertices: &mut Vec<TexturedVertex>, ends: &mut Vec<u32>) -> Result<(), AtlasFull> {
+        for scene in scenes {
+            self.build_scene_text_vertices(scene, alpha, vertices)?;
+            ends.push(vertices.len() as u32);
+        }
+
+        #[cfg(feature = "debug-draw")]
+        if let Some(font) = self.debug_font.clone() {
+            for marker in self.debug.get_text_markers().clone() {
+                let glyphs = layout(&font, &marker.text, 16.0, Alignment::Left, None);
+                let [r, g, b] = marker.colour;
+
+                self.push_glyphs(&font, 16.0, &glyphs, marker.position, [r, g, b, 1.0], vertices)?;
+            }
+
+            if let Some(end) = ends.last_mut() {
+                *end = vertices.len() as u32;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_scene_text_vertices(&mut self, scene: &Scene, alpha: f64, vertices: &mut Vec<TexturedVertex>) -> Result<(), AtlasFull> {
+        for component in scene.get_components() {
+            let component = component.borrow();
+
+            if component.type_of() != ComponentType::Text {
+                continue;
+            }
+
+            let text = match component.as_any().downcast_ref::<Text>() {
+                Some(text) => text,
+                None => continue,
+            };
+
+            let font = match text.get_font() {
+                Some(font) => font,
+                None => continue,
+            };
+
+            let position = text.get_weak_parent().upgrade()
+                .and_then(|entity| entity.find_component(ComponentType::Transform))
+                .and_then(|transform| {
+                    let transform = transform.borrow();
+
+                    transform.as_any().downcast_ref::<Transform>().map(|t| t.interpolated_position(alpha))
+                })
+                .map(|[x, y, _]| [x as f32, y as f32])
+                .unwrap_or([0.0, 0.0]);
+
+            let glyphs = layout(font, text.get_text(), text.get_size(), text.get_alignment(), text.get_wrap_width());
+            self.push_glyphs(font, text.get_size(), &glyphs, position, text.get_colour(), vertices)?;
+        }
+
+        Ok(())
+    }
+
+    /// Turns laid out glyphs into quads, positioned (in clip space) at `position`.
+    fn push_glyphs(&mut self, font: &Font, size: f32, glyphs: &[LaidOutGlyph], position: [f32; 2], colour: [f32; 4], vertices: &mut Vec<TexturedVertex>) -> Result<(), AtlasFull> {
+        // Pixels to clip space. Clip space is two units across, and y points up.
+        let (sx, sy) = (2.0 / self.size.width as f32, 2.0 / self.size.height as f32);
+
+        for glyph in glyphs {
+            let atlas_glyph = match self.glyph_atlas.get_or_insert(font, glyph.id, size)? {
+                Some(atlas_glyph) => atlas_glyph,
+                None => continue,
+            };
+
+            let left = position[0] + (glyph.origin[0] + atlas_glyph.offset[0]).round() * sx;
+            let top = position[1] - (glyph.origin[1] + atlas_glyph.offset[1]).round() * sy;
+            let right = left + atlas_glyph.size[0] * sx;
+            let bottom = top - atlas_glyph.size[1] * sy;
+
+            let [u0, v0] = atlas_glyph.uv_min;
+            let [u1, v1] = atlas_glyph.uv_max;
+
+            // Two counter-clockwise triangles.
+            let top_left = TexturedVertex::new([left, top, 0.0], [u0, v0], colour);
+            let bottom_left = TexturedVertex::new([left, bottom, 0.0], [u0, v1], colour);
+            let bottom_right = TexturedVertex::new([right, bottom, 0.0], [u1, v1], colour);
+            let top_right = TexturedVertex::new([right, top, 0.0], [u1, v0], colour);
+
+            vertices.extend_from_slice(&[top_left, bottom_left, bottom_right, top_left, bottom_right, top_right]);
+        }
+
+        Ok(())
+    }
+}
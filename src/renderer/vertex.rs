@@ -1,5 +1,5 @@
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vertex {
     position: [f32; 3],
     colour: [f32; 3],
@@ -14,6 +14,16 @@ impl Vertex {
             colour,
         }
     }
+
+    /// Gets the position.
+    pub fn get_position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    /// Gets the colour.
+    pub fn get_colour(&self) -> [f32; 3] {
+        self.colour
+    }
 }
 
 /// Static implementations
@@ -49,3 +59,58 @@ unsafe impl bytemuck::Zeroable for Vertex {}
 /// Implementation to enable bytemuck.
 unsafe impl bytemuck::Pod for Vertex {}
 
+
+/// A vertex for the textured pipeline. Used for glyphs (and anything else with a texture).
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct TexturedVertex {
+    position: [f32; 3],
+    uv: [f32; 2],
+    colour: [f32; 4],
+}
+
+/// Non-static implementations
+impl TexturedVertex {
+    /// Construct a new textured vertex.
+    pub fn new(position: [f32; 3], uv: [f32; 2], colour: [f32; 4]) -> Self {
+        Self {
+            position,
+            uv,
+            colour,
+        }
+    }
+}
+
+/// Static implementations
+impl TexturedVertex {
+    /// Descibes how a buffer is layed out in memory.
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TexturedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ]
+        }
+    }
+}
+
+/// Implementation to enable bytemuck.
+unsafe impl bytemuck::Zeroable for TexturedVertex {}
+
+/// Implementation to enable bytemuck.
+unsafe impl bytemuck::Pod for TexturedVertex {}
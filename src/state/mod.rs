@@ -1,5 +1,7 @@
 use winit::event::WindowEvent;
 
+use crate::time::Time;
+
 #[derive(Debug)]
 pub struct State {
 
@@ -19,8 +21,13 @@ impl State {
     }
 
     /// Run scripts, call the physics, etc...
-    pub fn update(&mut self) {
+    pub fn update(&mut self, _time: &Time) {
     
     }
 }
 
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,277 @@
+//! Keeping track of time.
+//!
+//! The application owns a single `Time` (a resource added by the `TimePlugin`), updates it once per
+//! frame and once per simulation step, and hands it to everything that gets updated
+//! (`State::update`, `Component::on_update`). Systems can read it from the resources.
+//!
+//! # Delta
+//!
+//! `delta` is context sensitive, much like other engines: during a simulation step it is the
+//! fixed step length, and outside of one it is the time the last frame took. So `position +=
+//! velocity * time.delta_seconds()` does the right thing wherever it's written.
+//!
+//! # Time scale and pausing
+//!
+//! The time scale stretches game time (0.5 is half speed slow-motion, 2.0 is double speed). It
+//! doesn't change the length of a simulation step, just how many of them happen per second, so
+//! physics behaves exactly the same in slow-motion. Pausing stops game time altogether, so no
+//! simulation steps happen until it is resumed. The `unscaled_*` variants ignore both, which is
+//! what you want for menus and the like.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! fn on_update(&mut self, time: &Time) {
+//!     self.x += self.speed * time.delta_seconds();
+//!
+//!     if self.respawn.update(time).just_finished() {
+//!         self.respawn_ball();
+//!     }
+//! }
+//! ```
+
+pub mod timer;
+pub mod plugin;
+
+use std::time::Duration;
+
+/// The fastest game time can be made to pass.
+pub const MAX_TIME_SCALE: f64 = 1000.0;
+
+/// The engine's clock.
+#[derive(Debug)]
+pub struct Time {
+    /// How long the last frame took, scaled.
+    frame_delta: Duration,
+
+    /// How long the last frame took, in real time.
+    unscaled_frame_delta: Duration,
+
+    /// The length of a simulation step.
+    fixed_delta: Duration,
+
+    /// Game time since the application started (scaled, and stopped while paused).
+    elapsed: Duration,
+
+    /// Real time since the application started.
+    unscaled_elapsed: Duration,
+
+    /// How many frames have been rendered.
+    frame_count: u64,
+
+    /// How many simulation steps have run.
+    tick_count: u64,
+
+    /// How fast game time passes compared to real time.
+    time_scale: f64,
+
+    /// Is game time stopped.
+    paused: bool,
+
+    /// Are we in the middle of a simulation step. Decides what `delta` means.
+    in_fixed_step: bool,
+}
+
+impl Time {
+    /// Creates a new clock, with simulation steps `fixed_delta` long.
+    pub fn new(fixed_delta: Duration) -> Self {
+        Self {
+            frame_delta: Duration::ZERO,
+            unscaled_frame_delta: Duration::ZERO,
+            fixed_delta,
+            elapsed: Duration::ZERO,
+            unscaled_elapsed: Duration::ZERO,
+            frame_count: 0,
+            tick_count: 0,
+            time_scale: 1.0,
+            paused: false,
+            in_fixed_step: false,
+        }
+    }
+
+    /// The time step of whatever is currently happening: the fixed step length during a simulation
+    /// step, or the (scaled) length of the last frame otherwise.
+    pub fn delta(&self) -> Duration {
+        if self.in_fixed_step {
+            self.fixed_delta
+        } else {
+            self.frame_delta
+        }
+    }
+
+    /// `delta`, in seconds.
+    pub fn delta_seconds(&self) -> f64 {
+        self.delta().as_secs_f64()
+    }
+
+    /// How long the last frame took, scaled by the time scale (and zero while paused).
+    pub fn frame_delta(&self) -> Duration {
+        self.frame_delta
+    }
+
+    /// How long the last frame took, in real time.
+    pub fn unscaled_frame_delta(&self) -> Duration {
+        self.unscaled_frame_delta
+    }
+
+    /// The length of a simulation step.
+    pub fn fixed_delta(&self) -> Duration {
+        self.fixed_delta
+    }
+
+    /// Game time since the application started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// `elapsed`, in seconds.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    /// Real time since the application started.
+    pub fn unscaled_elapsed(&self) -> Duration {
+        self.unscaled_elapsed
+    }
+
+    /// How many frames have been rendered.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// How many simulation steps have run.
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// Gets the time scale.
+    pub fn get_time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Sets the time scale. 1.0 is normal speed, 0.5 is half speed, and so on. Negative (and NaN)
+    /// scales are treated as 0, and anything over `MAX_TIME_SCALE` (infinity included) as that.
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = if time_scale.is_nan() {
+            0.0
+        } else {
+            time_scale.clamp(0.0, MAX_TIME_SCALE)
+        };
+    }
+
+    /// Stops game time.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Starts game time again.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Is game time stopped.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Is this in the middle of a simulation step.
+    pub fn is_fixed_step(&self) -> bool {
+        self.in_fixed_step
+    }
+
+    /// Starts a new frame that took `unscaled` real time. Returns the scaled (game) time that
+    /// passed, which is what should be fed to the fixed timestep.
+    pub(crate) fn begin_frame(&mut self, unscaled: Duration) -> Duration {
+        let scaled = if self.paused {
+            Duration::ZERO
+        } else {
+            // Saturates, rather than panicking, on a ridiculously long frame
+            Duration::try_from_secs_f64(unscaled.as_secs_f64() * self.time_scale).unwrap_or(Duration::MAX)
+        };
+
+        self.unscaled_frame_delta = unscaled;
+        self.frame_delta = scaled;
+        self.unscaled_elapsed = self.unscaled_elapsed.saturating_add(unscaled);
+        self.elapsed = self.elapsed.saturating_add(scaled);
+        self.frame_count += 1;
+
+        scaled
+    }
+
+    /// Marks the start of a simulation step.
+    pub(crate) fn begin_fixed_step(&mut self) {
+        self.in_fixed_step = true;
+    }
+
+    /// Marks the end of a simulation step.
+    pub(crate) fn end_fixed_step(&mut self) {
+        self.in_fixed_step = false;
+        self.tick_count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_scaled() {
+        let mut time = Time::new(Duration::from_millis(10));
+        time.set_time_scale(0.5);
+
+        assert_eq!(time.begin_frame(Duration::from_millis(100)), Duration::from_millis(50));
+        assert_eq!(time.frame_delta(), Duration::from_millis(50));
+        assert_eq!(time.unscaled_frame_delta(), Duration::from_millis(100));
+        assert_eq!(time.elapsed(), Duration::from_millis(50));
+        assert_eq!(time.unscaled_elapsed(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn silly_time_scales_are_clamped() {
+        let mut time = Time::new(Duration::from_millis(10));
+
+        time.set_time_scale(-2.0);
+        assert_eq!(time.get_time_scale(), 0.0);
+
+        time.set_time_scale(f64::NAN);
+        assert_eq!(time.get_time_scale(), 0.0);
+
+        time.set_time_scale(f64::INFINITY);
+        assert_eq!(time.get_time_scale(), MAX_TIME_SCALE);
+    }
+
+    #[test]
+    fn huge_frames_saturate() {
+        let mut time = Time::new(Duration::from_millis(10));
+        time.set_time_scale(MAX_TIME_SCALE);
+
+        assert_eq!(time.begin_frame(Duration::MAX), Duration::MAX);
+        assert_eq!(time.begin_frame(Duration::MAX), Duration::MAX);
+        assert_eq!(time.elapsed(), Duration::MAX);
+    }
+
+    #[test]
+    fn paused_frames_take_no_game_time() {
+        let mut time = Time::new(Duration::from_millis(10));
+        time.pause();
+
+        assert_eq!(time.begin_frame(Duration::from_millis(100)), Duration::ZERO);
+        assert_eq!(time.elapsed(), Duration::ZERO);
+        assert_eq!(time.unscaled_elapsed(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn delta_is_the_step_during_a_step() {
+        let mut time = Time::new(Duration::from_millis(10));
+        time.begin_frame(Duration::from_millis(16));
+
+        assert_eq!(time.delta(), Duration::from_millis(16));
+
+        time.begin_fixed_step();
+        assert_eq!(time.delta(), Duration::from_millis(10));
+        time.end_fixed_step();
+
+        assert_eq!(time.delta(), Duration::from_millis(16));
+        assert_eq!(time.tick_count(), 1);
+    }
+}
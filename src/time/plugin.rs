@@ -0,0 +1,23 @@
+//! The time plugin.
+
+use super::Time;
+use crate::application::builder::AppBuilder;
+use crate::application::plugin::Plugin;
+use crate::application::timestep::FixedTimestep;
+
+use std::time::Duration;
+
+/// Adds the `Time` and `FixedTimestep` resources, set up from the application config. The
+/// application steps them every frame.
+#[derive(Debug, Default)]
+pub struct TimePlugin;
+
+impl Plugin for TimePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let tick_rate = app.get_config().tick_rate;
+        let max_catch_up_steps = app.get_config().max_catch_up_steps;
+
+        app.insert_resource(Time::new(Duration::from_secs_f64(1.0 / tick_rate.max(1) as f64)))
+            .insert_resource(FixedTimestep::new(tick_rate, max_catch_up_steps));
+    }
+}
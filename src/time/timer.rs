@@ -0,0 +1,377 @@
+//! Timers and stopwatches, driven by `Time`.
+//!
+//! Both are updated with `update(&time)` (which uses `Time::delta`, so they work the same in a
+//! simulation step or outside of one), or with `tick(delta)` for a custom amount of time. Neither
+//! advances while game time is paused.
+
+use super::Time;
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// What a timer does once it finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    /// Finish once, and stay finished.
+    Once,
+
+    /// Start over every time it finishes.
+    Repeating,
+}
+
+/// Counts down a duration.
+#[derive(Debug, Clone)]
+pub struct Timer {
+    duration: Duration,
+    elapsed: Duration,
+    mode: TimerMode,
+    paused: bool,
+
+    /// Has a one-shot timer finished.
+    finished: bool,
+
+    /// How many times the timer finished during the last tick.
+    times_finished: u32,
+}
+
+impl Timer {
+    /// Creates a new timer.
+    pub fn new(duration: Duration, mode: TimerMode) -> Self {
+        Self {
+            duration,
+            elapsed: Duration::ZERO,
+            mode,
+            paused: false,
+            finished: false,
+            times_finished: 0,
+        }
+    }
+
+    /// Creates a new timer from a duration in seconds. Negative and NaN durations are treated as
+    /// 0, and ones too long for a `Duration` (infinity included) as `Duration::MAX`.
+    pub fn from_seconds(seconds: f64, mode: TimerMode) -> Self {
+        let duration = if seconds.is_nan() || seconds <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+        };
+
+        Self::new(duration, mode)
+    }
+
+    /// Advances the timer by the current `Time::delta`.
+    pub fn update(&mut self, time: &Time) -> &Self {
+        self.tick(time.delta())
+    }
+
+    /// Advances the timer by `delta`.
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        self.times_finished = 0;
+
+        if self.paused || (self.finished && self.mode == TimerMode::Once) {
+            return self;
+        }
+
+        self.elapsed = self.elapsed.saturating_add(delta);
+
+        if self.elapsed < self.duration {
+            return self;
+        }
+
+        match self.mode {
+            TimerMode::Once => {
+                self.elapsed = self.duration;
+                self.finished = true;
+                self.times_finished = 1;
+            },
+            TimerMode::Repeating => {
+                // A zero length repeating timer would finish an infinite number of times.
+                if self.duration.is_zero() {
+                    self.times_finished = 1;
+
+                    return self;
+                }
+
+                let duration = self.duration.as_nanos();
+                let elapsed = self.elapsed.as_nanos();
+
+                self.times_finished = u32::try_from(elapsed / duration).unwrap_or(u32::MAX);
+
+                let remainder = elapsed % duration;
+                self.elapsed = Duration::new((remainder / 1_000_000_000) as u64, (remainder % 1_000_000_000) as u32);
+            },
+        }
+
+        self
+    }
+
+    /// Did the timer finish during the last tick.
+    pub fn just_finished(&self) -> bool {
+        self.times_finished > 0
+    }
+
+    /// How many times the timer finished during the last tick. Can be more than one for a short
+    /// repeating timer.
+    pub fn times_finished(&self) -> u32 {
+        self.times_finished
+    }
+
+    /// Has a one-shot timer finished. Always false for a repeating timer.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Time since the timer started (or last repeated).
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Time until the timer finishes (or next repeats).
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// How far through the timer we are (0 to 1).
+    pub fn fraction(&self) -> f64 {
+        if self.duration.is_zero() {
+            1.0
+        } else {
+            self.elapsed.as_secs_f64() / self.duration.as_secs_f64()
+        }
+    }
+
+    /// Gets the duration.
+    pub fn get_duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Sets the duration. Doesn't reset the timer.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    /// Gets the mode.
+    pub fn get_mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    /// Sets the mode.
+    pub fn set_mode(&mut self, mode: TimerMode) {
+        self.mode = mode;
+    }
+
+    /// Stops the timer from advancing.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Lets the timer advance again.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Is the timer paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Starts the timer over.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+        self.times_finished = 0;
+    }
+}
+
+/// Counts up, forever.
+#[derive(Debug, Clone, Default)]
+pub struct Stopwatch {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Stopwatch {
+    /// Creates a new stopwatch, at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advances the stopwatch by the current `Time::delta`.
+    pub fn update(&mut self, time: &Time) -> &Self {
+        self.tick(time.delta())
+    }
+
+    /// Advances the stopwatch by `delta`.
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if !self.paused {
+            self.elapsed = self.elapsed.saturating_add(delta);
+        }
+
+        self
+    }
+
+    /// Time on the stopwatch.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// `elapsed`, in seconds.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    /// Stops the stopwatch from advancing.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Lets the stopwatch advance again.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Is the stopwatch paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Puts the stopwatch back to zero.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn a_one_shot_timer_finishes_once() {
+        let mut timer = Timer::new(millis(100), TimerMode::Once);
+
+        assert!(!timer.tick(millis(60)).just_finished());
+        assert_eq!(timer.remaining(), millis(40));
+
+        assert!(timer.tick(millis(60)).just_finished());
+        assert!(timer.is_finished());
+        assert_eq!(timer.elapsed(), millis(100));
+        assert_eq!(timer.fraction(), 1.0);
+
+        assert!(!timer.tick(millis(200)).just_finished());
+        assert!(timer.is_finished());
+    }
+
+    #[test]
+    fn a_repeating_timer_counts_every_finish() {
+        let mut timer = Timer::new(millis(100), TimerMode::Repeating);
+
+        assert_eq!(timer.tick(millis(250)).times_finished(), 2);
+        assert_eq!(timer.elapsed(), millis(50));
+        assert!(!timer.is_finished());
+
+        assert_eq!(timer.tick(millis(10)).times_finished(), 0);
+        assert_eq!(timer.tick(millis(40)).times_finished(), 1);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn a_zero_length_repeating_timer_finishes_once_per_tick() {
+        let mut timer = Timer::new(Duration::ZERO, TimerMode::Repeating);
+
+        assert_eq!(timer.tick(millis(10)).times_finished(), 1);
+        assert_eq!(timer.fraction(), 1.0);
+    }
+
+    #[test]
+    fn a_paused_timer_stays_put() {
+        let mut timer = Timer::new(millis(100), TimerMode::Once);
+        timer.pause();
+
+        assert!(!timer.tick(millis(200)).just_finished());
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+
+        timer.resume();
+        assert!(timer.tick(millis(200)).just_finished());
+    }
+
+    #[test]
+    fn resetting_starts_over() {
+        let mut timer = Timer::new(millis(100), TimerMode::Once);
+        timer.tick(millis(150));
+        timer.reset();
+
+        assert!(!timer.is_finished());
+        assert!(!timer.just_finished());
+        assert_eq!(timer.remaining(), millis(100));
+    }
+
+    #[test]
+    fn timers_use_the_step_during_a_step() {
+        let mut time = Time::new(millis(10));
+        time.begin_frame(millis(16));
+
+        let mut timer = Timer::new(millis(100), TimerMode::Once);
+        timer.update(&time);
+        assert_eq!(timer.elapsed(), millis(16));
+
+        time.begin_fixed_step();
+        timer.update(&time);
+        assert_eq!(timer.elapsed(), millis(26));
+    }
+
+    #[test]
+    fn timers_are_made_from_any_number_of_seconds() {
+        assert_eq!(Timer::from_seconds(0.25, TimerMode::Once).get_duration(), millis(250));
+        assert_eq!(Timer::from_seconds(-1.0, TimerMode::Once).get_duration(), Duration::ZERO);
+        assert_eq!(Timer::from_seconds(f64::NAN, TimerMode::Once).get_duration(), Duration::ZERO);
+        assert_eq!(Timer::from_seconds(f64::INFINITY, TimerMode::Once).get_duration(), Duration::MAX);
+    }
+
+    #[test]
+    fn saturated_frames_dont_overflow_timers() {
+        let mut time = Time::new(millis(10));
+        time.set_time_scale(crate::time::MAX_TIME_SCALE);
+        time.begin_frame(Duration::MAX);
+
+        let mut once = Timer::new(millis(100), TimerMode::Once);
+        let mut repeating = Timer::new(millis(100), TimerMode::Repeating);
+        let mut stopwatch = Stopwatch::new();
+
+        for timer in [&mut once, &mut repeating] {
+            timer.tick(millis(50));
+            timer.update(&time);
+        }
+
+        stopwatch.tick(millis(50));
+        stopwatch.update(&time);
+
+        assert!(once.is_finished());
+        assert_eq!(repeating.times_finished(), u32::MAX);
+        assert!(repeating.elapsed() < millis(100));
+        assert_eq!(stopwatch.elapsed(), Duration::MAX);
+    }
+
+    #[test]
+    fn stopwatches_count_up_unless_paused() {
+        let mut stopwatch = Stopwatch::new();
+
+        stopwatch.tick(millis(100));
+        stopwatch.tick(millis(50));
+        assert_eq!(stopwatch.elapsed(), millis(150));
+
+        stopwatch.pause();
+        stopwatch.tick(millis(100));
+        assert_eq!(stopwatch.elapsed(), millis(150));
+
+        stopwatch.resume();
+        stopwatch.tick(millis(100));
+        assert_eq!(stopwatch.elapsed_seconds(), 0.25);
+
+        stopwatch.reset();
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+    }
+}
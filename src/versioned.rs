@@ -0,0 +1,49 @@
+//! Versioned file formats.
+//!
+//! Binary files (input recordings, saves, packs) start with 8 magic bytes and then the format
+//! version, as a little endian `u32`. Text files (scenes, saves written as RON) have a `version`
+//! field instead. Either way, versions newer than the engine knows about are refused, rather than
+//! half read.
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+use std::convert::TryInto;
+
+/// How long the header of a binary file is.
+pub(crate) const HEADER_SIZE: usize = 8 + 4;
+
+/// Starts a binary file with its header.
+pub(crate) fn write_header(magic: &[u8; 8], version: u32) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.extend_from_slice(&version.to_le_bytes());
+
+    bytes
+}
+
+/// Reads the header of a binary file, handing back its format version and everything after it.
+/// `what` the file is (`"save"`, say) goes in the error message.
+pub(crate) fn read_header<'a>(bytes: &'a [u8], magic: &[u8; 8], what: &str, newest: u32) -> Result<(u32, &'a [u8]), String> {
+    if bytes.len() < HEADER_SIZE || &bytes[..magic.len()] != magic {
+        return Err(format!("not a {} file", what));
+    }
+
+    let (version, rest) = bytes[magic.len()..].split_at(4);
+    let version = check_version(u32::from_le_bytes(version.try_into().expect("4 bytes")), what, newest)?;
+
+    Ok((version, rest))
+}
+
+/// Refuses versions newer than `newest`.
+pub(crate) fn check_version(version: u32, what: &str, newest: u32) -> Result<u32, String> {
+    if version > newest {
+        return Err(format!("{} format version {} is newer than this engine supports ({})", what, version, newest));
+    }
+
+    Ok(version)
+}
+
+/// Reads a `version` field, refusing versions newer than `newest` while the parser still knows
+/// where it is. Wrap it in a function for `#[serde(deserialize_with)]`.
+pub(crate) fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D, what: &str, newest: u32) -> Result<u32, D::Error> {
+    check_version(u32::deserialize(deserializer)?, what, newest).map_err(D::Error::custom)
+}
@@ -0,0 +1,104 @@
+//! Dead simple file watching.
+//!
+//! Polls the modification time of every watched file and reports the ones that changed since the
+//! last poll. Not the most efficient thing in the world, but it works everywhere (even in CI
+//! containers with no inotify), and we only ever watch a handful of files during development.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use log::debug;
+
+/// Watches a set of files for modifications.
+#[derive(Debug)]
+pub struct FileWatcher {
+    /// Every watched file, with the modification time we last saw.
+    files: HashMap<PathBuf, Option<SystemTime>>,
+
+    /// How long to wait between polls. Stat-ing files every frame is wasteful.
+    interval: Duration,
+
+    /// When we last polled the filesystem.
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    /// Creates a new watcher that polls (at most) once every quarter of a second.
+    pub fn new() -> Self {
+        Self::with_interval(Duration::from_millis(250))
+    }
+
+    /// Creates a new watcher that polls (at most) once every `interval`.
+    pub fn with_interval(interval: Duration) -> Self {
+        Self {
+            files: HashMap::new(),
+            interval,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Starts watching a file. Watching the same file twice does nothing.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path);
+
+        self.files.entry(path).or_insert(modified);
+    }
+
+    /// Stops watching a file.
+    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) {
+        self.files.remove(path.as_ref());
+    }
+
+    /// Is the file being watched?
+    pub fn is_watching<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.files.contains_key(path.as_ref())
+    }
+
+    /// Returns every watched file that has changed since the last poll. Returns nothing if the
+    /// poll interval hasn't passed yet.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return vec![];
+        }
+
+        self.last_poll = Instant::now();
+
+        let mut changed = vec![];
+
+        for (path, last) in self.files.iter_mut() {
+            let modified = modified_time(path);
+
+            // A file that vanished is probably mid-save (a lot of editors write a new file and
+            // then rename it), so wait for it to come back rather than reporting it.
+            if modified.is_some() && modified != *last {
+                changed.push(path.clone());
+            }
+
+            if modified.is_some() {
+                *last = modified;
+            }
+        }
+
+        changed
+    }
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gets the modification time of a file, if it exists.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    match std::fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(time) => Some(time),
+        Err(e) => {
+            debug!("Failed to stat watched file {}: {}", path.display(), e);
+
+            None
+        }
+    }
+}